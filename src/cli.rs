@@ -1,12 +1,120 @@
 use std::path::PathBuf;
 
 use clap::{Parser, ValueEnum};
+use log::LevelFilter;
+use serde::Deserialize;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize)]
 #[value(rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
 pub enum OutputFormat {
     Text,
     Json,
+    #[value(name = "json-array")]
+    #[serde(rename = "json-array")]
+    JsonArray,
+    Yaml,
+    Csv,
+    /// Tab-separated rows with backslash escapes instead of quoting.
+    Tsv,
+}
+
+/// Character encoding of text-format output.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[value(rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum OutputEncoding {
+    #[default]
+    Utf8,
+    /// Little-endian UTF-16, with a byte order mark at the start of a new output.
+    Utf16le,
+}
+
+/// Shape of the log records written to stderr.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[value(rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// `env_logger`'s human-readable lines.
+    #[default]
+    Human,
+    /// One JSON object per record with `timestamp`, `level`, `target` and `message`.
+    Json,
+}
+
+/// Which tag field supplies the artist that filters are matched against.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[value(rename_all = "kebab-case")]
+#[serde(rename_all = "kebab-case")]
+pub enum ArtistSource {
+    /// The track artist, falling back to the album artist when there is none.
+    #[default]
+    Artist,
+    /// Only the album artist.
+    AlbumArtist,
+    /// The album artist, falling back to the track artist when there is none.
+    PreferAlbumArtist,
+}
+
+impl ArtistSource {
+    /// The artist frames this source consults, in order.
+    pub fn frames(self) -> &'static [ArtistFrame] {
+        match self {
+            Self::Artist => &[ArtistFrame::Artist, ArtistFrame::AlbumArtist],
+            Self::AlbumArtist => &[ArtistFrame::AlbumArtist],
+            Self::PreferAlbumArtist => &[ArtistFrame::AlbumArtist, ArtistFrame::Artist],
+        }
+    }
+}
+
+/// A tag field that can supply the artist, for `--artist-frames`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize)]
+#[value(rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum ArtistFrame {
+    /// ID3 `TPE1`, MP4 `©ART`, Vorbis `ARTIST`.
+    Artist,
+    /// ID3 `TPE2`, MP4 `aART`, Vorbis `ALBUMARTIST`.
+    AlbumArtist,
+    /// ID3 `TCOM`, MP4 `©wrt`, Vorbis `COMPOSER`.
+    Composer,
+    /// ID3 `TOPE`, Vorbis `ORIGINALARTIST`; MP4 has no such atom.
+    OriginalArtist,
+}
+
+/// Content hash computed for each matched file under `--hash`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[value(rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum HashAlgorithm {
+    #[default]
+    None,
+    Md5,
+    Blake3,
+}
+
+/// Most verbose log level to emit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize)]
+#[value(rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl From<LogLevel> for LevelFilter {
+    fn from(level: LogLevel) -> Self {
+        match level {
+            LogLevel::Error => LevelFilter::Error,
+            LogLevel::Warn => LevelFilter::Warn,
+            LogLevel::Info => LevelFilter::Info,
+            LogLevel::Debug => LevelFilter::Debug,
+            LogLevel::Trace => LevelFilter::Trace,
+        }
+    }
 }
 
 /// Command-line options for mdlyricgetter.
@@ -14,48 +122,400 @@ pub enum OutputFormat {
 #[command(
     author,
     version,
-    about = "Scan MP3 files and extract lyrics when the artist matches a filter."
+    about = "Scan audio files and extract lyrics when the artist matches a filter."
 )]
 pub struct CliArgs {
-    /// Root directory to scan; defaults to current working directory.
+    /// Read options from this TOML file instead of `mdlyricgetter.toml` in the root.
+    #[arg(long)]
+    pub config: Option<PathBuf>,
+
+    /// Root directory to scan; repeat to scan several. Defaults to current working directory.
     #[arg(long)]
-    pub root: Option<PathBuf>,
+    pub root: Vec<PathBuf>,
 
-    /// Output file to append lyrics to; defaults to lyrics.txt in the working directory.
+    /// Output file to append lyrics to, or `-` for stdout; defaults to lyrics.txt in the working directory.
     #[arg(long)]
     pub output: Option<PathBuf>,
 
+    /// Write each track's lyrics to `<stem>.lrc`/`<stem>.txt` next to its audio file instead of --output.
+    #[arg(long, default_value_t = false)]
+    pub split_output: bool,
+
+    /// Write each artist's tracks to its own file in DIR (`<artist>.<ext>`) instead of --output.
+    #[arg(
+        long,
+        value_name = "DIR",
+        conflicts_with_all = ["output", "split_output", "atomic"]
+    )]
+    pub split_by_artist: Option<PathBuf>,
+
+    /// Write each source directory's tracks to `<DIR>/<relative dir>/lyrics.<ext>`, mirroring the
+    /// scanned tree, instead of --output.
+    #[arg(
+        long,
+        value_name = "DIR",
+        conflicts_with_all = ["output", "split_output", "split_by_artist", "atomic"]
+    )]
+    pub mirror_output: Option<PathBuf>,
+
+    /// Truncate the output file at startup instead of appending to it.
+    #[arg(long, default_value_t = false)]
+    pub overwrite: bool,
+
+    /// Write the output to a temp file and rename it over --output only when the scan finishes;
+    /// replaces the file like --overwrite rather than appending.
+    #[arg(long, default_value_t = false)]
+    pub atomic: bool,
+
     /// When set, perform the scan without writing to the output file.
     #[arg(long, default_value_t = false)]
     pub dry_run: bool,
 
-    /// Case-insensitive substring to look for within the artist name.
-    #[arg(long, default_value = crate::metadata::DEFAULT_ARTIST_FILTER)]
-    pub artist_filter: String,
+    /// Only count the tracks that pass the artist and tag filters, without reading lyrics or
+    /// writing any output.
+    #[arg(long, conflicts_with_all = ["dry_run_show", "interactive"])]
+    pub count_only: bool,
+
+    /// Like --dry-run, but print each entry that would be written to stderr, prefixed with
+    /// `[dry-run]`.
+    #[arg(long, default_value_t = false)]
+    pub dry_run_show: bool,
+
+    /// Case-insensitive substring to look for within the artist name [default: udio].
+    #[arg(long)]
+    pub artist_filter: Option<String>,
+
+    /// Regular expression the artist name must match instead of --artist-filter; case-insensitive
+    /// unless the pattern sets its own flags (e.g. `(?-i)`).
+    #[arg(long, conflicts_with = "artist_filter")]
+    pub artist_regex: Option<String>,
+
+    /// Comma-separated substrings; a track matches when its artist contains any of them. Replaces
+    /// --artist-filter for matching several artists in one pass.
+    #[arg(
+        long,
+        value_name = "LIST",
+        value_delimiter = ',',
+        conflicts_with_all = ["artist_filter", "artist_regex", "artist_fuzzy"]
+    )]
+    pub artist_filter_any: Vec<String>,
+
+    /// Skip tracks whose artist contains this substring even when the artist filter matched;
+    /// repeatable.
+    #[arg(long = "artist-exclude", value_name = "SUBSTR")]
+    pub artist_exclude: Vec<String>,
+
+    /// Match the whole artist name against --artist-filter by Jaro-Winkler similarity, accepting
+    /// scores of at least THRESHOLD (0.0-1.0) to catch typos and stylized spellings.
+    #[arg(long, value_name = "THRESHOLD", conflicts_with = "artist_regex")]
+    pub artist_fuzzy: Option<f64>,
+
+    /// Ignore accents when matching --artist-filter / --artist-filter-any, so `Beyonce` matches
+    /// `Beyoncé`.
+    #[arg(
+        long,
+        default_value_t = false,
+        conflicts_with_all = ["artist_regex", "artist_fuzzy"]
+    )]
+    pub ascii_fold: bool,
+
+    /// Tag field to read the artist from [default: artist].
+    #[arg(long, value_enum)]
+    pub artist_source: Option<ArtistSource>,
+
+    /// Comma-separated tag fields to match the artist against, first matching one wins, e.g.
+    /// `artist,album_artist,composer,original_artist`. Replaces --artist-source.
+    #[arg(
+        long,
+        value_enum,
+        value_name = "LIST",
+        value_delimiter = ',',
+        conflicts_with = "artist_source"
+    )]
+    pub artist_frames: Vec<ArtistFrame>,
+
+    /// Match --artist-filter / --artist-filter-any / --artist-regex against the artist with exact
+    /// casing.
+    #[arg(long, default_value_t = false)]
+    pub case_sensitive: bool,
+
+    /// Case-insensitive substring to look for within the track title.
+    #[arg(long)]
+    pub title_filter: Option<String>,
+
+    /// Skip tracks released before this year; tracks without a year are skipped too.
+    #[arg(long)]
+    pub year_min: Option<i32>,
+
+    /// Skip tracks released after this year; tracks without a year are skipped too.
+    #[arg(long)]
+    pub year_max: Option<i32>,
+
+    /// Skip tracks shorter than this many seconds; tracks of unknown length are kept.
+    #[arg(long, value_name = "SECS")]
+    pub min_duration: Option<u64>,
+
+    /// Skip tracks longer than this many seconds; tracks of unknown length are kept.
+    #[arg(long, value_name = "SECS")]
+    pub max_duration: Option<u64>,
+
+    /// Case-insensitive substring to look for within the genre.
+    #[arg(long)]
+    pub genre: Option<String>,
+
+    /// Only collect USLT lyrics in this language code (e.g. `eng`), falling back to every language
+    /// when no frame matches.
+    #[arg(long, value_name = "CODE")]
+    pub lyrics_lang: Option<String>,
+
+    /// TXXX description to read lyrics from, case-insensitively; repeat for several. Replaces the
+    /// defaults: lyrics, unsynced lyrics, unsyncedlyrics and lyric.
+    #[arg(long = "lyrics-tag", value_name = "NAME")]
+    pub lyrics_tags: Vec<String>,
+
+    /// Keep lyrics in different languages apart, each under an `[eng]`-style header line,
+    /// instead of merging them.
+    #[arg(long, default_value_t = false)]
+    pub by_language: bool,
+
+    /// Write synchronised (SYLT) lyrics as `[mm:ss.xx]` LRC lines instead of stripping the timing.
+    #[arg(long, default_value_t = false)]
+    pub keep_timestamps: bool,
+
+    /// Remove leading `[mm:ss.xx]` timestamps and `[ar:...]`-style header lines from embedded
+    /// LRC-formatted lyrics.
+    #[arg(long, default_value_t = false, conflicts_with = "keep_timestamps")]
+    pub strip_lrc_timestamps: bool,
+
+    /// When no lyrics frame is found, use ID3 comments (COMM) with an empty description as lyrics.
+    #[arg(long, default_value_t = false)]
+    pub loose_comments: bool,
+
+    /// Add duration, bitrate and sample rate of matched MP3s to JSON and YAML records.
+    #[arg(long, default_value_t = false)]
+    pub audio_properties: bool,
+
+    /// Hash the audio of each matched file, without its tags, adding a `hash` field to JSON
+    /// and YAML records; with --dedupe, files with the same hash are also duplicates
+    /// [default: none].
+    #[arg(long, value_enum, value_name = "ALGORITHM")]
+    pub hash: Option<HashAlgorithm>,
+
+    /// Ask on the terminal before writing each match; declined tracks are skipped.
+    #[arg(long)]
+    pub interactive: bool,
+
+    /// Log the first N lines of each matched track's lyrics at info level; 0 disables it.
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    pub preview: usize,
 
-    /// Comma-separated list of file extensions to scan (case-insensitive).
-    #[arg(long, default_value = "mp3")]
-    pub extensions: String,
+    /// Retry reading a tag up to N times, with a short backoff, when it fails with a transient I/O
+    /// error such as a timeout on a network mount.
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    pub read_retries: u32,
 
-    /// Output formatting strategy for matched tracks.
-    #[arg(long, value_enum, default_value = "text")]
-    pub format: OutputFormat,
+    /// Skip files smaller than this many bytes (e.g. empty partial downloads) without reading
+    /// their tags [default: 0, keeping every file].
+    #[arg(long, value_name = "BYTES")]
+    pub min_size: Option<u64>,
+
+    /// Collapse runs of blank lines inside lyrics into a single blank line.
+    #[arg(long, default_value_t = false)]
+    pub collapse_blank_lines: bool,
+
+    /// Drop every blank line inside lyrics, including those between frames, so they come out
+    /// single-spaced.
+    #[arg(long, default_value_t = false)]
+    pub flatten: bool,
+
+    /// When a track has no embedded lyrics, read them from `<stem>.lrc` or `<stem>.txt` beside it.
+    #[arg(long, default_value_t = false)]
+    pub sidecar_lyrics: bool,
+
+    /// Also write matching tracks that have no lyrics, with empty lyrics (`(no lyrics)` in text).
+    #[arg(long, default_value_t = false)]
+    pub include_empty: bool,
+
+    /// Pipe each matched track's lyrics through this shell command and write its stdout instead;
+    /// the original lyrics are kept when it fails. Starts one process per track.
+    #[arg(long, value_name = "COMMAND")]
+    pub filter_cmd: Option<String>,
+
+    /// Cut lyrics longer than this many characters, ending them with `…` and logging a warning.
+    #[arg(long, value_name = "N")]
+    pub max_lyric_chars: Option<usize>,
+
+    /// Add a `source` field to JSON and YAML records naming the frame kinds the lyrics came from.
+    #[arg(long, default_value_t = false)]
+    pub lyrics_source: bool,
+
+    /// Comma-separated list of file extensions to scan (case-insensitive) [default: mp3].
+    #[arg(long)]
+    pub extensions: Option<String>,
+
+    /// Try to read tags from every file regardless of extension; files that are not audio are
+    /// counted as tag errors. Slow on trees with many non-audio files.
+    #[arg(long, default_value_t = false, conflicts_with = "extensions")]
+    pub all_extensions: bool,
+
+    /// Process the paths listed in this file (one per line, `-` for stdin) instead of walking
+    /// the root.
+    #[arg(long, value_name = "LIST")]
+    pub from_file: Option<PathBuf>,
+
+    /// Process NUL-separated paths streamed on stdin (e.g. from `find -print0`) instead of walking
+    /// the root.
+    #[arg(long, default_value_t = false, conflicts_with = "from_file")]
+    pub stdin0: bool,
+
+    /// Process only the files under the root that git reports as changed since this ref (e.g.
+    /// `HEAD~3` or a tag) instead of walking the root.
+    #[arg(long, value_name = "REF", conflicts_with_all = ["from_file", "stdin0"])]
+    pub git_since: Option<String>,
+
+    /// Skip files and directories whose path or name matches this glob; repeatable.
+    #[arg(long = "exclude", value_name = "GLOB")]
+    pub exclude: Vec<String>,
+
+    /// Only scan files whose path or name matches at least one of these globs; repeatable.
+    #[arg(long = "include", value_name = "GLOB")]
+    pub include: Vec<String>,
+
+    /// Only scan files modified after this RFC 3339 timestamp or relative age (e.g. `7d`, `24h`).
+    #[arg(long, value_name = "WHEN")]
+    pub modified_since: Option<String>,
+
+    /// Output formatting strategy for matched tracks [default: text].
+    #[arg(long, value_enum)]
+    pub format: Option<OutputFormat>,
+
+    /// Custom text entry with `{title}`, `{artist}`, `{album}`, `{lyrics}` and `{path}`
+    /// placeholders, replacing the default block (text format only).
+    #[arg(long, value_name = "TEMPLATE")]
+    pub template: Option<String>,
+
+    /// Character encoding of the output file [default: utf8]; utf16le is text format only.
+    #[arg(long, value_enum)]
+    pub output_encoding: Option<OutputEncoding>,
+
+    /// Only scan files at or below this depth (1 means files directly in the root).
+    #[arg(long)]
+    pub min_depth: Option<usize>,
 
     /// Limit recursion depth when scanning (0 means root only).
     #[arg(long)]
     pub max_depth: Option<usize>,
 
+    /// Only scan files directly in the root, without descending into subdirectories.
+    #[arg(long, default_value_t = false, conflicts_with = "max_depth")]
+    pub no_recurse: bool,
+
     /// Follow directory symlinks while scanning.
     #[arg(long, default_value_t = false)]
     pub follow_symlinks: bool,
 
-    /// Write a JSON summary report to the specified file.
+    /// JSON manifest of processed files; files whose mtime and size match it are skipped, and it
+    /// is rewritten after the run.
+    #[arg(long, value_name = "FILE")]
+    pub state: Option<PathBuf>,
+
+    /// Write the source path of every matched track to this file, one per line.
+    #[arg(long, value_name = "FILE")]
+    pub matched_list: Option<PathBuf>,
+
+    /// Write every traversal and tag error as a JSON array of `{path, kind, message}` objects.
+    #[arg(long, value_name = "FILE")]
+    pub errors_json: Option<PathBuf>,
+
+    /// Pretty-print each JSON record over several lines. Implies --format json-array, since the
+    /// records would no longer be one per line.
+    #[arg(long, default_value_t = false)]
+    pub json_pretty: bool,
+
+    /// Write paths in the summary, error report and JSON/YAML records relative to the scan root.
+    #[arg(long, default_value_t = false)]
+    pub relative_paths: bool,
+
+    /// Write a JSON summary report to the specified file, or to stdout when given `-`.
     #[arg(long)]
     pub summary_json: Option<PathBuf>,
 
-    /// Reduce log verbosity to errors only.
+    /// Insert the current UTC date and time into the output and summary file names, e.g.
+    /// `lyrics-2024-06-01T12-00-00.txt`, so each run gets its own files.
+    #[arg(long, default_value_t = false)]
+    pub timestamp_output: bool,
+
+    /// Write only the first track for each (artist, title) pair seen during the run.
     #[arg(long, default_value_t = false)]
+    pub dedupe: bool,
+
+    /// Read the existing --output first and don't write tracks whose (artist, title) it already
+    /// holds; text and json output only.
+    #[arg(long, default_value_t = false, conflicts_with_all = ["overwrite", "atomic"])]
+    pub skip_existing: bool,
+
+    /// Don't write tracks whose (artist, title) is listed in this reference file: earlier text
+    /// output, or JSON lines when it ends in .json, .jsonl or .ndjson.
+    #[arg(long, value_name = "FILE")]
+    pub exclude_from: Option<PathBuf>,
+
+    /// Stop scanning once this many tracks have been written.
+    #[arg(long)]
+    pub limit: Option<usize>,
+
+    /// Stop scanning once this many files have been examined, matched or not.
+    #[arg(long, value_name = "N")]
+    pub max_files: Option<usize>,
+
+    /// Exit with status 2 when the scan succeeds but no track matched.
+    #[arg(long, default_value_t = false)]
+    pub fail_on_empty: bool,
+
+    /// Exit with status 3 when the scan finishes but some files or directories could not be read.
+    #[arg(long, default_value_t = false)]
+    pub strict: bool,
+
+    /// Log every distinct artist that matched once the scan finishes.
+    #[arg(long, default_value_t = false)]
+    pub list_artists: bool,
+
+    /// Print `matched=<n>` as the last line on stdout, even with --quiet.
+    #[arg(long, default_value_t = false)]
+    pub print_count: bool,
+
+    /// Number of worker threads reading tags; defaults to the number of logical CPUs.
+    #[arg(long)]
+    pub jobs: Option<usize>,
+
+    /// Most scanned files waiting to be written before workers pause (ignored with --jobs 1)
+    /// [default: 1024].
+    #[arg(long, value_name = "N")]
+    pub queue_size: Option<usize>,
+
+    /// Show a spinner with scanned/matched counts on stderr (ignored with --quiet or when stderr is not a terminal).
+    #[arg(long, default_value_t = false)]
+    pub progress: bool,
+
+    /// Log record format on stderr [default: human].
+    #[arg(long, value_enum)]
+    pub log_format: Option<LogFormat>,
+
+    /// Also append every log record to this file; with --quiet, stderr only shows errors while
+    /// the file still gets the info records.
+    #[arg(long, value_name = "PATH")]
+    pub log_file: Option<PathBuf>,
+
+    /// Reduce log verbosity to errors only.
+    #[arg(long, default_value_t = false, conflicts_with_all = ["verbose", "log_level"])]
     pub quiet: bool,
+
+    /// Raise log verbosity: `-v` for debug (including why files are skipped), `-vv` for trace.
+    #[arg(short, long, action = clap::ArgAction::Count)]
+    pub verbose: u8,
+
+    /// Log level to use instead of the -v / --quiet shortcuts; overrides RUST_LOG.
+    #[arg(long, value_enum, value_name = "LEVEL")]
+    pub log_level: Option<LogLevel>,
 }
 
 impl CliArgs {
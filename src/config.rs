@@ -1,64 +1,870 @@
+use std::collections::HashSet;
+use std::io::{self, IsTerminal};
 use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 
 use anyhow::{Context, Result};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use log::LevelFilter;
+use regex::{Regex, RegexBuilder};
+use serde::Deserialize;
 
-use crate::cli::{CliArgs, OutputFormat};
+use crate::cli::{
+    ArtistFrame, ArtistSource, CliArgs, HashAlgorithm, LogFormat, LogLevel, OutputEncoding,
+    OutputFormat,
+};
+use crate::metadata::{ArtistMatcher, Filters, DEFAULT_ARTIST_FILTER, DEFAULT_LYRICS_TAGS};
+use crate::scanner;
+use crate::template::OutputTemplate;
+use crate::writer::{self, STDOUT_SENTINEL};
+
+pub const CONFIG_FILE_NAME: &str = "mdlyricgetter.toml";
 
 #[derive(Debug, Clone)]
 pub struct Config {
-    pub root: PathBuf,
+    /// Every root to scan, in command-line order. Relative output paths resolve against the
+    /// first, and only the first is searched for `mdlyricgetter.toml`.
+    pub roots: Vec<PathBuf>,
     pub output: PathBuf,
+    pub split_output: bool,
+    pub split_by_artist: Option<PathBuf>,
+    pub mirror_output: Option<PathBuf>,
+    pub overwrite: bool,
+    pub atomic: bool,
     pub dry_run: bool,
+    /// Print would-be output to stderr during a dry run; implies `dry_run`.
+    pub dry_run_show: bool,
     pub artist_filter: String,
+    /// Non-empty needles from `--artist-filter-any`; when present they replace `artist_filter`.
+    pub artist_filter_any: Vec<String>,
+    /// Non-empty needles from `--artist-exclude`.
+    pub artist_exclude: Vec<String>,
+    pub artist_regex: Option<Regex>,
+    pub artist_fuzzy: Option<f64>,
+    pub case_sensitive: bool,
+    pub ascii_fold: bool,
+    pub artist_source: ArtistSource,
+    /// `--artist-frames`; empty to follow `artist_source`.
+    pub artist_frames: Vec<ArtistFrame>,
+    pub title_filter: Option<String>,
+    pub year_min: Option<i32>,
+    pub year_max: Option<i32>,
+    pub min_duration: Option<u64>,
+    pub max_duration: Option<u64>,
+    pub genre: Option<String>,
+    pub lyrics_lang: Option<String>,
+    pub by_language: bool,
+    pub lyrics_tags: Vec<String>,
+    pub keep_timestamps: bool,
+    pub strip_lrc_timestamps: bool,
+    pub loose_comments: bool,
+    pub audio_properties: bool,
+    pub read_retries: u32,
+    pub preview: usize,
+    pub interactive: bool,
+    pub hash: HashAlgorithm,
+    pub min_size: u64,
+    pub collapse_blank_lines: bool,
+    pub flatten: bool,
+    pub sidecar_lyrics: bool,
+    pub include_empty: bool,
+    pub count_only: bool,
+    pub filter_cmd: Option<String>,
+    pub lyrics_source: bool,
+    pub max_lyric_chars: Option<usize>,
     pub extensions: Vec<String>,
+    /// `--from-file` list replacing the directory walk; `-` reads it from stdin.
+    pub from_file: Option<PathBuf>,
+    pub stdin0: bool,
+    pub git_since: Option<String>,
+    pub exclude: GlobSet,
+    pub include: GlobSet,
+    pub modified_since: Option<SystemTime>,
     pub output_format: OutputFormat,
+    pub template: Option<OutputTemplate>,
+    pub output_encoding: OutputEncoding,
+    pub min_depth: Option<usize>,
     pub max_depth: Option<usize>,
+    /// `max_depth` came from `--no-recurse`, so skipped subdirectories are expected, not news.
+    pub no_recurse: bool,
     pub follow_symlinks: bool,
     pub summary_json: Option<PathBuf>,
+    pub relative_paths: bool,
+    pub json_pretty: bool,
+    pub errors_json: Option<PathBuf>,
+    pub matched_list: Option<PathBuf>,
+    pub state: Option<PathBuf>,
+    pub dedupe: bool,
+    pub skip_existing: bool,
+    /// Tracks listed in the `--exclude-from` reference, read when the config is built.
+    pub excluded_tracks: Option<HashSet<(String, String)>>,
+    pub limit: Option<usize>,
+    pub max_files: Option<usize>,
+    pub fail_on_empty: bool,
+    pub strict: bool,
+    pub list_artists: bool,
+    pub print_count: bool,
+    pub jobs: usize,
+    pub queue_size: usize,
+    pub progress: bool,
+    pub log_format: LogFormat,
+    pub log_file: Option<PathBuf>,
     pub quiet: bool,
+    /// Level requested through --quiet, -v or --log-level; `None` leaves it to `RUST_LOG`,
+    /// defaulting to info.
+    pub log_level: Option<LevelFilter>,
+}
+
+/// Defaults for building a `Config` in code: scan `.` into `lyrics.txt` with the built-in
+/// artist filter on a single thread. Paths are used as given, without `from_args` resolution.
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            roots: vec![PathBuf::from(".")],
+            output: PathBuf::from("lyrics.txt"),
+            split_output: false,
+            split_by_artist: None,
+            mirror_output: None,
+            overwrite: false,
+            atomic: false,
+            dry_run: false,
+            dry_run_show: false,
+            artist_filter: DEFAULT_ARTIST_FILTER.to_string(),
+            artist_filter_any: Vec::new(),
+            artist_exclude: Vec::new(),
+            artist_regex: None,
+            artist_fuzzy: None,
+            case_sensitive: false,
+            ascii_fold: false,
+            artist_source: ArtistSource::Artist,
+            artist_frames: Vec::new(),
+            title_filter: None,
+            year_min: None,
+            year_max: None,
+            min_duration: None,
+            max_duration: None,
+            genre: None,
+            lyrics_lang: None,
+            by_language: false,
+            lyrics_tags: DEFAULT_LYRICS_TAGS.map(str::to_owned).to_vec(),
+            keep_timestamps: false,
+            strip_lrc_timestamps: false,
+            loose_comments: false,
+            audio_properties: false,
+            read_retries: 0,
+            preview: 0,
+            interactive: false,
+            hash: HashAlgorithm::None,
+            min_size: 0,
+            collapse_blank_lines: false,
+            flatten: false,
+            sidecar_lyrics: false,
+            include_empty: false,
+            count_only: false,
+            filter_cmd: None,
+            lyrics_source: false,
+            max_lyric_chars: None,
+            extensions: vec!["mp3".to_string()],
+            from_file: None,
+            stdin0: false,
+            git_since: None,
+            exclude: GlobSet::empty(),
+            include: GlobSet::empty(),
+            modified_since: None,
+            output_format: OutputFormat::Text,
+            template: None,
+            output_encoding: OutputEncoding::Utf8,
+            min_depth: None,
+            max_depth: None,
+            no_recurse: false,
+            follow_symlinks: false,
+            summary_json: None,
+            relative_paths: false,
+            json_pretty: false,
+            errors_json: None,
+            matched_list: None,
+            state: None,
+            dedupe: false,
+            skip_existing: false,
+            excluded_tracks: None,
+            limit: None,
+            max_files: None,
+            fail_on_empty: false,
+            strict: false,
+            list_artists: false,
+            print_count: false,
+            jobs: 1,
+            queue_size: DEFAULT_QUEUE_SIZE,
+            progress: false,
+            log_format: LogFormat::Human,
+            log_file: None,
+            quiet: false,
+            log_level: None,
+        }
+    }
 }
 
 impl Config {
-    pub fn from_args(args: CliArgs) -> Result<Self> {
-        let root = normalize_root(args.root)?;
-        let output = normalize_output(&root, args.output)?;
-        let summary_json = args.summary_json.map(|path| make_absolute(&root, path));
-        let extensions = parse_extensions(args.extensions);
+    pub fn from_args(mut args: CliArgs) -> Result<Self> {
+        let explicit_config = args.config.take();
+        if let Some(path) = &explicit_config {
+            let path = absolutize(path)?;
+            let file = FileConfig::load(&path)?;
+            let base = path.parent().unwrap_or(Path::new("/"));
+            file.apply_to(&mut args, Some(base))?;
+        }
+
+        let roots = normalize_roots(std::mem::take(&mut args.root))?;
+        let root = roots[0].clone();
+        let discovered = root.join(CONFIG_FILE_NAME);
+        if explicit_config.is_none() && discovered.is_file() {
+            FileConfig::load(&discovered)?.apply_to(&mut args, None)?;
+        }
+
+        let stamp = args
+            .timestamp_output
+            .then(|| output_timestamp(SystemTime::now()));
+        let mut output = normalize_output(&root, args.output)?;
+        let mut summary_json = args.summary_json.map(|path| {
+            if path == Path::new(STDOUT_SENTINEL) {
+                path
+            } else {
+                make_absolute(&root, path)
+            }
+        });
+        if let Some(stamp) = &stamp {
+            for path in [Some(&mut output), summary_json.as_mut()]
+                .into_iter()
+                .flatten()
+            {
+                if path != Path::new(STDOUT_SENTINEL) {
+                    *path = with_timestamp(path, stamp);
+                }
+            }
+        }
+        anyhow::ensure!(
+            !(args.include_empty && args.split_output),
+            "--include-empty cannot be combined with --split-output, which would write empty lyrics files."
+        );
+        let split_by_artist = args.split_by_artist.map(|path| make_absolute(&root, path));
+        anyhow::ensure!(
+            split_by_artist.is_none() || !(args.split_output || args.atomic),
+            "--split-by-artist cannot be combined with --split-output or --atomic."
+        );
+        let mirror_output = args.mirror_output.map(|path| make_absolute(&root, path));
+        anyhow::ensure!(
+            mirror_output.is_none()
+                || !(args.split_output || args.atomic || split_by_artist.is_some()),
+            "--mirror-output cannot be combined with --split-output, --split-by-artist or --atomic."
+        );
+        let output_to_stdout = output == Path::new(STDOUT_SENTINEL)
+            && !args.split_output
+            && split_by_artist.is_none()
+            && mirror_output.is_none()
+            && !(args.dry_run || args.dry_run_show || args.count_only);
+        anyhow::ensure!(
+            !(output_to_stdout && summary_json.as_deref() == Some(Path::new(STDOUT_SENTINEL))),
+            "--summary-json - cannot be combined with --output -; both would write to stdout."
+        );
+        let state = args.state.map(|path| make_absolute(&root, path));
+        let matched_list = args.matched_list.map(|path| make_absolute(&root, path));
+        let log_file = args.log_file.map(|path| make_absolute(&root, path));
+        let from_file = args.from_file.map(|path| {
+            if path == Path::new(STDOUT_SENTINEL) {
+                path
+            } else {
+                make_absolute(&root, path)
+            }
+        });
+        anyhow::ensure!(
+            !(args.stdin0 && from_file.is_some()),
+            "--stdin0 cannot be combined with --from-file."
+        );
+        anyhow::ensure!(
+            args.git_since.is_none() || !(args.stdin0 || from_file.is_some()),
+            "--git-since cannot be combined with --from-file or --stdin0."
+        );
+        anyhow::ensure!(
+            !args
+                .git_since
+                .as_deref()
+                .is_some_and(|rev| rev.is_empty() || rev.starts_with('-')),
+            "--git-since must name a git ref, not an empty string or an option."
+        );
+        let extensions = if args.all_extensions {
+            vec![scanner::ALL_EXTENSIONS.to_string()]
+        } else {
+            parse_extensions(args.extensions.unwrap_or_else(|| "mp3".to_string()))
+        };
+        let exclude = compile_globs(&args.exclude, "--exclude")?;
+        let include = compile_globs(&args.include, "--include")?;
+        let modified_since = args
+            .modified_since
+            .as_deref()
+            .map(|raw| parse_modified_since(raw, SystemTime::now()))
+            .transpose()?;
+        // Files directly in the root are at depth 1, so that is as deep as `--no-recurse` goes.
+        if args.no_recurse {
+            args.max_depth = Some(1);
+        }
+        validate_depths(args.min_depth, args.max_depth)?;
+        let output_format = match (args.format, args.json_pretty) {
+            (Some(format), _) => format,
+            (None, true) => OutputFormat::JsonArray,
+            (None, false) => OutputFormat::Text,
+        };
+        anyhow::ensure!(
+            !args.json_pretty || output_format == OutputFormat::JsonArray,
+            "--json-pretty only applies to --format json-array; pretty records would break the one-record-per-line contract of --format json."
+        );
+        let template = args
+            .template
+            .as_deref()
+            .map(OutputTemplate::parse)
+            .transpose()?;
+        anyhow::ensure!(
+            template.is_none() || output_format == OutputFormat::Text,
+            "--template only applies to --format text."
+        );
+        anyhow::ensure!(
+            !args.atomic || (!args.split_output && output != Path::new(STDOUT_SENTINEL)),
+            "--atomic needs a combined output file; it cannot be used with --split-output or --output -."
+        );
+        if args.skip_existing {
+            anyhow::ensure!(
+                !(args.overwrite || args.atomic),
+                "--skip-existing reads the output it appends to; it cannot be combined with --overwrite or --atomic."
+            );
+            anyhow::ensure!(
+                !args.split_output
+                    && split_by_artist.is_none()
+                    && mirror_output.is_none()
+                    && output != Path::new(STDOUT_SENTINEL),
+                "--skip-existing needs a combined --output file; it cannot be used with --split-output, --split-by-artist, --mirror-output or --output -."
+            );
+            anyhow::ensure!(
+                template.is_none(),
+                "--skip-existing cannot read back output written with --template."
+            );
+            anyhow::ensure!(
+                matches!(output_format, OutputFormat::Text | OutputFormat::Json),
+                "--skip-existing only supports --format text and json."
+            );
+        }
+        if output_format == OutputFormat::JsonArray
+            && !(args.overwrite
+                || args.atomic
+                || args.dry_run
+                || args.dry_run_show
+                || args.count_only)
+        {
+            ensure_no_json_array_to_append(
+                &output,
+                split_by_artist.as_deref().or(mirror_output.as_deref()),
+            )?;
+        }
+        let excluded_tracks = args
+            .exclude_from
+            .map(|path| writer::read_reference_tracks(&make_absolute(&root, path)))
+            .transpose()?;
+        let output_encoding = args.output_encoding.unwrap_or_default();
+        anyhow::ensure!(
+            output_encoding == OutputEncoding::Utf8
+                || (output_format == OutputFormat::Text && !args.split_output),
+            "--output-encoding utf16le only applies to --format text with a combined --output; \
+             JSON, YAML and CSV output is always UTF-8."
+        );
+        validate_years(args.year_min, args.year_max)?;
+        if let (Some(min), Some(max)) = (args.min_duration, args.max_duration) {
+            anyhow::ensure!(
+                min <= max,
+                "--min-duration {min} exceeds --max-duration {max}; no tracks could match."
+            );
+        }
+        let jobs = resolve_jobs(args.jobs)?;
+        anyhow::ensure!(
+            args.queue_size != Some(0),
+            "--queue-size must be at least 1."
+        );
+        anyhow::ensure!(args.limit != Some(0), "--limit must be at least 1.");
+        anyhow::ensure!(
+            !(args.count_only && args.interactive),
+            "--count-only cannot be combined with --interactive."
+        );
+        anyhow::ensure!(
+            !args.interactive || (io::stdin().is_terminal() && io::stderr().is_terminal()),
+            "--interactive needs a terminal on stdin and stderr."
+        );
+        anyhow::ensure!(args.max_files != Some(0), "--max-files must be at least 1.");
+        anyhow::ensure!(
+            args.max_lyric_chars != Some(0),
+            "--max-lyric-chars must be at least 1."
+        );
+        let lyrics_tags: Vec<String> = if args.lyrics_tags.is_empty() {
+            DEFAULT_LYRICS_TAGS.map(str::to_owned).to_vec()
+        } else {
+            args.lyrics_tags
+                .iter()
+                .map(|name| name.trim().to_owned())
+                .collect()
+        };
+        anyhow::ensure!(
+            lyrics_tags.iter().all(|name| !name.is_empty()),
+            "--lyrics-tag needs a non-empty description."
+        );
+        let artist_regex = args
+            .artist_regex
+            .as_deref()
+            .map(|pattern| compile_artist_regex(pattern, args.case_sensitive))
+            .transpose()?;
+        anyhow::ensure!(
+            args.artist_fuzzy.is_none() || artist_regex.is_none(),
+            "--artist-fuzzy cannot be combined with --artist-regex."
+        );
+        let artist_exclude: Vec<String> = args
+            .artist_exclude
+            .iter()
+            .map(|needle| needle.trim())
+            .filter(|needle| !needle.is_empty())
+            .map(str::to_owned)
+            .collect();
+        let artist_filter_any: Vec<String> = args
+            .artist_filter_any
+            .iter()
+            .map(|needle| needle.trim())
+            .filter(|needle| !needle.is_empty())
+            .map(str::to_owned)
+            .collect();
+        if !args.artist_filter_any.is_empty() {
+            anyhow::ensure!(
+                !artist_filter_any.is_empty(),
+                "--artist-filter-any needs at least one non-empty artist."
+            );
+            anyhow::ensure!(
+                args.artist_filter.is_none() && artist_regex.is_none() && args.artist_fuzzy.is_none(),
+                "--artist-filter-any cannot be combined with --artist-filter, --artist-regex or --artist-fuzzy."
+            );
+        }
+        anyhow::ensure!(
+            !args.ascii_fold || (artist_regex.is_none() && args.artist_fuzzy.is_none()),
+            "--ascii-fold only applies to --artist-filter and --artist-filter-any, not --artist-regex or --artist-fuzzy."
+        );
+        if let Some(threshold) = args.artist_fuzzy {
+            anyhow::ensure!(
+                (0.0..=1.0).contains(&threshold),
+                "--artist-fuzzy must be between 0.0 and 1.0, got {threshold}."
+            );
+        }
 
         Ok(Self {
-            root,
+            roots,
             output,
-            dry_run: args.dry_run,
-            artist_filter: args.artist_filter,
+            split_output: args.split_output,
+            split_by_artist,
+            mirror_output,
+            overwrite: args.overwrite,
+            atomic: args.atomic,
+            dry_run: args.dry_run || args.dry_run_show || args.count_only,
+            dry_run_show: args.dry_run_show,
+            artist_filter: args
+                .artist_filter
+                .unwrap_or_else(|| DEFAULT_ARTIST_FILTER.to_string()),
+            artist_filter_any,
+            artist_exclude,
+            artist_regex,
+            artist_fuzzy: args.artist_fuzzy,
+            case_sensitive: args.case_sensitive,
+            ascii_fold: args.ascii_fold,
+            artist_source: args.artist_source.unwrap_or_default(),
+            artist_frames: args.artist_frames,
+            title_filter: args.title_filter,
+            year_min: args.year_min,
+            year_max: args.year_max,
+            min_duration: args.min_duration,
+            max_duration: args.max_duration,
+            genre: args.genre,
+            lyrics_lang: args.lyrics_lang,
+            by_language: args.by_language,
+            lyrics_tags,
+            keep_timestamps: args.keep_timestamps,
+            strip_lrc_timestamps: args.strip_lrc_timestamps,
+            loose_comments: args.loose_comments,
+            audio_properties: args.audio_properties,
+            read_retries: args.read_retries,
+            preview: args.preview,
+            interactive: args.interactive,
+            hash: args.hash.unwrap_or_default(),
+            min_size: args.min_size.unwrap_or(0),
+            collapse_blank_lines: args.collapse_blank_lines,
+            flatten: args.flatten,
+            sidecar_lyrics: args.sidecar_lyrics,
+            include_empty: args.include_empty,
+            count_only: args.count_only,
+            filter_cmd: args.filter_cmd,
+            lyrics_source: args.lyrics_source,
+            max_lyric_chars: args.max_lyric_chars,
             extensions,
-            output_format: args.format,
+            from_file,
+            stdin0: args.stdin0,
+            git_since: args.git_since,
+            exclude,
+            include,
+            modified_since,
+            output_format,
+            template,
+            output_encoding,
+            min_depth: args.min_depth,
             max_depth: args.max_depth,
+            no_recurse: args.no_recurse,
             follow_symlinks: args.follow_symlinks,
             summary_json,
+            relative_paths: args.relative_paths,
+            json_pretty: args.json_pretty,
+            errors_json: args.errors_json.map(|path| make_absolute(&root, path)),
+            matched_list,
+            state,
+            dedupe: args.dedupe,
+            skip_existing: args.skip_existing,
+            excluded_tracks,
+            limit: args.limit,
+            max_files: args.max_files,
+            fail_on_empty: args.fail_on_empty,
+            strict: args.strict,
+            list_artists: args.list_artists,
+            print_count: args.print_count,
+            jobs,
+            queue_size: args.queue_size.unwrap_or(DEFAULT_QUEUE_SIZE),
+            progress: args.progress,
+            log_format: args.log_format.unwrap_or_default(),
+            log_file,
             quiet: args.quiet,
+            log_level: resolve_log_level(args.quiet, args.verbose, args.log_level),
         })
     }
+
+    pub fn filters(&self) -> Filters {
+        let artist = match (&self.artist_regex, self.artist_fuzzy) {
+            (Some(pattern), _) => ArtistMatcher::Regex(pattern.clone()),
+            (None, Some(threshold)) => ArtistMatcher::Fuzzy {
+                needle: self.artist_filter.clone(),
+                threshold,
+                case_sensitive: self.case_sensitive,
+            },
+            (None, None) if !self.artist_filter_any.is_empty() => ArtistMatcher::AnyOf {
+                needles: self.artist_filter_any.clone(),
+                case_sensitive: self.case_sensitive,
+                ascii_fold: self.ascii_fold,
+            },
+            (None, None) => ArtistMatcher::Substring {
+                needle: self.artist_filter.clone(),
+                case_sensitive: self.case_sensitive,
+                ascii_fold: self.ascii_fold,
+            },
+        };
+
+        Filters {
+            artist,
+            artist_exclude: (!self.artist_exclude.is_empty()).then(|| ArtistMatcher::AnyOf {
+                needles: self.artist_exclude.clone(),
+                case_sensitive: self.case_sensitive,
+                ascii_fold: self.ascii_fold,
+            }),
+            artist_frames: if self.artist_frames.is_empty() {
+                self.artist_source.frames().to_vec()
+            } else {
+                self.artist_frames.clone()
+            },
+            search_artist_frames: !self.artist_frames.is_empty(),
+            title: self.title_filter.clone(),
+            year_min: self.year_min,
+            year_max: self.year_max,
+            min_duration: self.min_duration,
+            max_duration: self.max_duration,
+            genre: self.genre.clone(),
+            lyrics_lang: self.lyrics_lang.clone(),
+            by_language: self.by_language,
+            lyrics_tags: self.lyrics_tags.clone(),
+            keep_timestamps: self.keep_timestamps,
+            strip_lrc_timestamps: self.strip_lrc_timestamps,
+            loose_comments: self.loose_comments,
+            audio_properties: self.audio_properties,
+            read_retries: self.read_retries,
+            hash: self.hash,
+            min_size: self.min_size,
+            collapse_blank_lines: self.collapse_blank_lines,
+            flatten: self.flatten,
+            sidecar_lyrics: self.sidecar_lyrics,
+            include_empty: self.include_empty,
+            count_only: self.count_only,
+            filter_cmd: self.filter_cmd.clone(),
+            lyrics_source: self.lyrics_source,
+            max_lyric_chars: self.max_lyric_chars,
+        }
+    }
 }
 
-fn normalize_root(root: Option<PathBuf>) -> Result<PathBuf> {
-    match root {
-        Some(path) => {
-            let resolved = absolutize(&path)?;
-            ensure_directory(&resolved)?;
-            Ok(resolved)
+/// Options read from `mdlyricgetter.toml`; every key is optional and only fills in values the
+/// command line left unset.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileConfig {
+    root: Option<PathBuf>,
+    output: Option<PathBuf>,
+    split_output: Option<bool>,
+    split_by_artist: Option<PathBuf>,
+    mirror_output: Option<PathBuf>,
+    overwrite: Option<bool>,
+    atomic: Option<bool>,
+    dry_run: Option<bool>,
+    dry_run_show: Option<bool>,
+    artist_filter: Option<String>,
+    artist_filter_any: Option<Vec<String>>,
+    artist_exclude: Option<Vec<String>>,
+    artist_regex: Option<String>,
+    artist_fuzzy: Option<f64>,
+    case_sensitive: Option<bool>,
+    ascii_fold: Option<bool>,
+    artist_source: Option<ArtistSource>,
+    artist_frames: Option<Vec<ArtistFrame>>,
+    title_filter: Option<String>,
+    year_min: Option<i32>,
+    year_max: Option<i32>,
+    min_duration: Option<u64>,
+    max_duration: Option<u64>,
+    genre: Option<String>,
+    lyrics_lang: Option<String>,
+    by_language: Option<bool>,
+    lyrics_tags: Option<Vec<String>>,
+    keep_timestamps: Option<bool>,
+    strip_lrc_timestamps: Option<bool>,
+    loose_comments: Option<bool>,
+    audio_properties: Option<bool>,
+    read_retries: Option<u32>,
+    preview: Option<usize>,
+    hash: Option<HashAlgorithm>,
+    min_size: Option<u64>,
+    collapse_blank_lines: Option<bool>,
+    flatten: Option<bool>,
+    sidecar_lyrics: Option<bool>,
+    include_empty: Option<bool>,
+    count_only: Option<bool>,
+    filter_cmd: Option<String>,
+    lyrics_source: Option<bool>,
+    max_lyric_chars: Option<usize>,
+    extensions: Option<Vec<String>>,
+    all_extensions: Option<bool>,
+    from_file: Option<PathBuf>,
+    stdin0: Option<bool>,
+    git_since: Option<String>,
+    exclude: Option<Vec<String>>,
+    include: Option<Vec<String>>,
+    modified_since: Option<String>,
+    format: Option<OutputFormat>,
+    template: Option<String>,
+    output_encoding: Option<OutputEncoding>,
+    min_depth: Option<usize>,
+    max_depth: Option<usize>,
+    no_recurse: Option<bool>,
+    follow_symlinks: Option<bool>,
+    summary_json: Option<PathBuf>,
+    relative_paths: Option<bool>,
+    json_pretty: Option<bool>,
+    timestamp_output: Option<bool>,
+    errors_json: Option<PathBuf>,
+    matched_list: Option<PathBuf>,
+    state: Option<PathBuf>,
+    dedupe: Option<bool>,
+    skip_existing: Option<bool>,
+    exclude_from: Option<PathBuf>,
+    limit: Option<usize>,
+    max_files: Option<usize>,
+    fail_on_empty: Option<bool>,
+    strict: Option<bool>,
+    list_artists: Option<bool>,
+    print_count: Option<bool>,
+    jobs: Option<usize>,
+    queue_size: Option<usize>,
+    progress: Option<bool>,
+    log_format: Option<LogFormat>,
+    log_file: Option<PathBuf>,
+    quiet: Option<bool>,
+    verbose: Option<u8>,
+    log_level: Option<LogLevel>,
+}
+
+impl FileConfig {
+    fn load(path: &Path) -> Result<Self> {
+        let raw = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read config file '{}'", path.display()))?;
+        toml::from_str(&raw)
+            .with_context(|| format!("failed to parse config file '{}'", path.display()))
+    }
+
+    /// Merges file values underneath the CLI arguments. `root` is honoured only when the file
+    /// was passed via `--config` (`root_base` is then its directory); a file discovered inside
+    /// the root cannot move it.
+    fn apply_to(self, args: &mut CliArgs, root_base: Option<&Path>) -> Result<()> {
+        if let (Some(base), true) = (root_base, args.root.is_empty()) {
+            args.root = self.root.map(|root| base.join(root)).into_iter().collect();
+        }
+
+        if args.artist_exclude.is_empty() {
+            args.artist_exclude = self.artist_exclude.unwrap_or_default();
+        }
+        if args.artist_filter.is_none()
+            && args.artist_regex.is_none()
+            && args.artist_filter_any.is_empty()
+        {
+            let set = [
+                self.artist_filter.is_some(),
+                self.artist_regex.is_some(),
+                self.artist_filter_any.is_some(),
+            ];
+            anyhow::ensure!(
+                set.into_iter().filter(|&set| set).count() <= 1,
+                "config file sets more than one of artist_filter, artist_filter_any and artist_regex; choose one."
+            );
+            args.artist_filter = self.artist_filter;
+            args.artist_regex = self.artist_regex;
+            args.artist_filter_any = self.artist_filter_any.unwrap_or_default();
         }
-        None => {
-            let cwd =
-                std::env::current_dir().context("could not resolve current working directory")?;
-            ensure_directory(&cwd)?;
-            Ok(cwd)
+
+        args.artist_fuzzy = args.artist_fuzzy.or(self.artist_fuzzy);
+        args.case_sensitive |= self.case_sensitive.unwrap_or(false);
+        args.ascii_fold |= self.ascii_fold.unwrap_or(false);
+        if args.artist_source.is_none() && args.artist_frames.is_empty() {
+            anyhow::ensure!(
+                self.artist_source.is_none() || self.artist_frames.is_none(),
+                "config file sets both artist_source and artist_frames; choose one."
+            );
+            args.artist_source = self.artist_source;
+            args.artist_frames = self.artist_frames.unwrap_or_default();
         }
+        args.output = args.output.take().or(self.output);
+        args.split_output |= self.split_output.unwrap_or(false);
+        args.split_by_artist = args.split_by_artist.take().or(self.split_by_artist);
+        args.mirror_output = args.mirror_output.take().or(self.mirror_output);
+        args.overwrite |= self.overwrite.unwrap_or(false);
+        args.atomic |= self.atomic.unwrap_or(false);
+        args.dry_run |= self.dry_run.unwrap_or(false);
+        args.dry_run_show |= self.dry_run_show.unwrap_or(false);
+        args.title_filter = args.title_filter.take().or(self.title_filter);
+        args.year_min = args.year_min.or(self.year_min);
+        args.year_max = args.year_max.or(self.year_max);
+        args.min_duration = args.min_duration.or(self.min_duration);
+        args.max_duration = args.max_duration.or(self.max_duration);
+        args.genre = args.genre.take().or(self.genre);
+        args.lyrics_lang = args.lyrics_lang.take().or(self.lyrics_lang);
+        args.by_language |= self.by_language.unwrap_or(false);
+        if args.lyrics_tags.is_empty() {
+            args.lyrics_tags = self.lyrics_tags.unwrap_or_default();
+        }
+        args.keep_timestamps |= self.keep_timestamps.unwrap_or(false);
+        args.strip_lrc_timestamps |= self.strip_lrc_timestamps.unwrap_or(false);
+        anyhow::ensure!(
+            !(args.keep_timestamps && args.strip_lrc_timestamps),
+            "keep_timestamps and strip_lrc_timestamps cannot both be enabled."
+        );
+        args.loose_comments |= self.loose_comments.unwrap_or(false);
+        args.audio_properties |= self.audio_properties.unwrap_or(false);
+        args.hash = args.hash.or(self.hash);
+        if args.preview == 0 {
+            args.preview = self.preview.unwrap_or(0);
+        }
+        if args.read_retries == 0 {
+            args.read_retries = self.read_retries.unwrap_or(0);
+        }
+        args.min_size = args.min_size.or(self.min_size);
+        args.collapse_blank_lines |= self.collapse_blank_lines.unwrap_or(false);
+        args.flatten |= self.flatten.unwrap_or(false);
+        args.sidecar_lyrics |= self.sidecar_lyrics.unwrap_or(false);
+        args.include_empty |= self.include_empty.unwrap_or(false);
+        args.count_only |= self.count_only.unwrap_or(false);
+        args.filter_cmd = args.filter_cmd.take().or(self.filter_cmd);
+        args.lyrics_source |= self.lyrics_source.unwrap_or(false);
+        args.max_lyric_chars = args.max_lyric_chars.or(self.max_lyric_chars);
+        if args.extensions.is_none() && !args.all_extensions {
+            anyhow::ensure!(
+                self.extensions.is_none() || self.all_extensions != Some(true),
+                "config file sets both extensions and all_extensions; choose one."
+            );
+            args.extensions = self.extensions.map(|exts| exts.join(","));
+            args.all_extensions = self.all_extensions.unwrap_or(false);
+        }
+        args.from_file = args.from_file.take().or(self.from_file);
+        args.stdin0 |= self.stdin0.unwrap_or(false);
+        args.git_since = args.git_since.take().or(self.git_since);
+        if args.exclude.is_empty() {
+            args.exclude = self.exclude.unwrap_or_default();
+        }
+        if args.include.is_empty() {
+            args.include = self.include.unwrap_or_default();
+        }
+        args.modified_since = args.modified_since.take().or(self.modified_since);
+        args.format = args.format.or(self.format);
+        args.template = args.template.take().or(self.template);
+        args.output_encoding = args.output_encoding.or(self.output_encoding);
+        args.min_depth = args.min_depth.or(self.min_depth);
+        if args.max_depth.is_none() && !args.no_recurse {
+            anyhow::ensure!(
+                self.max_depth.is_none() || self.no_recurse != Some(true),
+                "config file sets both max_depth and no_recurse; choose one."
+            );
+            args.max_depth = self.max_depth;
+            args.no_recurse = self.no_recurse.unwrap_or(false);
+        }
+        args.follow_symlinks |= self.follow_symlinks.unwrap_or(false);
+        args.summary_json = args.summary_json.take().or(self.summary_json);
+        args.timestamp_output |= self.timestamp_output.unwrap_or(false);
+        args.relative_paths |= self.relative_paths.unwrap_or(false);
+        args.json_pretty |= self.json_pretty.unwrap_or(false);
+        args.errors_json = args.errors_json.take().or(self.errors_json);
+        args.matched_list = args.matched_list.take().or(self.matched_list);
+        args.state = args.state.take().or(self.state);
+        args.dedupe |= self.dedupe.unwrap_or(false);
+        args.skip_existing |= self.skip_existing.unwrap_or(false);
+        args.exclude_from = args.exclude_from.take().or(self.exclude_from);
+        args.fail_on_empty |= self.fail_on_empty.unwrap_or(false);
+        args.strict |= self.strict.unwrap_or(false);
+        args.list_artists |= self.list_artists.unwrap_or(false);
+        args.print_count |= self.print_count.unwrap_or(false);
+        args.limit = args.limit.or(self.limit);
+        args.max_files = args.max_files.or(self.max_files);
+        args.jobs = args.jobs.or(self.jobs);
+        args.queue_size = args.queue_size.or(self.queue_size);
+        args.progress |= self.progress.unwrap_or(false);
+        args.log_format = args.log_format.or(self.log_format);
+        args.log_file = args.log_file.take().or(self.log_file);
+        if !args.quiet && args.verbose == 0 && args.log_level.is_none() {
+            args.quiet = self.quiet.unwrap_or(false);
+            args.verbose = self.verbose.unwrap_or(0);
+            args.log_level = self.log_level;
+        }
+        Ok(())
     }
 }
 
+/// Resolves each root against the working directory; no roots means the working directory.
+fn normalize_roots(roots: Vec<PathBuf>) -> Result<Vec<PathBuf>> {
+    if roots.is_empty() {
+        let cwd = std::env::current_dir().context("could not resolve current working directory")?;
+        ensure_directory(&cwd)?;
+        return Ok(vec![cwd]);
+    }
+
+    roots
+        .iter()
+        .map(|path| {
+            let resolved = absolutize(path)?;
+            ensure_directory(&resolved)?;
+            Ok(resolved)
+        })
+        .collect()
+}
+
 fn normalize_output(root: &Path, output: Option<PathBuf>) -> Result<PathBuf> {
     let output_path = match output {
-        Some(path) if path.is_absolute() => path,
+        Some(path) if path.is_absolute() || path == Path::new(STDOUT_SENTINEL) => path,
         Some(path) => root.join(path),
         None => root.join("lyrics.txt"),
     };
@@ -66,6 +872,26 @@ fn normalize_output(root: &Path, output: Option<PathBuf>) -> Result<PathBuf> {
     Ok(output_path)
 }
 
+/// The current UTC time as `2024-06-01T12-00-00`: RFC 3339 without the colons Windows rejects
+/// in file names.
+fn output_timestamp(now: SystemTime) -> String {
+    humantime::format_rfc3339_seconds(now)
+        .to_string()
+        .trim_end_matches('Z')
+        .replace(':', "-")
+}
+
+/// Inserts `-<stamp>` before the extension: `lyrics.txt` becomes `lyrics-<stamp>.txt`.
+fn with_timestamp(path: &Path, stamp: &str) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let mut name = format!("{stem}-{stamp}");
+    if let Some(extension) = path.extension() {
+        name.push('.');
+        name.push_str(&extension.to_string_lossy());
+    }
+    path.with_file_name(name)
+}
+
 fn make_absolute(root: &Path, path: PathBuf) -> PathBuf {
     if path.is_absolute() {
         path
@@ -82,6 +908,93 @@ fn absolutize(path: &Path) -> Result<PathBuf> {
     }
 }
 
+fn validate_depths(min_depth: Option<usize>, max_depth: Option<usize>) -> Result<()> {
+    if let (Some(min), Some(max)) = (min_depth, max_depth) {
+        anyhow::ensure!(
+            min <= max,
+            "--min-depth {min} exceeds --max-depth {max}; no files could be scanned."
+        );
+    }
+    Ok(())
+}
+
+fn validate_years(year_min: Option<i32>, year_max: Option<i32>) -> Result<()> {
+    if let (Some(min), Some(max)) = (year_min, year_max) {
+        anyhow::ensure!(
+            min <= max,
+            "--year-min {min} exceeds --year-max {max}; no tracks could match."
+        );
+    }
+    Ok(())
+}
+
+/// `--log-level` wins over `--quiet` and `-v`; each extra `-v` after the first stays at trace.
+fn resolve_log_level(quiet: bool, verbose: u8, log_level: Option<LogLevel>) -> Option<LevelFilter> {
+    match (log_level, quiet, verbose) {
+        (Some(level), _, _) => Some(level.into()),
+        (None, true, _) => Some(LevelFilter::Error),
+        (None, false, 0) => None,
+        (None, false, 1) => Some(LevelFilter::Debug),
+        (None, false, _) => Some(LevelFilter::Trace),
+    }
+}
+
+/// Results buffered between the parallel workers and the writer.
+const DEFAULT_QUEUE_SIZE: usize = 1024;
+
+fn resolve_jobs(jobs: Option<usize>) -> Result<usize> {
+    match jobs {
+        Some(0) => anyhow::bail!("--jobs must be at least 1."),
+        Some(count) => Ok(count),
+        None => Ok(std::thread::available_parallelism()
+            .map(|count| count.get())
+            .unwrap_or(1)),
+    }
+}
+
+/// Compiles case-insensitively unless `case_sensitive` is set; inline flags such as `(?-i)` in
+/// the pattern take precedence.
+fn compile_artist_regex(pattern: &str, case_sensitive: bool) -> Result<Regex> {
+    RegexBuilder::new(pattern)
+        .case_insensitive(!case_sensitive)
+        .build()
+        .with_context(|| format!("invalid --artist-regex pattern '{pattern}'"))
+}
+
+/// Accepts a relative age such as `7d` or `24h` (counted back from `now`) or an RFC 3339
+/// timestamp; a bare `YYYY-MM-DD` date means midnight UTC.
+fn parse_modified_since(raw: &str, now: SystemTime) -> Result<SystemTime> {
+    let raw = raw.trim();
+    if let Ok(age) = humantime::parse_duration(raw) {
+        return now
+            .checked_sub(age)
+            .with_context(|| format!("--modified-since age '{raw}' is too large"));
+    }
+
+    let timestamp = if raw.len() == 10 {
+        format!("{raw}T00:00:00Z")
+    } else {
+        raw.to_string()
+    };
+    humantime::parse_rfc3339_weak(&timestamp).with_context(|| {
+        format!(
+            "invalid --modified-since value '{raw}'; expected an RFC 3339 timestamp or an age like 7d"
+        )
+    })
+}
+
+fn compile_globs(patterns: &[String], flag: &str) -> Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob =
+            Glob::new(pattern).with_context(|| format!("invalid {flag} glob '{pattern}'"))?;
+        builder.add(glob);
+    }
+    builder
+        .build()
+        .with_context(|| format!("failed to compile {flag} globs"))
+}
+
 fn ensure_directory(path: &Path) -> Result<()> {
     anyhow::ensure!(
         path.is_dir(),
@@ -91,41 +1004,81 @@ fn ensure_directory(path: &Path) -> Result<()> {
     Ok(())
 }
 
+/// `--format json-array` writes one whole array per file, so appending to a file that already
+/// holds one would leave two arrays back to back, which is not valid JSON. `output_dir` is the
+/// `--split-by-artist` or `--mirror-output` directory, which replaces `output` when set.
+fn ensure_no_json_array_to_append(output: &Path, output_dir: Option<&Path>) -> Result<()> {
+    if let Some(dir) = output_dir {
+        let has_files = std::fs::read_dir(dir).is_ok_and(|mut entries| entries.next().is_some());
+        anyhow::ensure!(
+            !has_files,
+            "--format json-array cannot append to the existing files in '{}'; pass --overwrite to replace them.",
+            dir.display()
+        );
+    } else if output != Path::new(STDOUT_SENTINEL) {
+        let len = std::fs::metadata(output).map_or(0, |metadata| metadata.len());
+        anyhow::ensure!(
+            len == 0,
+            "--format json-array cannot append a second array to '{}', which would not be valid JSON; pass --overwrite or --atomic to replace it.",
+            output.display()
+        );
+    }
+    Ok(())
+}
+
+fn parse_extensions(raw: String) -> Vec<String> {
+    let mut exts: Vec<String> = raw
+        .split(',')
+        .map(|ext| ext.trim())
+        .filter(|ext| !ext.is_empty())
+        .map(|ext| ext.trim_start_matches('.').to_ascii_lowercase())
+        .collect();
+
+    if exts.is_empty() {
+        exts.push("mp3".to_string());
+    }
+
+    exts
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     use std::fs;
 
+    use clap::Parser;
     use tempfile::TempDir;
 
+    fn parse_args(extra: &[&str]) -> CliArgs {
+        CliArgs::parse_from(std::iter::once("mdlyricgetter").chain(extra.iter().copied()))
+    }
+
     #[test]
     fn defaults_to_current_directory() {
         let cwd = std::env::current_dir().unwrap();
-        let args = CliArgs {
-            root: None,
-            output: None,
-            dry_run: false,
-            artist_filter: "udio".into(),
-            extensions: "mp3".into(),
-            format: OutputFormat::Text,
-            max_depth: None,
-            follow_symlinks: false,
-            summary_json: None,
-            quiet: false,
-        };
+        let args = parse_args(&[]);
 
         let config = Config::from_args(args).expect("config");
 
-        assert_eq!(config.root, cwd);
+        assert_eq!(config.roots, vec![cwd.clone()]);
         assert_eq!(config.output, cwd.join("lyrics.txt"));
         assert!(!config.dry_run);
+        assert!(!config.overwrite);
         assert_eq!(config.artist_filter, "udio");
+        assert!(config.artist_regex.is_none());
+        assert_eq!(config.title_filter, None);
+        assert_eq!(config.lyrics_lang, None);
         assert_eq!(config.extensions, vec!["mp3"]);
+        assert!(config.exclude.is_empty());
+        assert!(config.include.is_empty());
         assert_eq!(config.output_format, OutputFormat::Text);
+        assert_eq!(config.min_depth, None);
         assert_eq!(config.max_depth, None);
         assert!(!config.follow_symlinks);
         assert_eq!(config.summary_json, None);
+        assert!(config.jobs >= 1);
+        assert!(!config.progress);
         assert!(!config.quiet);
     }
 
@@ -137,22 +1090,31 @@ mod tests {
 
         let _guard = CwdGuard::set(temp_dir.path());
 
-        let args = CliArgs {
-            root: Some(PathBuf::from("library")),
-            output: Some(PathBuf::from("custom.txt")),
-            dry_run: true,
-            artist_filter: "mix".into(),
-            extensions: "mp3,flac".into(),
-            format: OutputFormat::Json,
-            max_depth: Some(2),
-            follow_symlinks: true,
-            summary_json: Some(PathBuf::from("summary.json")),
-            quiet: true,
-        };
+        let args = parse_args(&[
+            "--root",
+            "library",
+            "--output",
+            "custom.txt",
+            "--dry-run",
+            "--artist-filter",
+            "mix",
+            "--extensions",
+            "mp3,flac",
+            "--format",
+            "json",
+            "--max-depth",
+            "2",
+            "--follow-symlinks",
+            "--summary-json",
+            "summary.json",
+            "--jobs",
+            "3",
+            "--quiet",
+        ]);
 
         let config = Config::from_args(args).expect("config");
 
-        assert_eq!(config.root, nested);
+        assert_eq!(config.roots, vec![nested.clone()]);
         assert_eq!(config.output, nested.join("custom.txt"));
         assert!(config.dry_run);
         assert_eq!(config.artist_filter, "mix");
@@ -161,6 +1123,7 @@ mod tests {
         assert_eq!(config.max_depth, Some(2));
         assert!(config.follow_symlinks);
         assert_eq!(config.summary_json, Some(nested.join("summary.json")));
+        assert_eq!(config.jobs, 3);
         assert!(config.quiet);
     }
 
@@ -173,42 +1136,63 @@ mod tests {
         let output_path = temp_dir.path().join("lyrics").join("stash.txt");
         fs::create_dir_all(output_path.parent().unwrap()).unwrap();
 
-        let args = CliArgs {
-            root: Some(nested.clone()),
-            output: Some(output_path.clone()),
-            dry_run: false,
-            artist_filter: "udio".into(),
-            extensions: "mp3".into(),
-            format: OutputFormat::Text,
-            max_depth: None,
-            follow_symlinks: false,
-            summary_json: None,
-            quiet: false,
-        };
+        let args = parse_args(&[
+            "--root",
+            nested.to_str().unwrap(),
+            "--output",
+            output_path.to_str().unwrap(),
+        ]);
 
         let config = Config::from_args(args).expect("config");
 
-        assert_eq!(config.root, nested);
+        assert_eq!(config.roots, vec![nested.clone()]);
         assert_eq!(config.output, output_path);
     }
 
+    #[test]
+    fn repeated_roots_are_each_validated() {
+        let temp_dir = TempDir::new().unwrap();
+        let music = temp_dir.path().join("music");
+        let downloads = temp_dir.path().join("downloads");
+        fs::create_dir(&music).unwrap();
+        fs::create_dir(&downloads).unwrap();
+
+        let args = parse_args(&[
+            "--root",
+            music.to_str().unwrap(),
+            "--root",
+            downloads.to_str().unwrap(),
+        ]);
+        let config = Config::from_args(args).expect("config");
+        assert_eq!(config.roots, vec![music.clone(), downloads]);
+        assert_eq!(config.output, music.join("lyrics.txt"));
+
+        let missing = temp_dir.path().join("missing");
+        let args = parse_args(&[
+            "--root",
+            music.to_str().unwrap(),
+            "--root",
+            missing.to_str().unwrap(),
+        ]);
+        let error = Config::from_args(args).unwrap_err();
+        assert!(format!("{error:#}").contains(missing.to_str().unwrap()));
+    }
+
+    #[test]
+    fn dash_output_is_not_joined_to_root() {
+        let temp_dir = TempDir::new().unwrap();
+        let args = parse_args(&["--root", temp_dir.path().to_str().unwrap(), "--output", "-"]);
+
+        let config = Config::from_args(args).expect("config");
+        assert_eq!(config.output, PathBuf::from("-"));
+    }
+
     #[test]
     fn missing_root_yields_error() {
         let temp_dir = TempDir::new().unwrap();
         let nonexistent = temp_dir.path().join("missing");
 
-        let args = CliArgs {
-            root: Some(nonexistent.clone()),
-            output: None,
-            dry_run: false,
-            artist_filter: "udio".into(),
-            extensions: "mp3".into(),
-            format: OutputFormat::Text,
-            max_depth: None,
-            follow_symlinks: false,
-            summary_json: None,
-            quiet: false,
-        };
+        let args = parse_args(&["--root", nonexistent.to_str().unwrap()]);
 
         let error = Config::from_args(args).unwrap_err();
         let message = format!("{error:#}");
@@ -218,6 +1202,418 @@ mod tests {
         );
     }
 
+    #[test]
+    fn no_recurse_limits_scan_to_root_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().to_str().unwrap();
+
+        let config = Config::from_args(parse_args(&["--root", root, "--no-recurse"])).unwrap();
+        assert_eq!(config.max_depth, Some(1));
+
+        let conflict =
+            CliArgs::try_parse_from(["mdlyricgetter", "--no-recurse", "--max-depth", "2"]);
+        assert!(conflict.is_err());
+    }
+
+    #[test]
+    fn template_is_validated_up_front() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().to_str().unwrap();
+
+        let config =
+            Config::from_args(parse_args(&["--root", root, "--template", "{title}\n"])).unwrap();
+        assert!(config.template.is_some());
+
+        let unknown =
+            Config::from_args(parse_args(&["--root", root, "--template", "{year}"])).unwrap_err();
+        assert!(format!("{unknown:#}").contains("unknown placeholder '{year}'"));
+
+        let json = Config::from_args(parse_args(&[
+            "--root",
+            root,
+            "--template",
+            "{title}",
+            "--format",
+            "json",
+        ]))
+        .unwrap_err();
+        assert!(format!("{json:#}").contains("--template only applies to --format text"));
+    }
+
+    #[test]
+    fn timestamp_output_stamps_output_and_summary_names() {
+        let now = humantime::parse_rfc3339("2024-06-01T12:00:00Z").unwrap();
+        let stamp = output_timestamp(now);
+        assert_eq!(stamp, "2024-06-01T12-00-00");
+        assert_eq!(
+            with_timestamp(Path::new("/out/lyrics.txt"), &stamp),
+            PathBuf::from("/out/lyrics-2024-06-01T12-00-00.txt")
+        );
+        assert_eq!(
+            with_timestamp(Path::new("/out/lyrics"), &stamp),
+            PathBuf::from("/out/lyrics-2024-06-01T12-00-00")
+        );
+
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().to_str().unwrap();
+        let config = Config::from_args(parse_args(&[
+            "--root",
+            root,
+            "--timestamp-output",
+            "--summary-json",
+            "summary.json",
+        ]))
+        .unwrap();
+        let output = config.output.file_name().unwrap().to_str().unwrap();
+        let summary = config.summary_json.as_deref().unwrap();
+        let summary = summary.file_name().unwrap().to_str().unwrap();
+        assert!(output.starts_with("lyrics-") && output.ends_with(".txt"));
+        assert_eq!(
+            summary,
+            output
+                .replace("lyrics-", "summary-")
+                .replace(".txt", ".json")
+        );
+
+        let stdout = Config::from_args(parse_args(&[
+            "--root",
+            root,
+            "--timestamp-output",
+            "--output",
+            "-",
+        ]))
+        .unwrap();
+        assert_eq!(stdout.output, PathBuf::from("-"));
+    }
+
+    #[test]
+    fn json_pretty_implies_json_array() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().to_str().unwrap();
+
+        let config = Config::from_args(parse_args(&["--root", root, "--json-pretty"])).unwrap();
+        assert_eq!(config.output_format, OutputFormat::JsonArray);
+        assert!(config.json_pretty);
+
+        let lines = Config::from_args(parse_args(&[
+            "--root",
+            root,
+            "--json-pretty",
+            "--format",
+            "json",
+        ]))
+        .unwrap_err();
+        assert!(format!("{lines:#}").contains("--json-pretty only applies to --format json-array"));
+    }
+
+    #[test]
+    fn json_array_refuses_to_append_to_existing_output() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().to_str().unwrap();
+        let json_array = |extra: &[&str]| {
+            let mut args = vec!["--root", root, "--format", "json-array"];
+            args.extend_from_slice(extra);
+            Config::from_args(parse_args(&args))
+        };
+
+        assert!(json_array(&[]).is_ok());
+        std::fs::write(temp_dir.path().join("lyrics.txt"), "[\n{}\n]\n").unwrap();
+        let error = json_array(&[]).unwrap_err();
+        assert!(format!("{error:#}").contains("cannot append a second array"));
+        assert!(json_array(&["--overwrite"]).is_ok());
+        assert!(json_array(&["--atomic"]).is_ok());
+        assert!(json_array(&["--dry-run"]).is_ok());
+
+        let artists = temp_dir.path().join("artists");
+        std::fs::create_dir(&artists).unwrap();
+        let artists = artists.to_str().unwrap();
+        assert!(json_array(&["--split-by-artist", artists]).is_ok());
+        std::fs::write(temp_dir.path().join("artists/udio.json"), "[]").unwrap();
+        let error = json_array(&["--mirror-output", artists]).unwrap_err();
+        assert!(format!("{error:#}").contains("cannot append to the existing files"));
+        assert!(json_array(&["--split-by-artist", artists, "--overwrite"]).is_ok());
+    }
+
+    #[test]
+    fn summary_json_accepts_stdout_unless_output_uses_it() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().to_str().unwrap();
+
+        let config =
+            Config::from_args(parse_args(&["--root", root, "--summary-json", "-"])).unwrap();
+        assert_eq!(config.summary_json, Some(PathBuf::from("-")));
+
+        let clash = Config::from_args(parse_args(&[
+            "--root",
+            root,
+            "--summary-json",
+            "-",
+            "--output",
+            "-",
+        ]))
+        .unwrap_err();
+        assert!(format!("{clash:#}").contains("both would write to stdout"));
+
+        let dry_run = parse_args(&[
+            "--root",
+            root,
+            "--summary-json",
+            "-",
+            "--output",
+            "-",
+            "--dry-run",
+        ]);
+        assert!(Config::from_args(dry_run).is_ok());
+    }
+
+    #[test]
+    fn min_depth_above_max_depth_yields_error() {
+        let temp_dir = TempDir::new().unwrap();
+        let args = parse_args(&[
+            "--root",
+            temp_dir.path().to_str().unwrap(),
+            "--min-depth",
+            "3",
+            "--max-depth",
+            "2",
+        ]);
+
+        let error = Config::from_args(args).unwrap_err();
+        assert!(
+            format!("{error:#}").contains("--min-depth 3 exceeds --max-depth 2"),
+            "unexpected error message: {error:#}"
+        );
+    }
+
+    #[test]
+    fn year_min_above_year_max_yields_error() {
+        let temp_dir = TempDir::new().unwrap();
+        let args = parse_args(&[
+            "--root",
+            temp_dir.path().to_str().unwrap(),
+            "--year-min",
+            "2000",
+            "--year-max",
+            "1990",
+        ]);
+
+        let error = Config::from_args(args).unwrap_err();
+        assert!(
+            format!("{error:#}").contains("--year-min 2000 exceeds --year-max 1990"),
+            "unexpected error message: {error:#}"
+        );
+    }
+
+    #[test]
+    fn modified_since_accepts_ages_and_timestamps() {
+        let now = humantime::parse_rfc3339("2024-03-10T12:00:00Z").unwrap();
+
+        assert_eq!(
+            parse_modified_since("7d", now).unwrap(),
+            humantime::parse_rfc3339("2024-03-03T12:00:00Z").unwrap()
+        );
+        assert_eq!(
+            parse_modified_since("24h", now).unwrap(),
+            humantime::parse_rfc3339("2024-03-09T12:00:00Z").unwrap()
+        );
+        assert_eq!(
+            parse_modified_since("2024-01-01T08:30:00Z", now).unwrap(),
+            humantime::parse_rfc3339("2024-01-01T08:30:00Z").unwrap()
+        );
+        assert_eq!(
+            parse_modified_since("2024-01-01", now).unwrap(),
+            humantime::parse_rfc3339("2024-01-01T00:00:00Z").unwrap()
+        );
+
+        let error = parse_modified_since("last tuesday", now).unwrap_err();
+        assert!(format!("{error:#}").contains("invalid --modified-since value 'last tuesday'"));
+    }
+
+    #[test]
+    fn zero_jobs_yields_error() {
+        let temp_dir = TempDir::new().unwrap();
+        let args = parse_args(&["--root", temp_dir.path().to_str().unwrap(), "--jobs", "0"]);
+
+        assert!(Config::from_args(args).is_err());
+    }
+
+    #[test]
+    fn zero_limit_yields_error() {
+        let temp_dir = TempDir::new().unwrap();
+        let args = parse_args(&["--root", temp_dir.path().to_str().unwrap(), "--limit", "0"]);
+
+        assert!(Config::from_args(args).is_err());
+    }
+
+    #[test]
+    fn artist_regex_is_compiled_case_insensitively() {
+        let temp_dir = TempDir::new().unwrap();
+        let args = parse_args(&[
+            "--root",
+            temp_dir.path().to_str().unwrap(),
+            "--artist-regex",
+            "^studio",
+        ]);
+
+        let config = Config::from_args(args).expect("config");
+        let filters = config.filters();
+        assert!(filters.artist.matches("Studio Band"));
+        assert!(!filters.artist.matches("Big Studio"));
+    }
+
+    #[test]
+    fn case_sensitive_applies_to_filter_and_regex() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().to_str().unwrap();
+
+        let args = parse_args(&[
+            "--root",
+            root,
+            "--artist-filter",
+            "udio",
+            "--case-sensitive",
+        ]);
+        let filters = Config::from_args(args).expect("config").filters();
+        assert!(filters.artist.matches("studio"));
+        assert!(!filters.artist.matches("Udio"));
+
+        let args = parse_args(&[
+            "--root",
+            root,
+            "--artist-regex",
+            "^udio",
+            "--case-sensitive",
+        ]);
+        let filters = Config::from_args(args).expect("config").filters();
+        assert!(filters.artist.matches("udio mix"));
+        assert!(!filters.artist.matches("Udio mix"));
+    }
+
+    #[test]
+    fn invalid_artist_regex_yields_error() {
+        let temp_dir = TempDir::new().unwrap();
+        let args = parse_args(&[
+            "--root",
+            temp_dir.path().to_str().unwrap(),
+            "--artist-regex",
+            "(unclosed",
+        ]);
+
+        let error = Config::from_args(args).unwrap_err();
+        assert!(
+            format!("{error:#}").contains("invalid --artist-regex pattern '(unclosed'"),
+            "unexpected error message: {error:#}"
+        );
+    }
+
+    #[test]
+    fn exclude_globs_are_compiled() {
+        let temp_dir = TempDir::new().unwrap();
+        let args = parse_args(&[
+            "--root",
+            temp_dir.path().to_str().unwrap(),
+            "--exclude",
+            ".trash",
+            "--exclude",
+            "@eaDir",
+        ]);
+
+        let config = Config::from_args(args).expect("config");
+        assert_eq!(config.exclude.len(), 2);
+        assert!(config.exclude.is_match("@eaDir"));
+        assert!(!config.exclude.is_match("Singles"));
+    }
+
+    #[test]
+    fn invalid_exclude_glob_yields_error() {
+        let temp_dir = TempDir::new().unwrap();
+        let args = parse_args(&[
+            "--root",
+            temp_dir.path().to_str().unwrap(),
+            "--exclude",
+            "[unclosed",
+        ]);
+
+        let error = Config::from_args(args).unwrap_err();
+        assert!(
+            format!("{error:#}").contains("invalid --exclude glob '[unclosed'"),
+            "unexpected error message: {error:#}"
+        );
+    }
+
+    #[test]
+    fn artist_regex_conflicts_with_artist_filter() {
+        let result = CliArgs::try_parse_from([
+            "mdlyricgetter",
+            "--artist-filter",
+            "udio",
+            "--artist-regex",
+            "udio",
+        ]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn discovered_config_file_fills_unset_options() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(
+            temp_dir.path().join(CONFIG_FILE_NAME),
+            r#"
+output = "from-file.txt"
+artist_filter = "choir"
+extensions = ["mp3", "FLAC"]
+format = "json"
+max_depth = 4
+quiet = true
+"#,
+        )
+        .unwrap();
+
+        let args = parse_args(&[
+            "--root",
+            temp_dir.path().to_str().unwrap(),
+            "--format",
+            "csv",
+        ]);
+        let config = Config::from_args(args).expect("config");
+
+        assert_eq!(config.output, temp_dir.path().join("from-file.txt"));
+        assert_eq!(config.artist_filter, "choir");
+        assert_eq!(config.extensions, vec!["mp3", "flac"]);
+        assert_eq!(config.output_format, OutputFormat::Csv);
+        assert_eq!(config.max_depth, Some(4));
+        assert!(config.quiet);
+    }
+
+    #[test]
+    fn explicit_config_file_can_set_root() {
+        let temp_dir = TempDir::new().unwrap();
+        let library = temp_dir.path().join("library");
+        fs::create_dir(&library).unwrap();
+        let config_path = temp_dir.path().join("settings.toml");
+        fs::write(&config_path, "root = \"library\"\n").unwrap();
+
+        let args = parse_args(&["--config", config_path.to_str().unwrap()]);
+        let config = Config::from_args(args).expect("config");
+
+        assert_eq!(config.roots, vec![library.clone()]);
+        assert_eq!(config.output, library.join("lyrics.txt"));
+    }
+
+    #[test]
+    fn malformed_config_file_names_path() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join("broken.toml");
+        fs::write(&config_path, "max_depth = \"deep\"\n").unwrap();
+
+        let args = parse_args(&["--config", config_path.to_str().unwrap()]);
+        let error = Config::from_args(args).unwrap_err();
+        assert!(
+            format!("{error:#}").contains(config_path.to_str().unwrap()),
+            "unexpected error message: {error:#}"
+        );
+    }
+
     struct CwdGuard {
         original: PathBuf,
     }
@@ -235,18 +1631,162 @@ mod tests {
             let _ = std::env::set_current_dir(&self.original);
         }
     }
-}
-fn parse_extensions(raw: String) -> Vec<String> {
-    let mut exts: Vec<String> = raw
-        .split(',')
-        .map(|ext| ext.trim())
-        .filter(|ext| !ext.is_empty())
-        .map(|ext| ext.trim_start_matches('.').to_ascii_lowercase())
-        .collect();
 
-    if exts.is_empty() {
-        exts.push("mp3".to_string());
+    #[test]
+    fn artist_fuzzy_uses_filter_as_needle_and_rejects_regex() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().to_str().unwrap();
+
+        let args = parse_args(&[
+            "--root",
+            root,
+            "--artist-filter",
+            "Studio Band",
+            "--artist-fuzzy",
+            "0.8",
+        ]);
+        let filters = Config::from_args(args).expect("config").filters();
+        assert!(filters.artist.matches("Stuido Bnad"));
+        assert!(!filters.artist.matches("Orchestra"));
+
+        let args = parse_args(&["--root", root, "--artist-fuzzy", "1.5"]);
+        assert!(Config::from_args(args).is_err());
+
+        let result = CliArgs::try_parse_from([
+            "mdlyricgetter",
+            "--artist-regex",
+            "^Studio",
+            "--artist-fuzzy",
+            "0.8",
+        ]);
+        assert!(result.is_err());
     }
 
-    exts
+    #[test]
+    fn artist_filter_any_ignores_empty_entries() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().to_str().unwrap();
+
+        let config = Config::from_args(parse_args(&[
+            "--root",
+            root,
+            "--artist-filter-any",
+            "udio, ,suno,",
+        ]))
+        .expect("config");
+        assert_eq!(config.artist_filter_any, ["udio", "suno"]);
+        let filters = config.filters();
+        assert!(filters.artist.matches("Suno Sessions"));
+        assert!(filters.artist.matches("Udio Band"));
+        assert!(!filters.artist.matches("Orchestra"));
+
+        let blank = Config::from_args(parse_args(&["--root", root, "--artist-filter-any", ","]));
+        assert!(blank.is_err());
+
+        let result = CliArgs::try_parse_from([
+            "mdlyricgetter",
+            "--artist-filter",
+            "udio",
+            "--artist-filter-any",
+            "suno",
+        ]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn utf16_output_is_rejected_for_json() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().to_str().unwrap();
+
+        let config = Config::from_args(parse_args(&[
+            "--root",
+            root,
+            "--output-encoding",
+            "utf16le",
+        ]))
+        .unwrap();
+        assert_eq!(config.output_encoding, OutputEncoding::Utf16le);
+
+        let json = Config::from_args(parse_args(&[
+            "--root",
+            root,
+            "--output-encoding",
+            "utf16le",
+            "--format",
+            "json",
+        ]))
+        .unwrap_err();
+        assert!(format!("{json:#}").contains("always UTF-8"));
+    }
+
+    #[test]
+    fn verbosity_flags_map_to_log_levels() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().to_str().unwrap();
+        let level = |extra: &[&str]| {
+            let mut args = vec!["--root", root];
+            args.extend_from_slice(extra);
+            Config::from_args(parse_args(&args)).unwrap().log_level
+        };
+
+        assert_eq!(level(&[]), None);
+        assert_eq!(level(&["--quiet"]), Some(LevelFilter::Error));
+        assert_eq!(level(&["-v"]), Some(LevelFilter::Debug));
+        assert_eq!(level(&["-vvv"]), Some(LevelFilter::Trace));
+        assert_eq!(
+            level(&["-v", "--log-level", "warn"]),
+            Some(LevelFilter::Warn)
+        );
+        assert!(CliArgs::try_parse_from(["mdlyricgetter", "--quiet", "-v"]).is_err());
+    }
+
+    #[test]
+    fn queue_size_defaults_and_rejects_zero() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().to_str().unwrap();
+
+        let config = Config::from_args(parse_args(&["--root", root])).unwrap();
+        assert_eq!(config.queue_size, DEFAULT_QUEUE_SIZE);
+        let config = Config::from_args(parse_args(&["--root", root, "--queue-size", "8"])).unwrap();
+        assert_eq!(config.queue_size, 8);
+        assert!(Config::from_args(parse_args(&["--root", root, "--queue-size", "0"])).is_err());
+    }
+
+    #[test]
+    fn artist_exclude_wins_over_the_artist_filter() {
+        let temp_dir = TempDir::new().unwrap();
+        let args = parse_args(&[
+            "--root",
+            temp_dir.path().to_str().unwrap(),
+            "--artist-exclude",
+            "karaoke",
+            "--artist-exclude",
+            " ",
+            "--artist-exclude",
+            "Instrumental",
+        ]);
+        let config = Config::from_args(args).unwrap();
+        assert_eq!(config.artist_exclude, ["karaoke", "Instrumental"]);
+
+        let filters = config.filters();
+        assert!(filters.accepts_artist("Udio Band"));
+        assert!(!filters.accepts_artist("Udio KARAOKE Versions"));
+        assert!(!filters.accepts_artist("Udio instrumentals"));
+        assert!(!filters.accepts_artist("Other Band"));
+    }
+
+    #[test]
+    fn inverted_duration_range_yields_error() {
+        let temp_dir = TempDir::new().unwrap();
+        let args = parse_args(&[
+            "--root",
+            temp_dir.path().to_str().unwrap(),
+            "--min-duration",
+            "300",
+            "--max-duration",
+            "60",
+        ]);
+        let error = Config::from_args(args).unwrap_err();
+        assert!(error.to_string().contains("--min-duration 300 exceeds"));
+    }
 }
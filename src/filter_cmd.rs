@@ -0,0 +1,90 @@
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use anyhow::{bail, Context, Result};
+
+use crate::metadata::{self, TrackMetadata};
+
+/// Pipes the track's lyrics through `command`, run by the platform shell, and returns its stdout
+/// as the new lyrics. The source path, artist and title are exported to the command as
+/// `MDLYRICGETTER_PATH`, `MDLYRICGETTER_ARTIST` and `MDLYRICGETTER_TITLE`. A nonzero exit or
+/// empty output is an error, so the caller can keep the original text.
+pub fn run(command: &str, source: &Path, track: &TrackMetadata) -> Result<String> {
+    let mut child = shell(command)
+        .env("MDLYRICGETTER_PATH", source)
+        .env("MDLYRICGETTER_ARTIST", &track.artist)
+        .env("MDLYRICGETTER_TITLE", &track.title)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .spawn()
+        .with_context(|| format!("failed to start --filter-cmd '{command}'"))?;
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+
+    // Feed stdin from a second thread, so a command that writes output before it has read all of
+    // its input cannot deadlock against us on full pipes.
+    let output = std::thread::scope(|scope| {
+        scope.spawn(move || {
+            // A command that exits without reading everything closes the pipe; its exit status
+            // decides whether the run failed.
+            let _ = stdin.write_all(track.lyrics.as_bytes());
+        });
+        child.wait_with_output()
+    })
+    .with_context(|| format!("failed to run --filter-cmd '{command}'"))?;
+
+    if !output.status.success() {
+        bail!("--filter-cmd '{command}' failed ({})", output.status);
+    }
+    let lyrics = metadata::normalize_line_endings(&String::from_utf8_lossy(&output.stdout))
+        .trim()
+        .to_owned();
+    if lyrics.is_empty() {
+        bail!("--filter-cmd '{command}' produced no output");
+    }
+    Ok(lyrics)
+}
+
+#[cfg(unix)]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(command);
+    shell
+}
+
+#[cfg(windows)]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("cmd");
+    shell.arg("/C").arg(command);
+    shell
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    use crate::metadata::sample_metadata;
+
+    #[test]
+    fn replaces_lyrics_with_command_output() {
+        let lyrics = run("tr a-z A-Z", Path::new("song.mp3"), &sample_metadata()).unwrap();
+        assert_eq!(lyrics, "LINE ONE\nLINE TWO");
+
+        let title = run(
+            "cat >/dev/null; printf '%s by %s\\r\\n' \"$MDLYRICGETTER_TITLE\" \"$MDLYRICGETTER_ARTIST\"",
+            Path::new("song.mp3"),
+            &sample_metadata(),
+        )
+        .unwrap();
+        assert_eq!(title, "Echoes by Studio Band");
+    }
+
+    #[test]
+    fn failing_or_silent_commands_are_errors() {
+        let failed = run("exit 3", Path::new("song.mp3"), &sample_metadata()).unwrap_err();
+        assert!(format!("{failed:#}").contains("failed"));
+
+        assert!(run("cat >/dev/null", Path::new("song.mp3"), &sample_metadata()).is_err());
+    }
+}
@@ -0,0 +1,259 @@
+use std::fs::File;
+use std::io::{ErrorKind, Read, Seek, SeekFrom};
+use std::ops::Range;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use md5::{Digest, Md5};
+
+use crate::cli::HashAlgorithm;
+use crate::reader::TagFormat;
+use crate::{mp4, mpeg, vorbis};
+
+/// Bytes read per chunk, so large files are hashed without holding them in memory.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Lowercase hex digest of the audio in the file at `path`, so copies that differ only in their
+/// tags hash alike; `None` for `HashAlgorithm::None`. ID3 files skip a leading ID3v2 and a
+/// trailing ID3v1 tag, WAV files hash only their `data` chunk, MP4 files their `mdat` atoms and
+/// Ogg files the bodies of their audio pages.
+pub fn hash_file(path: &Path, algorithm: HashAlgorithm) -> Result<Option<String>> {
+    let mut hasher = match algorithm {
+        HashAlgorithm::None => return Ok(None),
+        HashAlgorithm::Md5 => Hasher::Md5(Md5::new()),
+        HashAlgorithm::Blake3 => Hasher::Blake3(Box::new(blake3::Hasher::new())),
+    };
+    let mut file =
+        File::open(path).with_context(|| format!("failed to open '{}'", path.display()))?;
+    let audio = match TagFormat::from_path(path) {
+        TagFormat::Id3 => mpeg::audio_range(&mut file).map(|range| vec![range]),
+        TagFormat::Wav => wav_data_range(&mut file).map(|range| vec![range]),
+        TagFormat::Mp4 => mp4::media_data_ranges(&mut file),
+        TagFormat::Vorbis => vorbis::audio_page_ranges(&mut file),
+    }
+    .with_context(|| format!("failed to find the audio in '{}'", path.display()))?;
+
+    let mut chunk = vec![0_u8; CHUNK_SIZE];
+    for range in audio {
+        file.seek(SeekFrom::Start(range.start))
+            .with_context(|| format!("failed to seek in '{}'", path.display()))?;
+        let mut audio = (&file).take(range.end - range.start);
+        loop {
+            let read = match audio.read(&mut chunk) {
+                Ok(0) => break,
+                Ok(read) => read,
+                Err(error) if error.kind() == ErrorKind::Interrupted => continue,
+                Err(error) => {
+                    return Err(error)
+                        .with_context(|| format!("failed to read '{}'", path.display()))
+                }
+            };
+            hasher.update(&chunk[..read]);
+        }
+    }
+    Ok(Some(hasher.finish()))
+}
+
+fn whole_file(file: &File) -> Result<Range<u64>> {
+    Ok(0..file
+        .metadata()
+        .context("failed to read the file length")?
+        .len())
+}
+
+/// Byte range of the `data` chunk of a RIFF WAVE file, or the whole file when it has none.
+fn wav_data_range(file: &mut File) -> Result<Range<u64>> {
+    let whole = whole_file(file)?;
+    let mut header = [0_u8; 12];
+    match file.read_exact(&mut header) {
+        Ok(()) if &header[..4] == b"RIFF" && &header[8..] == b"WAVE" => {}
+        Ok(()) => return Ok(whole),
+        Err(error) if error.kind() == ErrorKind::UnexpectedEof => return Ok(whole),
+        Err(error) => return Err(error).context("failed to read the RIFF header"),
+    }
+
+    let mut offset = header.len() as u64;
+    while offset + 8 <= whole.end {
+        let mut chunk = [0_u8; 8];
+        file.seek(SeekFrom::Start(offset))
+            .and_then(|_| file.read_exact(&mut chunk))
+            .context("failed to read a RIFF chunk header")?;
+        let size = u64::from(u32::from_le_bytes([chunk[4], chunk[5], chunk[6], chunk[7]]));
+        let body = offset + 8;
+        if &chunk[..4] == b"data" {
+            return Ok(body..(body + size).min(whole.end));
+        }
+        // Chunks are padded to an even length.
+        offset = body + size + (size & 1);
+    }
+    Ok(whole)
+}
+
+enum Hasher {
+    Md5(Md5),
+    Blake3(Box<blake3::Hasher>),
+}
+
+impl Hasher {
+    fn update(&mut self, bytes: &[u8]) {
+        match self {
+            Self::Md5(hasher) => hasher.update(bytes),
+            Self::Blake3(hasher) => {
+                hasher.update(bytes);
+            }
+        }
+    }
+
+    fn finish(self) -> String {
+        match self {
+            Self::Md5(hasher) => format!("{:x}", hasher.finalize()),
+            Self::Blake3(hasher) => hasher.finalize().to_hex().to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use id3::TagLike;
+
+    use super::*;
+
+    #[test]
+    fn hashes_file_contents() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let path = temp.path().join("song.mp3");
+        std::fs::write(&path, b"abc").unwrap();
+
+        assert_eq!(hash_file(&path, HashAlgorithm::None).unwrap(), None);
+        assert_eq!(
+            hash_file(&path, HashAlgorithm::Md5).unwrap().as_deref(),
+            Some("900150983cd24fb0d6963f7d28e17f72")
+        );
+        assert_eq!(
+            hash_file(&path, HashAlgorithm::Blake3).unwrap().as_deref(),
+            Some("6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85")
+        );
+    }
+
+    #[test]
+    fn hashes_files_larger_than_one_chunk() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let path = temp.path().join("big.mp3");
+        let bytes: Vec<u8> = (0..CHUNK_SIZE * 2 + 7).map(|i| i as u8).collect();
+        std::fs::write(&path, &bytes).unwrap();
+
+        assert_eq!(
+            hash_file(&path, HashAlgorithm::Md5).unwrap(),
+            Some(format!("{:x}", Md5::digest(&bytes)))
+        );
+    }
+
+    #[test]
+    fn differently_tagged_mp3s_of_the_same_audio_hash_alike() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let audio = b"\xFF\xFBsame audio frames";
+        let tagged = |name: &str, title: &str, v1_title: Option<&[u8]>| {
+            let path = temp.path().join(name);
+            let mut bytes = audio.to_vec();
+            if let Some(v1_title) = v1_title {
+                let mut v1 = b"TAG".to_vec();
+                v1.extend_from_slice(v1_title);
+                v1.resize(128, 0);
+                bytes.extend_from_slice(&v1);
+            }
+            std::fs::write(&path, bytes).unwrap();
+            let mut tag = id3::Tag::new();
+            tag.set_title(title);
+            tag.write_to_path(&path, id3::Version::Id3v24).unwrap();
+            hash_file(&path, HashAlgorithm::Md5).unwrap()
+        };
+
+        let expected = Some(format!("{:x}", Md5::digest(audio)));
+        assert_eq!(tagged("a.mp3", "Original", None), expected);
+        assert_eq!(tagged("b.mp3", "Remastered Tag", Some(b"Old")), expected);
+    }
+
+    #[test]
+    fn wav_hash_covers_only_the_data_chunk() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let wav = |name: &str, tag_chunk: &[u8]| {
+            let mut chunks = Vec::new();
+            for (id, body) in [(b"id3 ", tag_chunk), (b"data", b"pcm".as_slice())] {
+                chunks.extend_from_slice(id);
+                chunks.extend_from_slice(&(body.len() as u32).to_le_bytes());
+                chunks.extend_from_slice(body);
+                if body.len() % 2 == 1 {
+                    chunks.push(0);
+                }
+            }
+            let mut bytes = b"RIFF".to_vec();
+            bytes.extend_from_slice(&(4 + chunks.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(b"WAVE");
+            bytes.extend_from_slice(&chunks);
+            let path = temp.path().join(name);
+            std::fs::write(&path, bytes).unwrap();
+            hash_file(&path, HashAlgorithm::Md5).unwrap()
+        };
+
+        let expected = Some(format!("{:x}", Md5::digest(b"pcm")));
+        assert_eq!(wav("a.wav", b"first tag"), expected);
+        assert_eq!(wav("b.wav", b"a longer second tag"), expected);
+    }
+
+    #[test]
+    fn mp4_hash_covers_only_the_mdat_atoms() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let atom = |kind: &[u8], body: &[u8]| {
+            let mut bytes = ((body.len() + 8) as u32).to_be_bytes().to_vec();
+            bytes.extend_from_slice(kind);
+            bytes.extend_from_slice(body);
+            bytes
+        };
+        let m4a = |name: &str, moov: &[u8]| {
+            let bytes = [
+                atom(b"ftyp", b"M4A "),
+                atom(b"moov", moov),
+                atom(b"mdat", b"aac"),
+                atom(b"mdat", b" frames"),
+            ]
+            .concat();
+            let path = temp.path().join(name);
+            std::fs::write(&path, bytes).unwrap();
+            hash_file(&path, HashAlgorithm::Md5).unwrap()
+        };
+
+        let expected = Some(format!("{:x}", Md5::digest(b"aac frames")));
+        assert_eq!(m4a("a.m4a", b"first tag"), expected);
+        assert_eq!(m4a("b.m4a", b"a longer second tag"), expected);
+    }
+
+    #[test]
+    fn ogg_hash_covers_only_the_audio_pages() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let page = |granule: u64, body: &[u8]| {
+            let mut bytes = b"OggS\0\0".to_vec();
+            bytes.extend_from_slice(&granule.to_le_bytes());
+            bytes.extend_from_slice(&[7, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+            bytes.push(1);
+            bytes.push(body.len() as u8);
+            bytes.extend_from_slice(body);
+            bytes
+        };
+        let ogg = |name: &str, comments: &[u8]| {
+            let bytes = [
+                page(0, b"\x01vorbis"),
+                page(0, comments),
+                page(960, b"opus"),
+                page(1920, b" packets"),
+            ]
+            .concat();
+            let path = temp.path().join(name);
+            std::fs::write(&path, bytes).unwrap();
+            hash_file(&path, HashAlgorithm::Md5).unwrap()
+        };
+
+        let expected = Some(format!("{:x}", Md5::digest(b"opus packets")));
+        assert_eq!(ogg("a.ogg", b"\x03vorbis first"), expected);
+        assert_eq!(ogg("b.ogg", b"\x03vorbis a longer second"), expected);
+    }
+}
@@ -0,0 +1,806 @@
+//! Library entry points for embedding the scan in another program: build a [`Config`] (from
+//! [`cli::CliArgs`] or field by field) and pass it to [`run`].
+
+pub mod cli;
+pub mod config;
+mod filter_cmd;
+mod hash;
+pub mod metadata;
+mod mp4;
+mod mpeg;
+mod prompt;
+pub mod reader;
+pub mod report;
+pub mod scanner;
+pub mod state;
+pub mod template;
+mod vorbis;
+mod writer;
+
+pub use config::Config;
+pub use metadata::extract_metadata;
+pub use report::Report;
+pub use scanner::Scanner;
+
+use anyhow::{Context, Result};
+use indicatif::{ProgressBar, ProgressStyle};
+use rayon::iter::{ParallelBridge, ParallelIterator};
+use std::collections::{BTreeMap, HashSet};
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Condvar, Mutex, PoisonError};
+use std::time::Instant;
+
+/// Scans every root in `config`, writes matched lyrics to the configured output, and returns
+/// the run's counters. Logging is left to the caller; only `--summary-json` is written here.
+pub fn run(config: Config) -> Result<Report> {
+    let started = Instant::now();
+    // Read before the writer opens the file, which may add a header or array bracket.
+    let existing_tracks = config
+        .skip_existing
+        .then(|| writer::read_written_tracks(&config.output, config.output_format))
+        .transpose()?;
+    let writer = if config.dry_run_show {
+        // Split output has no combined format; preview its entries as text blocks.
+        if config.split_output {
+            writer::OutputWriter::preview(cli::OutputFormat::Text)
+        } else {
+            writer::OutputWriter::preview(config.output_format)
+                .with_template(config.template.clone())
+        }
+    } else if config.split_output {
+        writer::OutputWriter::split(config.dry_run)
+    } else if let Some(dir) = &config.split_by_artist {
+        writer::OutputWriter::by_artist(
+            dir,
+            config.output_format,
+            config.dry_run,
+            config.overwrite,
+        )?
+        .with_template(config.template.clone())
+        .with_encoding(config.output_encoding)
+    } else if let Some(dir) = &config.mirror_output {
+        writer::OutputWriter::mirror(
+            dir,
+            &config.roots,
+            config.output_format,
+            config.dry_run,
+            config.overwrite,
+        )?
+        .with_template(config.template.clone())
+        .with_encoding(config.output_encoding)
+    } else if config.atomic && !config.dry_run {
+        writer::OutputWriter::create_atomic(&config.output, config.output_format)?
+            .with_template(config.template.clone())
+            .with_encoding(config.output_encoding)
+    } else {
+        writer::OutputWriter::create(
+            &config.output,
+            config.output_format,
+            config.dry_run,
+            config.overwrite,
+        )?
+        .with_template(config.template.clone())
+        .with_encoding(config.output_encoding)
+    };
+    let writer = writer
+        .with_relative_paths(config.relative_paths.then(|| config.roots.clone()))
+        .with_json_pretty(config.json_pretty);
+    let scanner = Scanner::new(
+        &config.roots,
+        config.min_depth,
+        config.max_depth,
+        config.follow_symlinks,
+        config.extensions.clone(),
+        config.exclude.clone(),
+        config.include.clone(),
+    )
+    .with_modified_since(config.modified_since);
+    let filters = config.filters();
+    let previous_state = config
+        .state
+        .as_deref()
+        .map(state::Manifest::load)
+        .transpose()?;
+    let matched_list = match (&config.matched_list, config.dry_run) {
+        (Some(path), false) => Some(writer::MatchedList::create(path)?),
+        _ => None,
+    };
+    let mut collector = Collector {
+        writer,
+        matched_list,
+        report: Report::default(),
+        seen_tracks: config.dedupe.then(HashSet::new),
+        seen_hashes: config.dedupe.then(HashSet::new),
+        existing_tracks,
+        excluded_tracks: config.excluded_tracks.clone(),
+        progress: progress_bar(&config),
+        preview: config.preview,
+        count_only: config.count_only,
+        interactive: config.interactive,
+        limit: config.limit,
+        max_files: config.max_files,
+        // Carried over whole, so files this run did not visit keep their stamps.
+        state: previous_state.clone(),
+    };
+
+    let entries: Box<dyn Iterator<Item = FoundEntry> + Send + '_> =
+        match (&config.from_file, &config.git_since) {
+            (Some(list), _) => Box::new(scanner::listed_files(
+                scanner::read_file_list(list)?.into_iter().map(Ok),
+                config.extensions.clone(),
+            )),
+            (None, Some(rev)) => Box::new(scanner::listed_files(
+                scanner::git_changed_files(&config.roots, rev)?
+                    .into_iter()
+                    .map(Ok),
+                config.extensions.clone(),
+            )),
+            (None, None) if config.stdin0 => Box::new(scanner::listed_files(
+                scanner::stdin_paths_nul(),
+                config.extensions.clone(),
+            )),
+            (None, None) => Box::new(scanner.walk()),
+        };
+
+    if config.jobs <= 1 {
+        for entry in entries {
+            let scanned = entry.map(|found| scan_file(found, &filters, previous_state.as_ref()));
+            collector.record_entry(scanned)?;
+            if collector.should_stop() {
+                break;
+            }
+        }
+    } else {
+        scan_parallel(
+            entries,
+            config.jobs,
+            config.queue_size,
+            &filters,
+            previous_state.as_ref(),
+            &mut collector,
+        )?;
+    }
+
+    if collector.max_files_reached() && !collector.limit_reached() {
+        log::warn!(
+            "Stopped early after examining {} files (--max-files); the rest of the library was not scanned.",
+            collector.report.scanned
+        );
+    }
+
+    let Collector {
+        mut writer,
+        matched_list,
+        mut report,
+        progress,
+        state,
+        ..
+    } = collector;
+    if let Some(progress) = progress {
+        progress.finish_and_clear();
+    }
+
+    let depth_skipped = scanner.skipped_due_to_depth();
+    if depth_skipped > 0 && config.no_recurse {
+        log::debug!("--no-recurse left out {depth_skipped} subdirectories.");
+    } else if depth_skipped > 0 {
+        let skipped_paths = scanner.depth_skipped_paths();
+        report.record_depth_skips(depth_skipped, skipped_paths.clone());
+        if let Some(limit) = config.max_depth {
+            log::warn!("Max depth {limit} prevented descending into {depth_skipped} directories.");
+            for path in skipped_paths {
+                log::info!("Skipped due to depth limit: {}", path.display());
+            }
+        }
+    }
+
+    writer.flush()?;
+    if let Some(mut matched_list) = matched_list {
+        matched_list.flush()?;
+    }
+    if let (Some(manifest), Some(path), false) = (&state, &config.state, config.dry_run) {
+        manifest.save(path)?;
+    }
+    report.record_elapsed(started.elapsed());
+
+    if let Some(summary_path) = &config.summary_json {
+        let summary = if config.relative_paths {
+            report.relative_summary(&config.roots)
+        } else {
+            report.summary()
+        };
+        write_summary(summary_path, &summary)?;
+    }
+    if let Some(errors_path) = &config.errors_json {
+        if config.relative_paths {
+            let errors = report.relative_errors(&config.roots);
+            write_json_file(errors_path, &errors, "error report")?;
+        } else {
+            write_json_file(errors_path, &report.errors, "error report")?;
+        }
+    }
+
+    Ok(report)
+}
+
+/// Spinner on stderr for `--progress`; the total is unknown while walking, so it only counts.
+fn progress_bar(config: &Config) -> Option<ProgressBar> {
+    if !config.progress || config.quiet || config.interactive || !std::io::stderr().is_terminal() {
+        return None;
+    }
+
+    let progress = ProgressBar::new_spinner();
+    progress.set_style(
+        ProgressStyle::with_template("{spinner} {elapsed} {msg}").expect("valid progress template"),
+    );
+    progress.enable_steady_tick(std::time::Duration::from_millis(120));
+    Some(progress)
+}
+
+/// Result of inspecting a single file, computed off the main thread when `--jobs` > 1.
+enum FileOutcome {
+    Matched(metadata::TrackMetadata),
+    MissingLyrics {
+        artist: String,
+        title: String,
+    },
+    ArtistMismatch,
+    /// The artist matched but the title, year or genre filter did not.
+    Filtered,
+    /// `--state` recorded the same mtime and size on a previous run; the tags were not read.
+    Unchanged,
+    /// Smaller than `--min-size`, e.g. an empty partial download; the tags were not read.
+    TooSmall,
+    /// Passed the artist and tag filters, but its length is outside the `--min-duration` range.
+    OutsideDuration,
+    TagError(anyhow::Error),
+}
+
+/// A walked file with its outcome and, under `--state`, the stamp to record for it.
+struct ScannedFile {
+    path: PathBuf,
+    size: Option<u64>,
+    stamp: Option<state::FileStamp>,
+    outcome: FileOutcome,
+}
+
+/// A file picked by the walk or `--from-file`, or the path that could not be visited.
+type FoundEntry = std::result::Result<scanner::FoundFile, scanner::VisitError>;
+
+type ScannedEntry = std::result::Result<ScannedFile, scanner::VisitError>;
+
+/// Everything fed by file outcomes: the output writer, the report, and run-wide match state.
+struct Collector {
+    writer: writer::OutputWriter,
+    /// `--matched-list` sink, unless this is a dry run.
+    matched_list: Option<writer::MatchedList>,
+    report: Report,
+    /// (artist, title) pairs found in the output before the run, under `--skip-existing`.
+    existing_tracks: Option<HashSet<(String, String)>>,
+    /// (artist, title) pairs from the `--exclude-from` reference.
+    excluded_tracks: Option<HashSet<(String, String)>>,
+    /// (artist, title) pairs already written, when `--dedupe` is set.
+    seen_tracks: Option<HashSet<(String, String)>>,
+    /// `--hash` digests already written, when `--dedupe` is set.
+    seen_hashes: Option<HashSet<String>>,
+    progress: Option<ProgressBar>,
+    /// Lyric lines logged per written track (`--preview`); 0 logs none.
+    preview: usize,
+    /// Ask on stderr before writing each match (`--interactive`).
+    interactive: bool,
+    /// Count matches without writing them (`--count-only`).
+    count_only: bool,
+    /// Stop scanning once this many tracks have been written (`--limit`).
+    limit: Option<usize>,
+    /// Stop scanning once this many files have been examined (`--max-files`).
+    max_files: Option<usize>,
+    /// Stamps of the files seen this run, saved to `--state` afterwards.
+    state: Option<state::Manifest>,
+}
+
+/// Fans tag reads out across a rayon pool while the calling thread keeps sole ownership of
+/// the collector. Results are numbered and put back in walk order before they are recorded, so
+/// the output matches `--jobs 1`. Reaching `--limit` or `--max-files` stops handing out entries
+/// and drops the receiver, which stops the workers at their next send. Workers only take entries
+/// within `queue_size` (or `jobs`, if larger) of the next one to record, so neither the channel
+/// nor the reorder buffer can grow to the whole library when one file is slow or the output disk
+/// is.
+fn scan_parallel(
+    entries: impl Iterator<Item = FoundEntry> + Send,
+    jobs: usize,
+    queue_size: usize,
+    filters: &metadata::Filters,
+    previous_state: Option<&state::Manifest>,
+    collector: &mut Collector,
+) -> Result<()> {
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(jobs)
+        .build()
+        .context("failed to start worker thread pool")?;
+    let window = ReorderWindow::new(queue_size.max(jobs));
+    let (sender, receiver) = mpsc::sync_channel::<(usize, ScannedEntry)>(queue_size);
+
+    std::thread::scope(|scope| {
+        let window = &window;
+        scope.spawn(move || {
+            pool.install(|| {
+                // A failed send means the receiver bailed out on an error; stop walking.
+                let _ = entries
+                    .enumerate()
+                    .take_while(|(index, _)| window.admit(*index))
+                    .par_bridge()
+                    .try_for_each_with(sender, |sender, (index, entry)| {
+                        let scanned = entry.map(|found| scan_file(found, filters, previous_state));
+                        sender.send((index, scanned)).map_err(|_| ())
+                    });
+            });
+        });
+
+        let recorded = record_in_order(receiver, window, collector);
+        window.stop();
+        recorded
+    })
+}
+
+/// Records results in index order, holding back those that finish ahead of an earlier one.
+fn record_in_order(
+    receiver: mpsc::Receiver<(usize, ScannedEntry)>,
+    window: &ReorderWindow,
+    collector: &mut Collector,
+) -> Result<()> {
+    let mut pending = BTreeMap::new();
+    let mut next = 0;
+    for (index, scanned) in receiver {
+        pending.insert(index, scanned);
+        while let Some(scanned) = pending.remove(&next) {
+            next += 1;
+            collector.record_entry(scanned)?;
+            if collector.should_stop() {
+                return Ok(());
+            }
+        }
+        window.advance(next);
+    }
+    Ok(())
+}
+
+/// Lets workers take entry `index` only while it is less than `size` past the next entry the
+/// collector will record.
+struct ReorderWindow {
+    state: Mutex<WindowState>,
+    advanced: Condvar,
+    size: usize,
+}
+
+struct WindowState {
+    next: usize,
+    stopped: bool,
+}
+
+impl ReorderWindow {
+    fn new(size: usize) -> Self {
+        Self {
+            state: Mutex::new(WindowState {
+                next: 0,
+                stopped: false,
+            }),
+            advanced: Condvar::new(),
+            size,
+        }
+    }
+
+    /// Waits until `index` fits in the window; `false` once the collector has stopped.
+    fn admit(&self, index: usize) -> bool {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        while !state.stopped && index >= state.next + self.size {
+            state = self
+                .advanced
+                .wait(state)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+        !state.stopped
+    }
+
+    fn advance(&self, next: usize) {
+        self.state
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .next = next;
+        self.advanced.notify_all();
+    }
+
+    fn stop(&self) {
+        self.state
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .stopped = true;
+        self.advanced.notify_all();
+    }
+}
+
+/// Skips files below `--min-size` and files `previous_state` has already seen unchanged;
+/// everything else is inspected.
+fn scan_file(
+    found: scanner::FoundFile,
+    filters: &metadata::Filters,
+    previous_state: Option<&state::Manifest>,
+) -> ScannedFile {
+    let scanner::FoundFile { path, size } = found;
+    let stamp = previous_state.and_then(|_| state::FileStamp::read(&path));
+    let outcome = match (previous_state, &stamp) {
+        _ if size.is_some_and(|size| size < filters.min_size) => FileOutcome::TooSmall,
+        (Some(previous), Some(stamp)) if previous.is_unchanged(&path, stamp) => {
+            FileOutcome::Unchanged
+        }
+        _ => inspect_file(&path, filters),
+    };
+
+    ScannedFile {
+        path,
+        size,
+        stamp,
+        outcome,
+    }
+}
+
+fn inspect_file(path: &Path, filters: &metadata::Filters) -> FileOutcome {
+    let mut outcome = inspect_tag(path, filters);
+    if filters.has_duration_range()
+        && matches!(
+            outcome,
+            FileOutcome::Matched(_) | FileOutcome::MissingLyrics { .. }
+        )
+        && !within_duration(path, filters)
+    {
+        return FileOutcome::OutsideDuration;
+    }
+    if filters.count_only {
+        return outcome;
+    }
+    if let (Some(command), FileOutcome::Matched(track)) = (&filters.filter_cmd, &mut outcome) {
+        if !track.lyrics.is_empty() {
+            match filter_cmd::run(command, path, track) {
+                Ok(lyrics) => track.lyrics = lyrics,
+                Err(error) => log::warn!(
+                    "Keeping the original lyrics of '{}': {error:#}",
+                    path.display()
+                ),
+            }
+        }
+    }
+    if let (Some(max_chars), FileOutcome::Matched(track)) = (filters.max_lyric_chars, &mut outcome)
+    {
+        if let Some(truncated) = metadata::truncate_lyrics(&track.lyrics, max_chars) {
+            log::warn!(
+                "Truncated the lyrics of '{}' to {max_chars} characters (--max-lyric-chars)",
+                path.display()
+            );
+            track.lyrics = truncated;
+        }
+    }
+    if let (true, FileOutcome::Matched(track)) = (filters.audio_properties, &mut outcome) {
+        track.audio = reader::read_audio_properties(path);
+    }
+    if let FileOutcome::Matched(track) = &mut outcome {
+        track.hash = hash::hash_file(path, filters.hash)
+            .inspect_err(|error| log::warn!("Could not hash '{}': {error:#}", path.display()))
+            .ok()
+            .flatten();
+    }
+    outcome
+}
+
+/// Fails open: a track whose length cannot be read, such as a non-MPEG file, is kept.
+fn within_duration(path: &Path, filters: &metadata::Filters) -> bool {
+    match reader::read_audio_properties(path) {
+        Some(audio) => filters.matches_duration(audio.duration_ms),
+        None => {
+            log::debug!(
+                "Keeping '{}': its duration could not be determined.",
+                path.display()
+            );
+            true
+        }
+    }
+}
+
+fn inspect_tag(path: &Path, filters: &metadata::Filters) -> FileOutcome {
+    let tag = match reader::AudioTag::read_with_retries(path, filters.read_retries) {
+        Ok(Some(tag)) => tag,
+        // A WAV file without an ID3 chunk has nothing to filter on, but is still a track.
+        Ok(None) => {
+            return FileOutcome::MissingLyrics {
+                artist: "Unknown Artist".to_string(),
+                title: "Unknown Title".to_string(),
+            }
+        }
+        Err(error) => return FileOutcome::TagError(error),
+    };
+
+    if filters.count_only {
+        let Some(artist) = tag.match_artist(filters) else {
+            return FileOutcome::ArtistMismatch;
+        };
+        if !tag.matches_filters(filters) {
+            return FileOutcome::Filtered;
+        }
+        return FileOutcome::Matched(tag.track_with_lyrics(artist, String::new()));
+    }
+    if let Some(track) = tag.extract_metadata(filters) {
+        return FileOutcome::Matched(track);
+    }
+
+    let Some(artist) = tag.match_artist(filters) else {
+        return FileOutcome::ArtistMismatch;
+    };
+    if !tag.matches_filters(filters) {
+        return FileOutcome::Filtered;
+    }
+    if filters.sidecar_lyrics {
+        if let Some(lyrics) = reader::read_sidecar_lyrics(path, filters.keep_timestamps)
+            .and_then(|lyrics| filters.finish_lyrics(lyrics))
+        {
+            let mut track = tag.track_with_lyrics(artist, lyrics);
+            track.source = filters.lyrics_source.then(|| "sidecar".to_string());
+            return FileOutcome::Matched(track);
+        }
+    }
+    if filters.include_empty {
+        return FileOutcome::Matched(tag.track_with_lyrics(artist, String::new()));
+    }
+    FileOutcome::MissingLyrics {
+        artist,
+        title: tag.resolve_title(),
+    }
+}
+
+impl Collector {
+    fn limit_reached(&self) -> bool {
+        self.limit.is_some_and(|limit| self.report.matched >= limit)
+    }
+
+    fn max_files_reached(&self) -> bool {
+        self.max_files
+            .is_some_and(|max_files| self.report.scanned >= max_files)
+    }
+
+    /// `--limit` or `--max-files` was hit, whichever came first.
+    fn should_stop(&self) -> bool {
+        self.limit_reached() || self.max_files_reached()
+    }
+
+    fn record_entry(&mut self, scanned: ScannedEntry) -> Result<()> {
+        match scanned {
+            Ok(ScannedFile {
+                path,
+                size,
+                stamp,
+                outcome,
+            }) => {
+                self.report.record_scan(&path, size);
+                // Files that failed to read stay out of the manifest so they are retried.
+                let retry = matches!(outcome, FileOutcome::TagError(_));
+                self.record_outcome(&path, outcome)?;
+                if let (Some(manifest), Some(stamp), false) = (self.state.as_mut(), stamp, retry) {
+                    manifest.record(path, stamp);
+                }
+            }
+            Err(error) => {
+                if let (Some(path), Some(ancestor)) = (error.path(), error.loop_ancestor()) {
+                    self.report.record_symlink_loop(path, ancestor);
+                    log::warn!(
+                        "Symlink loop: '{}' points back to '{}'; not descending into it",
+                        path.display(),
+                        ancestor.display()
+                    );
+                } else {
+                    self.report
+                        .record_walk_error(error.path(), error.to_string());
+                    let path = error.path().map(|p| p.display().to_string());
+                    match path {
+                        Some(path) => log::warn!("Traversal error on '{}': {error}", path),
+                        None => log::warn!("Traversal error: {error}"),
+                    }
+                }
+            }
+        }
+
+        if let Some(progress) = &self.progress {
+            progress.set_message(format!(
+                "scanned {scanned}, matched {matched}",
+                scanned = self.report.scanned,
+                matched = self.report.matched
+            ));
+        }
+        Ok(())
+    }
+
+    /// `--interactive` prompt for one match, on stdin and stderr.
+    fn confirm(&self, path: &Path, track: &metadata::TrackMetadata) -> Result<bool> {
+        let question = format!(
+            "Write '{title}' by {artist} ({file})?",
+            title = track.title,
+            artist = track.artist,
+            file = path.display()
+        );
+        prompt::confirm(std::io::stdin().lock(), std::io::stderr(), &question)
+    }
+
+    fn record_outcome(&mut self, path: &Path, outcome: FileOutcome) -> Result<()> {
+        match outcome {
+            FileOutcome::Matched(track) => {
+                if let Some(existing) = &self.existing_tracks {
+                    if existing.contains(&(track.artist.clone(), track.title.clone())) {
+                        self.report.record_skipped_existing();
+                        log::info!(
+                            "Skipping '{title}' by {artist} in file '{file}' -- already in the output.",
+                            title = track.title,
+                            artist = track.artist,
+                            file = path.display()
+                        );
+                        return Ok(());
+                    }
+                }
+                if let Some(excluded) = &self.excluded_tracks {
+                    if excluded.contains(&(track.artist.clone(), track.title.clone())) {
+                        self.report.record_excluded();
+                        log::info!(
+                            "Skipping '{title}' by {artist} in file '{file}' -- listed in the --exclude-from reference.",
+                            title = track.title,
+                            artist = track.artist,
+                            file = path.display()
+                        );
+                        return Ok(());
+                    }
+                }
+                if let Some(seen) = self.seen_tracks.as_mut() {
+                    if !seen.insert((track.artist.clone(), track.title.clone())) {
+                        self.report.record_duplicate();
+                        log::info!(
+                            "Skipping duplicate '{title}' by {artist} in file '{file}'",
+                            title = track.title,
+                            artist = track.artist,
+                            file = path.display()
+                        );
+                        return Ok(());
+                    }
+                }
+                if let (Some(seen), Some(hash)) = (self.seen_hashes.as_mut(), &track.hash) {
+                    if !seen.insert(hash.clone()) {
+                        self.report.record_duplicate();
+                        log::info!(
+                            "Skipping '{title}' by {artist} in file '{file}' -- same audio hash as an earlier file",
+                            title = track.title,
+                            artist = track.artist,
+                            file = path.display()
+                        );
+                        return Ok(());
+                    }
+                }
+                if self.count_only {
+                    self.report.record_counted_match(path, &track.artist);
+                    return Ok(());
+                }
+                if self.interactive && !self.confirm(path, &track)? {
+                    self.report.record_rejected();
+                    log::info!(
+                        "Skipping '{title}' by {artist} in file '{file}' -- rejected at the prompt.",
+                        title = track.title,
+                        artist = track.artist,
+                        file = path.display()
+                    );
+                    return Ok(());
+                }
+
+                self.writer.write_entry(path, &track)?;
+                if let Some(matched_list) = self.matched_list.as_mut() {
+                    matched_list.record(path)?;
+                }
+                self.report.record_match(path, &track);
+                if track.lyrics.is_empty() {
+                    log::info!(
+                        "Listed '{title}' by {artist} without lyrics",
+                        title = track.title,
+                        artist = track.artist
+                    );
+                } else {
+                    log::info!(
+                        "Captured lyrics for '{title}' by {artist}",
+                        title = track.title,
+                        artist = track.artist
+                    );
+                    if self.preview > 0 {
+                        let preview: Vec<String> = track
+                            .lyrics
+                            .lines()
+                            .take(self.preview)
+                            .map(|line| format!("    {line}"))
+                            .collect();
+                        log::info!("Preview of '{}':\n{}", track.title, preview.join("\n"));
+                    }
+                }
+            }
+            FileOutcome::MissingLyrics { artist, title } => {
+                self.report.record_missing_lyrics();
+                log::info!(
+                    "Skipping '{title}' by {artist} in file '{file}' -- no lyrics frames found.",
+                    title = title,
+                    artist = artist,
+                    file = path.display()
+                );
+            }
+            FileOutcome::ArtistMismatch => {
+                self.report.record_artist_skip();
+                log::debug!(
+                    "Skipping '{}': no artist or the artist does not match the filter.",
+                    path.display()
+                );
+            }
+            FileOutcome::Filtered => {
+                self.report.record_filtered();
+                log::debug!(
+                    "Skipping '{}': rejected by the title, year or genre filter.",
+                    path.display()
+                );
+            }
+            FileOutcome::Unchanged => {
+                self.report.record_unchanged();
+                log::debug!(
+                    "Skipping '{}': unchanged since the last run.",
+                    path.display()
+                );
+            }
+            FileOutcome::TooSmall => {
+                self.report.record_too_small();
+                log::debug!("Skipping '{}': smaller than --min-size.", path.display());
+            }
+            FileOutcome::OutsideDuration => {
+                self.report.record_outside_duration();
+                log::info!(
+                    "Skipping '{}': duration outside --min-duration/--max-duration.",
+                    path.display()
+                );
+            }
+            FileOutcome::TagError(error) => {
+                self.report.record_tag_error(path, format!("{error:#}"));
+                log::warn!("Failed to read tags from '{}': {error:#}", path.display());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Writes the pretty-printed summary to `path`, or to stdout when it is `-`.
+fn write_summary(path: &Path, summary: &report::Summary) -> Result<()> {
+    if path == Path::new(writer::STDOUT_SENTINEL) {
+        let mut stdout = std::io::stdout().lock();
+        serde_json::to_writer_pretty(&mut stdout, summary)
+            .map_err(std::io::Error::from)
+            .and_then(|_| writeln!(stdout))
+            .context("failed to write JSON summary to stdout")?;
+        return Ok(());
+    }
+
+    write_json_file(path, summary, "summary")
+}
+
+/// Pretty-prints `value` to `path`, creating missing parent directories; `what` names the
+/// report in error messages.
+fn write_json_file(path: &Path, value: &impl serde::Serialize, what: &str) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).with_context(|| {
+            format!(
+                "failed to create directories for {what} '{}'",
+                parent.display()
+            )
+        })?;
+    }
+    let file = std::fs::File::create(path)
+        .with_context(|| format!("failed to create {what} file '{}'", path.display()))?;
+    let writer = std::io::BufWriter::new(file);
+    serde_json::to_writer_pretty(writer, value)
+        .with_context(|| format!("failed to write JSON {what} to '{}'", path.display()))?;
+    Ok(())
+}
@@ -1,153 +1,135 @@
-mod cli;
-mod config;
-mod metadata;
-mod report;
-mod scanner;
-mod writer;
-
 use anyhow::{Context, Result};
+use env_logger::fmt::Formatter;
 use env_logger::Builder;
-use log::LevelFilter;
+use log::{LevelFilter, Record};
+use mdlyricgetter::cli::{self, LogFormat};
+use mdlyricgetter::Config;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
 use std::path::Path;
+use std::sync::{Mutex, PoisonError};
+
+/// Exit status for a successful `--fail-on-empty` run that matched nothing; errors exit with 1.
+const EXIT_NO_MATCHES: i32 = 2;
+/// Exit status for a `--strict` run that finished but could not read some files or directories.
+const EXIT_PARTIAL_FAILURE: i32 = 3;
 
 fn main() {
-    if let Err(error) = run() {
-        eprintln!("{error:?}");
-        std::process::exit(1);
+    match run() {
+        Ok(0) => {}
+        Ok(status) => std::process::exit(status),
+        Err(error) => {
+            eprintln!("{error:?}");
+            std::process::exit(1);
+        }
     }
 }
 
-fn run() -> Result<()> {
-    let cli_args = cli::CliArgs::parse();
-
-    let config = config::Config::from_args(cli_args)?;
-    init_logging(config.quiet);
-    let mut writer =
-        writer::OutputWriter::create(&config.output, config.output_format, config.dry_run)?;
-    let scanner = scanner::Scanner::new(
-        &config.root,
-        config.max_depth,
-        config.follow_symlinks,
-        config.extensions.clone(),
-    );
-    let mut report = report::Report::default();
-    let artist_filter = config.artist_filter.clone();
-
-    for entry in scanner.walk() {
-        match entry {
-            Ok(path) => {
-                report.record_scan();
-                process_file(&path, &artist_filter, &mut writer, &mut report)?;
-            }
-            Err(error) => {
-                report.record_walk_error();
-                let path = error.path().map(|p| p.display().to_string());
-                match path {
-                    Some(path) => log::warn!("Traversal error on '{}': {error}", path),
-                    None => log::warn!("Traversal error: {error}"),
-                }
-            }
-        }
+/// Returns the exit status of a run that finished: 0, or the status asked for by `--strict` or
+/// `--fail-on-empty` when their condition holds.
+fn run() -> Result<i32> {
+    let config = Config::from_args(cli::CliArgs::parse())?;
+    let log_file = config.log_file.as_deref().map(open_log_file).transpose()?;
+    init_logging(config.log_level, config.log_format, log_file, config.quiet);
+    let fail_on_empty = config.fail_on_empty;
+    let strict = config.strict;
+    let list_artists = config.list_artists;
+    let print_count = config.print_count;
+    let report = mdlyricgetter::run(config)?;
+    report.emit_summary();
+    if list_artists {
+        report.emit_matched_artists();
     }
-
-    let depth_skipped = scanner.skipped_due_to_depth();
-    if depth_skipped > 0 {
-        let skipped_paths = scanner.depth_skipped_paths();
-        report.record_depth_skips(depth_skipped, skipped_paths.clone());
-        if let Some(limit) = config.max_depth {
-            log::warn!("Max depth {limit} prevented descending into {depth_skipped} directories.");
-            for path in skipped_paths {
-                log::info!("Skipped due to depth limit: {}", path.display());
-            }
-        }
+    if print_count {
+        println!("matched={}", report.matched);
     }
-
-    writer.flush()?;
-    report.emit_summary();
-
-    if let Some(summary_path) = &config.summary_json {
-        write_summary(summary_path, &report)?;
+    if strict && (report.tag_errors > 0 || report.walk_errors > 0) {
+        log::error!(
+            "{} tag and {} traversal error(s); exiting with status {EXIT_PARTIAL_FAILURE} (--strict).",
+            report.tag_errors,
+            report.walk_errors
+        );
+        return Ok(EXIT_PARTIAL_FAILURE);
     }
-
-    Ok(())
-}
-
-fn init_logging(quiet: bool) {
-    let default_level = if quiet { "error" } else { "info" };
-
-    let mut builder =
-        Builder::from_env(env_logger::Env::default().default_filter_or(default_level));
-    if quiet {
-        builder.filter_level(LevelFilter::Error);
+    if fail_on_empty && report.matched == 0 {
+        log::error!("No tracks matched; exiting with status {EXIT_NO_MATCHES} (--fail-on-empty).");
+        return Ok(EXIT_NO_MATCHES);
     }
-    let _ = builder.try_init();
+    Ok(0)
 }
 
-fn process_file(
-    path: &Path,
-    artist_filter: &str,
-    writer: &mut writer::OutputWriter,
-    report: &mut report::Report,
-) -> Result<()> {
-    match id3::Tag::read_from_path(path) {
-        Ok(tag) => handle_tag(path, tag, artist_filter, writer, report),
-        Err(error) => {
-            report.record_tag_error();
-            log::warn!("Failed to read ID3 tags from '{}': {error}", path.display());
-            Ok(())
-        }
-    }
+/// Appends, so the logs of successive unattended runs accumulate in one file.
+fn open_log_file(path: &Path) -> Result<File> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("failed to open --log-file '{}'", path.display()))
 }
 
-fn handle_tag(
-    path: &Path,
-    tag: id3::Tag,
-    artist_filter: &str,
-    writer: &mut writer::OutputWriter,
-    report: &mut report::Report,
-) -> Result<()> {
-    match metadata::extract_metadata(&tag, artist_filter) {
-        Some(track) => {
-            writer.write_entry(&track)?;
-            report.record_match();
-            log::info!(
-                "Captured lyrics for '{title}' by {artist}",
-                title = track.title,
-                artist = track.artist
-            );
-        }
-        None => {
-            if let Some(artist) = metadata::match_artist(&tag, artist_filter) {
-                report.record_missing_lyrics();
-                let title = metadata::resolve_title(&tag);
-                log::info!(
-                    "Skipping '{title}' by {artist} in file '{file}' -- no lyrics frames found.",
-                    title = title,
-                    artist = artist,
-                    file = path.display()
-                );
-            } else {
-                report.record_artist_skip();
+/// An explicit `level` overrides `RUST_LOG`. Levels above info only apply to this crate, so
+/// `-vv` does not also turn on the trace output of every dependency. Each record also goes to
+/// `log_file` when one is given; `--quiet` then only limits stderr to errors, and the file keeps
+/// the info records.
+fn init_logging(
+    level: Option<LevelFilter>,
+    format: LogFormat,
+    log_file: Option<File>,
+    quiet: bool,
+) {
+    let level = if quiet && log_file.is_some() {
+        None
+    } else {
+        level
+    };
+    let mut builder = Builder::from_env(env_logger::Env::default().default_filter_or("info"));
+    if let Some(level) = level {
+        builder
+            .filter_level(level.min(LevelFilter::Info))
+            .filter_module("mdlyricgetter", level);
+    }
+    if let Some(file) = log_file {
+        let file = Mutex::new(file);
+        let stderr_level = if quiet {
+            LevelFilter::Error
+        } else {
+            LevelFilter::Trace
+        };
+        builder.format(move |buf, record| {
+            let line = format_record(buf, record, format);
+            writeln!(
+                file.lock().unwrap_or_else(PoisonError::into_inner),
+                "{line}"
+            )?;
+            if record.level() <= stderr_level {
+                writeln!(buf, "{line}")?;
             }
-        }
+            Ok(())
+        });
+    } else if format == LogFormat::Json {
+        builder
+            .format(|buf, record| writeln!(buf, "{}", format_record(buf, record, LogFormat::Json)));
     }
-
-    Ok(())
+    let _ = builder.try_init();
 }
 
-fn write_summary(path: &Path, report: &report::Report) -> Result<()> {
-    if let Some(parent) = path.parent() {
-        std::fs::create_dir_all(parent).with_context(|| {
-            format!(
-                "failed to create directories for summary '{}'",
-                parent.display()
-            )
-        })?;
+/// One log line without its newline. The human format mirrors `env_logger`'s default layout,
+/// minus the colours, so it reads the same in a log file.
+fn format_record(buf: &Formatter, record: &Record, format: LogFormat) -> String {
+    match format {
+        LogFormat::Human => format!(
+            "[{} {:<5} {}] {}",
+            buf.timestamp(),
+            record.level(),
+            record.target(),
+            record.args()
+        ),
+        LogFormat::Json => serde_json::json!({
+            "timestamp": buf.timestamp_millis().to_string(),
+            "level": record.level().as_str(),
+            "target": record.target(),
+            "message": record.args().to_string(),
+        })
+        .to_string(),
     }
-    let file = std::fs::File::create(path)
-        .with_context(|| format!("failed to create summary file '{}'", path.display()))?;
-    let writer = std::io::BufWriter::new(file);
-    serde_json::to_writer_pretty(writer, &report.summary())
-        .with_context(|| format!("failed to write JSON summary to '{}'", path.display()))?;
-    Ok(())
 }
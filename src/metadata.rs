@@ -1,8 +1,15 @@
+use std::borrow::Cow;
+
 use id3::{
-    frame::{Comment, Content, ExtendedText, Lyrics as LyricsFrame},
+    frame::{Comment, Content, ExtendedText, SynchronisedLyrics, TimestampFormat},
     Tag, TagLike,
 };
+use regex::Regex;
 use serde::{Deserialize, Serialize};
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::{is_nfc_quick, IsNormalized, UnicodeNormalization};
+
+use crate::cli::{ArtistFrame, ArtistSource, HashAlgorithm};
 
 pub const DEFAULT_ARTIST_FILTER: &str = "udio";
 
@@ -11,42 +18,416 @@ pub struct TrackMetadata {
     pub artist: String,
     pub title: String,
     pub lyrics: String,
+    #[serde(default)]
+    pub album: Option<String>,
+    #[serde(default)]
+    pub album_artist: Option<String>,
+    /// Technical details from `--audio-properties`; left out when not requested or unreadable.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audio: Option<AudioProperties>,
+    /// Comma-separated kinds of frame the lyrics came from (`uslt`, `txxx`, `comm`, `sylt`,
+    /// `text`, `mp4`, `vorbis` or `sidecar`), when `--lyrics-source` is set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    /// Hex digest of the file's audio with its tags left out, when `--hash` is set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+}
+
+/// Stream details read from the audio data rather than the tag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AudioProperties {
+    pub duration_ms: u64,
+    /// Average bitrate for VBR streams with a Xing/VBRI header, otherwise the first frame's.
+    pub bitrate_kbps: u32,
+    pub sample_rate_hz: u32,
+}
+
+/// How the resolved artist name is tested against the user's filter.
+#[derive(Debug, Clone)]
+pub enum ArtistMatcher {
+    /// Substring, compared case-insensitively unless `case_sensitive` is set, and ignoring
+    /// accents when `ascii_fold` is set; an empty needle matches every artist.
+    Substring {
+        needle: String,
+        case_sensitive: bool,
+        ascii_fold: bool,
+    },
+    /// Any of several substrings from `--artist-filter-any`, compared like `Substring`.
+    AnyOf {
+        needles: Vec<String>,
+        case_sensitive: bool,
+        ascii_fold: bool,
+    },
+    /// Regular expression compiled from `--artist-regex`.
+    Regex(Regex),
+    /// Jaro-Winkler similarity between the needle and the whole artist name, from
+    /// `--artist-fuzzy`; matches when the score is at least `threshold`.
+    Fuzzy {
+        needle: String,
+        threshold: f64,
+        case_sensitive: bool,
+    },
+}
+
+impl ArtistMatcher {
+    pub fn matches(&self, artist: &str) -> bool {
+        match self {
+            Self::Substring {
+                needle,
+                case_sensitive,
+                ascii_fold,
+            } => matches_artist(artist, needle, *case_sensitive, *ascii_fold),
+            Self::AnyOf {
+                needles,
+                case_sensitive,
+                ascii_fold,
+            } => needles
+                .iter()
+                .any(|needle| matches_artist(artist, needle, *case_sensitive, *ascii_fold)),
+            Self::Regex(pattern) => pattern.is_match(artist),
+            Self::Fuzzy {
+                needle,
+                threshold,
+                case_sensitive,
+            } => matches_artist_fuzzy(artist, needle, *threshold, *case_sensitive),
+        }
+    }
+}
+
+/// TXXX descriptions read as lyrics unless `--lyrics-tag` replaces them; taggers disagree on the
+/// name.
+pub const DEFAULT_LYRICS_TAGS: [&str; 4] = ["lyrics", "unsynced lyrics", "unsyncedlyrics", "lyric"];
+
+/// Track-level filters a tag must pass before its lyrics are collected.
+#[derive(Debug, Clone)]
+pub struct Filters {
+    pub artist: ArtistMatcher,
+    /// `--artist-exclude`: artists it matches are rejected even when `artist` matches them.
+    pub artist_exclude: Option<ArtistMatcher>,
+    /// Tag fields the matched artist is read from, in order.
+    pub artist_frames: Vec<ArtistFrame>,
+    /// `--artist-frames`: the first of `artist_frames` that passes the artist filters wins,
+    /// rather than only the first present one.
+    pub search_artist_frames: bool,
+    /// Case-insensitive substring required in the title, when set.
+    pub title: Option<String>,
+    /// Preferred USLT language code (e.g. `eng`); other languages are dropped when a frame in
+    /// this language exists.
+    pub lyrics_lang: Option<String>,
+    /// Keep lyrics in different languages apart under `[eng]`-style headers instead of merging.
+    pub by_language: bool,
+    /// TXXX descriptions holding lyrics, compared case-insensitively.
+    pub lyrics_tags: Vec<String>,
+    /// Emit millisecond-timed SYLT frames as `[mm:ss.xx]` LRC lines instead of flattening them.
+    pub keep_timestamps: bool,
+    /// Remove LRC `[mm:ss.xx]` timing and `[ar:...]`-style header lines from embedded lyrics.
+    pub strip_lrc_timestamps: bool,
+    /// When no lyrics frame is found, treat `COMM` frames with a blank description as lyrics.
+    pub loose_comments: bool,
+    /// Attach duration, bitrate and sample rate to matched tracks where they can be read.
+    pub audio_properties: bool,
+    /// Extra attempts at reading a tag after a transient I/O error.
+    pub read_retries: u32,
+    /// Content hash added to matched tracks.
+    pub hash: HashAlgorithm,
+    /// Files smaller than this many bytes are skipped before their tags are read; 0 keeps all.
+    pub min_size: u64,
+    /// Inclusive track length range in seconds; tracks of unknown length pass.
+    pub min_duration: Option<u64>,
+    pub max_duration: Option<u64>,
+    /// Squeeze runs of blank lines inside lyrics down to a single blank line.
+    pub collapse_blank_lines: bool,
+    /// Drop every blank line, so lyrics and the frames joined into them are single-spaced.
+    pub flatten: bool,
+    /// Fall back to `<stem>.lrc`/`<stem>.txt` beside the audio file when no lyrics are embedded.
+    pub sidecar_lyrics: bool,
+    /// Keep matching tracks that have no lyrics at all, with empty lyrics, instead of skipping them.
+    pub include_empty: bool,
+    /// `--count-only`: match on the artist and other tag filters without reading any lyrics.
+    pub count_only: bool,
+    /// `--filter-cmd`: shell command each matched track's lyrics are piped through.
+    pub filter_cmd: Option<String>,
+    /// Record where each track's lyrics came from in `TrackMetadata::source`.
+    pub lyrics_source: bool,
+    /// `--max-lyric-chars`: longer lyrics are cut to this many characters plus an ellipsis.
+    pub max_lyric_chars: Option<usize>,
+    /// Inclusive release-year bounds; tracks without a year fail whenever either bound is set.
+    pub year_min: Option<i32>,
+    pub year_max: Option<i32>,
+    /// Case-insensitive substring required in the genre, when set.
+    pub genre: Option<String>,
+}
+
+impl Default for Filters {
+    fn default() -> Self {
+        Self {
+            artist: ArtistMatcher::Substring {
+                needle: DEFAULT_ARTIST_FILTER.to_string(),
+                case_sensitive: false,
+                ascii_fold: false,
+            },
+            artist_exclude: None,
+            artist_frames: ArtistSource::Artist.frames().to_vec(),
+            search_artist_frames: false,
+            title: None,
+            lyrics_lang: None,
+            by_language: false,
+            lyrics_tags: DEFAULT_LYRICS_TAGS.map(str::to_owned).to_vec(),
+            keep_timestamps: false,
+            strip_lrc_timestamps: false,
+            loose_comments: false,
+            audio_properties: false,
+            read_retries: 0,
+            hash: HashAlgorithm::None,
+            min_size: 0,
+            min_duration: None,
+            max_duration: None,
+            collapse_blank_lines: false,
+            flatten: false,
+            sidecar_lyrics: false,
+            include_empty: false,
+            count_only: false,
+            filter_cmd: None,
+            lyrics_source: false,
+            max_lyric_chars: None,
+            year_min: None,
+            year_max: None,
+            genre: None,
+        }
+    }
+}
+
+impl Filters {
+    /// Applies the title, year and genre filters to values already read from a tag.
+    pub(crate) fn matches_track(
+        &self,
+        title: Option<&str>,
+        year: Option<i32>,
+        genre: Option<&str>,
+    ) -> bool {
+        matches_title(title, self.title.as_deref())
+            && self.matches_year(year)
+            && matches_title(genre, self.genre.as_deref())
+    }
+
+    /// The artist filter, then `--artist-exclude`, which takes precedence.
+    pub(crate) fn accepts_artist(&self, artist: &str) -> bool {
+        self.artist.matches(artist)
+            && !self
+                .artist_exclude
+                .as_ref()
+                .is_some_and(|exclude| exclude.matches(artist))
+    }
+
+    /// Applies lyric post-processing options; `None` when nothing is left afterwards.
+    pub(crate) fn finish_lyrics(&self, mut lyrics: String) -> Option<String> {
+        if self.strip_lrc_timestamps {
+            lyrics = strip_lrc_timestamps(&lyrics);
+        }
+        if self.collapse_blank_lines {
+            lyrics = collapse_blank_lines(&lyrics);
+        }
+        if self.flatten {
+            lyrics = flatten_lyrics(&lyrics);
+        }
+        (!lyrics.is_empty()).then_some(lyrics)
+    }
+
+    pub(crate) fn has_duration_range(&self) -> bool {
+        self.min_duration.is_some() || self.max_duration.is_some()
+    }
+
+    pub(crate) fn matches_duration(&self, duration_ms: u64) -> bool {
+        self.min_duration
+            .is_none_or(|min| duration_ms >= min.saturating_mul(1000))
+            && self
+                .max_duration
+                .is_none_or(|max| duration_ms <= max.saturating_mul(1000))
+    }
+
+    fn matches_year(&self, year: Option<i32>) -> bool {
+        if self.year_min.is_none() && self.year_max.is_none() {
+            return true;
+        }
+        year.is_some_and(|year| {
+            self.year_min.is_none_or(|min| year >= min)
+                && self.year_max.is_none_or(|max| year <= max)
+        })
+    }
 }
 
-pub fn extract_metadata(tag: &Tag, needle: &str) -> Option<TrackMetadata> {
-    let artist = match_artist(tag, needle)?;
-    let lyrics = collect_lyrics(tag)?;
-    let title = resolve_title(tag);
+pub fn extract_metadata(tag: &Tag, filters: &Filters) -> Option<TrackMetadata> {
+    let artist = match_artist(tag, filters)?;
+    if !matches_filters(tag, filters) {
+        return None;
+    }
+    let collected = collect_lyrics(tag, filters).or_else(|| {
+        filters
+            .loose_comments
+            .then(|| loose_comment_lyrics(tag, filters.by_language))?
+    })?;
+    let Some(lyrics) = filters.finish_lyrics(collected.text) else {
+        log::debug!("Lyrics were only LRC timing and headers; nothing left after post-processing");
+        return None;
+    };
+
+    let mut track = track_with_lyrics(tag, artist, lyrics);
+    track.source = filters.lyrics_source.then_some(collected.source);
+    Some(track)
+}
 
-    Some(TrackMetadata {
+/// Assembles the output record from a tag that already passed the filters.
+pub(crate) fn track_with_lyrics(tag: &Tag, artist: String, lyrics: String) -> TrackMetadata {
+    TrackMetadata {
         artist,
-        title,
+        title: resolve_title(tag),
         lyrics,
-    })
+        album: non_empty(tag.album()),
+        album_artist: non_empty(tag.album_artist()),
+        audio: None,
+        source: None,
+        hash: None,
+    }
 }
 
-pub(crate) fn match_artist(tag: &Tag, needle: &str) -> Option<String> {
-    let artist = resolve_artist(tag)?;
-    if !matches_artist(&artist, needle) {
-        return None;
+pub(crate) fn match_artist(tag: &Tag, filters: &Filters) -> Option<String> {
+    pick_matching_artist(filters, |frame| artist_frame(tag, frame)).map(str::to_owned)
+}
+
+pub(crate) fn resolve_artist(tag: &Tag, frames: &[ArtistFrame]) -> Option<String> {
+    pick_artist(frames, |frame| artist_frame(tag, frame), |_| true).map(str::to_owned)
+}
+
+fn artist_frame(tag: &Tag, frame: ArtistFrame) -> Option<&str> {
+    match frame {
+        ArtistFrame::Artist => tag.artist(),
+        ArtistFrame::AlbumArtist => tag.album_artist(),
+        ArtistFrame::Composer => text_frame(tag, "TCOM"),
+        ArtistFrame::OriginalArtist => text_frame(tag, "TOPE"),
     }
+}
+
+fn text_frame<'a>(tag: &'a Tag, id: &str) -> Option<&'a str> {
+    tag.get(id).and_then(|frame| frame.content().text())
+}
 
-    Some(artist)
+/// The artist the filters accept, from the fields `read` finds in `filters.artist_frames`.
+/// `--artist-frames` tries every listed field in order, so a track tagged `Various Artists` still
+/// matches on its composer; `--artist-source` only looks at the first present one.
+pub(crate) fn pick_matching_artist<'a>(
+    filters: &Filters,
+    read: impl Fn(ArtistFrame) -> Option<&'a str>,
+) -> Option<&'a str> {
+    if filters.search_artist_frames {
+        pick_artist(&filters.artist_frames, read, |artist| {
+            filters.accepts_artist(artist)
+        })
+    } else {
+        pick_artist(&filters.artist_frames, read, |_| true)
+            .filter(|artist| filters.accepts_artist(artist))
+    }
 }
 
-pub(crate) fn resolve_artist(tag: &Tag) -> Option<String> {
-    tag.artist()
-        .or_else(|| tag.album_artist())
+/// The first of `frames`, in order, that `read` finds present and not blank and that `accept`
+/// takes, trimmed.
+fn pick_artist<'a>(
+    frames: &[ArtistFrame],
+    read: impl Fn(ArtistFrame) -> Option<&'a str>,
+    accept: impl Fn(&str) -> bool,
+) -> Option<&'a str> {
+    frames
+        .iter()
+        .filter_map(|&frame| read(frame))
         .map(str::trim)
-        .filter(|value| !value.is_empty())
-        .map(|value| value.to_owned())
+        .find(|value| !value.is_empty() && accept(value))
+}
+
+/// Both sides are NFC-normalized first, so decomposed tags (`Bjo\u{308}rk`) match composed
+/// needles (`Björk`) and vice versa; with `ascii_fold` their accents are dropped instead.
+pub(crate) fn matches_artist(
+    artist: &str,
+    needle: &str,
+    case_sensitive: bool,
+    ascii_fold: bool,
+) -> bool {
+    let needle = needle.trim();
+    if needle.is_empty() {
+        return true;
+    }
+    let (artist, needle) = if ascii_fold {
+        (fold_diacritics(artist), fold_diacritics(needle))
+    } else {
+        (nfc(artist), nfc(needle))
+    };
+    let (artist, needle) = (artist.as_ref(), needle.as_ref());
+    if case_sensitive {
+        return artist.contains(needle);
+    }
+
+    artist.to_lowercase().contains(&needle.to_lowercase())
 }
 
-fn matches_artist(artist: &str, needle: &str) -> bool {
-    let normalized_artist = artist.to_ascii_lowercase();
-    let normalized_needle = needle.trim().to_ascii_lowercase();
+/// `text` in Unicode Normalization Form C, borrowed when it already is (the common case).
+fn nfc(text: &str) -> Cow<'_, str> {
+    match is_nfc_quick(text.chars()) {
+        IsNormalized::Yes => Cow::Borrowed(text),
+        _ => Cow::Owned(text.nfc().collect()),
+    }
+}
 
-    normalized_needle.is_empty() || normalized_artist.contains(&normalized_needle)
+/// Decomposes `text` (NFD) and drops the combining marks, so `Beyoncé` becomes `Beyonce`.
+/// Letters that are not composed from a base and a mark, such as `ø` or `ß`, are kept.
+fn fold_diacritics(text: &str) -> Cow<'_, str> {
+    Cow::Owned(text.nfd().filter(|&ch| !is_combining_mark(ch)).collect())
+}
+
+/// Scores the whole artist name, not its best-matching substring, so `udio` will not fuzzily
+/// match `Studio Band`; an empty needle matches every artist.
+pub(crate) fn matches_artist_fuzzy(
+    artist: &str,
+    needle: &str,
+    threshold: f64,
+    case_sensitive: bool,
+) -> bool {
+    let needle = needle.trim();
+    if needle.is_empty() {
+        return true;
+    }
+    let artist = artist.trim();
+    let score = if case_sensitive {
+        strsim::jaro_winkler(artist, needle)
+    } else {
+        strsim::jaro_winkler(&artist.to_lowercase(), &needle.to_lowercase())
+    };
+
+    score >= threshold
+}
+
+/// Checks the non-artist filters; the year comes from TYER, falling back to TDRC.
+pub(crate) fn matches_filters(tag: &Tag, filters: &Filters) -> bool {
+    let year = tag
+        .year()
+        .or_else(|| tag.date_recorded().map(|date| date.year));
+    filters.matches_track(tag.title(), year, tag.genre_parsed().as_deref())
+}
+
+/// An unset or blank needle accepts every value, including a missing one.
+pub(crate) fn matches_title(title: Option<&str>, needle: Option<&str>) -> bool {
+    let needle = match needle.map(str::trim) {
+        Some(needle) if !needle.is_empty() => needle.to_ascii_lowercase(),
+        _ => return true,
+    };
+
+    title.is_some_and(|title| title.to_ascii_lowercase().contains(&needle))
+}
+
+/// Trims an optional tag value, treating blank strings as absent.
+pub(crate) fn non_empty(value: Option<&str>) -> Option<String> {
+    value
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(|value| value.to_owned())
 }
 
 pub(crate) fn resolve_title(tag: &Tag) -> String {
@@ -57,51 +438,351 @@ pub(crate) fn resolve_title(tag: &Tag) -> String {
         .unwrap_or_else(|| "Unknown Title".to_string())
 }
 
-pub(crate) fn collect_lyrics(tag: &Tag) -> Option<String> {
-    let mut blocks = Vec::new();
-    let mut seen = std::collections::HashSet::new();
+/// Gathers every lyrics-bearing frame. With `lyrics_lang` set, USLT frames in other languages
+/// are skipped, unless no frame carries that language at all. With `keep_timestamps`, timed SYLT
+/// frames come first as LRC text and unsynchronised copies of the same words are dropped. TXXX
+/// frames count when their description is one of `lyrics_tags`.
+pub(crate) fn collect_lyrics(tag: &Tag, filters: &Filters) -> Option<CollectedLyrics> {
+    let mut blocks = Blocks::new(filters.by_language);
 
+    log_frame_counts(tag);
+
+    if filters.keep_timestamps {
+        for synced in tag.synchronised_lyrics() {
+            match format_lrc(synced) {
+                Some(lrc) => {
+                    blocks.push("timed SYLT", "sylt", &synced.lang, &lrc);
+                    blocks
+                        .seen
+                        .insert(dedup_key(&flatten_synced_lyrics(synced)));
+                }
+                None => log::debug!("SYLT frame has no millisecond timing; flattening it instead"),
+            }
+        }
+    }
+
+    let lang = filters.lyrics_lang.as_deref().filter(|code| {
+        tag.lyrics()
+            .any(|lyric| lyric.lang.eq_ignore_ascii_case(code))
+    });
+
+    // USLT frames go first; the frame walk below only picks up the other lyric sources.
     for lyric in tag.lyrics() {
-        push_block(&mut blocks, &mut seen, lyric.text.as_str());
+        match lang {
+            Some(code) if !lyric.lang.eq_ignore_ascii_case(code) => log::debug!(
+                "Ignoring USLT frame in language '{}'; a '{code}' frame is preferred",
+                lyric.lang
+            ),
+            _ => blocks.push("USLT", "uslt", &lyric.lang, &lyric.text),
+        }
     }
 
     for frame in tag.frames() {
+        log::trace!("Inspecting {} frame", frame.id());
         match frame.content() {
             Content::ExtendedText(ExtendedText { description, value })
-                if description.eq_ignore_ascii_case("lyrics") =>
+                if filters
+                    .lyrics_tags
+                    .iter()
+                    .any(|name| description.trim().eq_ignore_ascii_case(name)) =>
             {
-                push_block(&mut blocks, &mut seen, value);
+                blocks.push("TXXX", "txxx", "", value);
+            }
+            Content::ExtendedText(ExtendedText { description, .. }) => {
+                log::debug!("Ignoring TXXX frame with non-lyrics description '{description}'");
             }
             Content::Comment(Comment {
-                description, text, ..
+                lang,
+                description,
+                text,
             }) if description.eq_ignore_ascii_case("lyrics") => {
-                push_block(&mut blocks, &mut seen, text);
+                blocks.push("COMM", "comm", lang, text);
+            }
+            Content::Comment(Comment { description, .. }) => {
+                log::debug!("Ignoring COMM frame with non-lyrics description '{description}'");
             }
-            Content::Lyrics(LyricsFrame { text, .. }) => {
-                push_block(&mut blocks, &mut seen, text);
+            Content::SynchronisedLyrics(synced) => {
+                blocks.push("SYLT", "sylt", &synced.lang, &flatten_synced_lyrics(synced));
             }
             Content::Text(value) if frame.id().eq_ignore_ascii_case("lyrics") => {
-                push_block(&mut blocks, &mut seen, value);
+                blocks.push("LYRICS text", "text", "", value);
             }
             _ => {}
         }
     }
 
-    if blocks.is_empty() {
-        None
+    log::debug!("Collected {} distinct lyrics block(s)", blocks.texts.len());
+    blocks.finish()
+}
+
+/// Joins the text of `COMM` frames whose description is empty or whitespace, which some taggers
+/// use for lyrics. Only consulted behind `--loose-comments`, as such comments often hold notes
+/// like "encoded by" instead.
+pub(crate) fn loose_comment_lyrics(tag: &Tag, by_language: bool) -> Option<CollectedLyrics> {
+    let mut blocks = Blocks::new(by_language);
+    for comment in tag.comments() {
+        if comment.description.trim().is_empty() {
+            blocks.push(
+                "blank-description COMM",
+                "comm",
+                &comment.lang,
+                &comment.text,
+            );
+        }
+    }
+
+    blocks.finish()
+}
+
+/// Reports whether any line of `text` starts with an LRC `[mm:ss...]` timestamp.
+pub(crate) fn has_lrc_timestamps(text: &str) -> bool {
+    text.lines()
+        .any(|line| strip_leading_timestamp(line.trim_start()).is_some())
+}
+
+/// Removes LRC timing from `text`: every leading `[mm:ss.xx]` tag on a line, and whole-line
+/// header tags such as `[ar:Artist]`. Plain lines pass through unchanged.
+pub(crate) fn strip_lrc_timestamps(text: &str) -> String {
+    text.lines()
+        .filter_map(|line| {
+            let mut rest = line.trim_start();
+            let mut timed = false;
+            while let Some(after) = strip_leading_timestamp(rest) {
+                rest = after;
+                timed = true;
+            }
+            if !timed && is_lrc_header(rest) {
+                return None;
+            }
+            Some(rest.trim())
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .to_owned()
+}
+
+/// Replaces every run of blank (empty or whitespace-only) lines with one empty line. Blocks
+/// joined by `collect_lyrics` are already one blank line apart, so their separators survive.
+pub(crate) fn collapse_blank_lines(text: &str) -> String {
+    let mut out = Vec::new();
+    let mut previous_blank = false;
+    for line in text.lines() {
+        let blank = line.trim().is_empty();
+        if !(blank && previous_blank) {
+            out.push(if blank { "" } else { line });
+        }
+        previous_blank = blank;
+    }
+    out.join("\n")
+}
+
+/// Drops blank (empty or whitespace-only) lines, including the blank line `collect_lyrics` puts
+/// between frames, for players that cannot handle double newlines.
+pub(crate) fn flatten_lyrics(text: &str) -> String {
+    text.lines()
+        .filter(|line| !line.trim().is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Cuts `text` to its first `max_chars` characters, dropping trailing whitespace and appending
+/// `…`. `None` when it is no longer than that, so short lyrics are left alone.
+pub(crate) fn truncate_lyrics(text: &str, max_chars: usize) -> Option<String> {
+    let (end, _) = text.char_indices().nth(max_chars)?;
+    Some(format!("{}…", text[..end].trim_end()))
+}
+
+/// Returns the remainder of `line` after a leading `[mm:ss...]` tag, if it starts with one.
+fn strip_leading_timestamp(line: &str) -> Option<&str> {
+    let inner = line.strip_prefix('[')?;
+    let (tag, rest) = inner.split_once(']')?;
+    let (minutes, seconds) = tag.split_once(':')?;
+    let valid = !minutes.is_empty()
+        && minutes.chars().all(|ch| ch.is_ascii_digit())
+        && seconds.starts_with(|ch: char| ch.is_ascii_digit())
+        && seconds.chars().all(|ch| ch.is_ascii_digit() || ch == '.');
+    valid.then_some(rest)
+}
+
+fn is_lrc_header(line: &str) -> bool {
+    let Some(tag) = line
+        .trim_end()
+        .strip_prefix('[')
+        .and_then(|rest| rest.strip_suffix(']'))
+    else {
+        return false;
+    };
+    tag.split_once(':')
+        .is_some_and(|(key, _)| !key.is_empty() && key.chars().all(|ch| ch.is_ascii_alphabetic()))
+}
+
+/// Orders synchronized lyric entries by timestamp and joins their text, dropping the timing.
+pub(crate) fn flatten_synced_lyrics(synced: &SynchronisedLyrics) -> String {
+    let mut entries: Vec<&(u32, String)> = synced.content.iter().collect();
+    entries.sort_by_key(|(timestamp, _)| *timestamp);
+
+    entries
+        .into_iter()
+        .map(|(_, text)| text.trim())
+        .filter(|text| !text.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Renders millisecond-timed entries as `[mm:ss.xx]` lines in timestamp order. Frames timed in
+/// MPEG frames cannot be converted without the bitrate, so they yield `None`.
+pub(crate) fn format_lrc(synced: &SynchronisedLyrics) -> Option<String> {
+    if synced.timestamp_format != TimestampFormat::Ms {
+        return None;
+    }
+
+    let mut entries: Vec<&(u32, String)> = synced.content.iter().collect();
+    entries.sort_by_key(|(timestamp, _)| *timestamp);
+
+    let lines: Vec<String> = entries
+        .into_iter()
+        .filter(|(_, text)| !text.trim().is_empty())
+        .map(|(timestamp, text)| {
+            format!(
+                "[{:02}:{:02}.{:02}]{}",
+                timestamp / 60_000,
+                timestamp / 1000 % 60,
+                timestamp % 1000 / 10,
+                text.trim()
+            )
+        })
+        .collect();
+
+    (!lines.is_empty()).then(|| lines.join("\n"))
+}
+
+/// Lyrics joined from one or more tag frames.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct CollectedLyrics {
+    pub text: String,
+    /// Comma-separated source labels of the frames used, in the order first used, e.g. `uslt,sylt`.
+    pub source: String,
+}
+
+/// Distinct lyrics blocks in the order found, with the labels of the frames they came from.
+struct Blocks {
+    /// Each block with the language label it is grouped under by `--by-language`.
+    texts: Vec<(String, String)>,
+    seen: std::collections::HashSet<String>,
+    sources: Vec<&'static str>,
+    by_language: bool,
+}
+
+impl Blocks {
+    fn new(by_language: bool) -> Self {
+        Self {
+            texts: Vec::new(),
+            seen: std::collections::HashSet::new(),
+            sources: Vec::new(),
+            by_language,
+        }
+    }
+
+    /// `frame` names the frame kind in the debug log explaining whether the block was kept;
+    /// `source` is the label recorded when it is. `lang` is the frame's language code, empty
+    /// for frames without one.
+    fn push(&mut self, frame: &str, source: &'static str, lang: &str, candidate: &str) {
+        let text = normalize_line_endings(candidate.trim());
+        if text.is_empty() {
+            log::debug!("Ignoring {frame} frame: empty text");
+        } else if !self.seen.insert(dedup_key(&text)) {
+            log::debug!("Ignoring {frame} frame: same words as an earlier block");
+        } else {
+            log::debug!("Using {frame} frame ({} lines)", text.lines().count());
+            self.texts.push((language_label(lang), text));
+            if !self.sources.contains(&source) {
+                self.sources.push(source);
+            }
+        }
+    }
+
+    /// Joins the blocks one blank line apart. With `by_language`, blocks are first gathered per
+    /// language, in the order each language was first seen, and each group gets a `[lang]` line.
+    fn finish(self) -> Option<CollectedLyrics> {
+        if self.texts.is_empty() {
+            return None;
+        }
+        let text = if self.by_language {
+            let mut groups: Vec<(String, Vec<String>)> = Vec::new();
+            for (lang, text) in self.texts {
+                match groups.iter_mut().find(|(seen, _)| *seen == lang) {
+                    Some((_, texts)) => texts.push(text),
+                    None => groups.push((lang, vec![text])),
+                }
+            }
+            groups
+                .into_iter()
+                .map(|(lang, texts)| format!("[{lang}]\n{}", texts.join("\n\n")))
+                .collect::<Vec<_>>()
+                .join("\n\n")
+        } else {
+            let texts: Vec<String> = self.texts.into_iter().map(|(_, text)| text).collect();
+            texts.join("\n\n")
+        };
+        Some(CollectedLyrics {
+            text,
+            source: self.sources.join(","),
+        })
+    }
+}
+
+/// Lowercased ISO 639-2 code for `--by-language` headers; blank or NUL-padded codes, and frames
+/// without a language, become `und` (undetermined).
+fn language_label(lang: &str) -> String {
+    let code = lang.trim_matches(|ch: char| ch == '\0' || ch.is_whitespace());
+    if code.is_empty() {
+        "und".to_string()
     } else {
-        Some(blocks.join("\n\n"))
+        code.to_ascii_lowercase()
     }
 }
 
-fn push_block(
-    blocks: &mut Vec<String>,
-    seen: &mut std::collections::HashSet<String>,
-    candidate: &str,
-) {
-    let text = candidate.trim();
-    if !text.is_empty() && seen.insert(text.to_owned()) {
-        blocks.push(text.to_owned());
+fn log_frame_counts(tag: &Tag) {
+    if !log::log_enabled!(log::Level::Debug) {
+        return;
+    }
+    let count = |id: &str| tag.frames().filter(|frame| frame.id() == id).count();
+    log::debug!(
+        "Examining {} USLT, {} TXXX, {} COMM and {} SYLT frame(s)",
+        count("USLT"),
+        count("TXXX"),
+        count("COMM"),
+        count("SYLT")
+    );
+}
+
+/// Rewrites `\r\n` and lone `\r` as `\n` so Windows-tagged lyrics don't leak carriage returns.
+pub(crate) fn normalize_line_endings(text: &str) -> String {
+    text.replace("\r\n", "\n").replace('\r', "\n")
+}
+
+/// Lowercases and collapses whitespace runs (newlines included) so blocks differing only
+/// cosmetically share a key; the first occurrence's text is the one kept.
+fn dedup_key(text: &str) -> String {
+    text.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Track shared by the tests of the modules that render or rewrite a [`TrackMetadata`].
+#[cfg(test)]
+pub(crate) fn sample_metadata() -> TrackMetadata {
+    TrackMetadata {
+        artist: "Studio Band".to_string(),
+        title: "Echoes".to_string(),
+        lyrics: "Line one\nLine two\n".to_string(),
+        album: None,
+        album_artist: None,
+        audio: None,
+        source: None,
+        hash: None,
     }
 }
 
@@ -109,16 +790,50 @@ fn push_block(
 mod tests {
     use super::*;
 
-    use id3::frame::{Content, ExtendedText, Frame, Lyrics};
+    use id3::frame::{
+        Content, ExtendedText, Frame, Lyrics, SynchronisedLyricsType, TimestampFormat,
+    };
+
+    fn collect(
+        tag: &Tag,
+        lyrics_lang: Option<&str>,
+        keep_timestamps: bool,
+        by_language: bool,
+    ) -> Option<CollectedLyrics> {
+        let filters = Filters {
+            lyrics_lang: lyrics_lang.map(str::to_owned),
+            keep_timestamps,
+            by_language,
+            ..Filters::default()
+        };
+        collect_lyrics(tag, &filters)
+    }
 
     fn lyric(description: &str, text: &str) -> Lyrics {
+        lyric_in("eng", description, text)
+    }
+
+    fn lyric_in(lang: &str, description: &str, text: &str) -> Lyrics {
         Lyrics {
-            lang: "eng".to_string(),
+            lang: lang.to_string(),
             description: description.to_string(),
             text: text.to_string(),
         }
     }
 
+    fn synced(content: &[(u32, &str)]) -> SynchronisedLyrics {
+        SynchronisedLyrics {
+            lang: "eng".to_string(),
+            timestamp_format: TimestampFormat::Ms,
+            content_type: SynchronisedLyricsType::Lyrics,
+            description: String::new(),
+            content: content
+                .iter()
+                .map(|(timestamp, text)| (*timestamp, text.to_string()))
+                .collect(),
+        }
+    }
+
     #[test]
     fn extracts_metadata_when_artist_matches() {
         let mut tag = Tag::new();
@@ -128,11 +843,27 @@ mod tests {
         tag.add_frame(lyric("verse2", "Line two"));
 
         let metadata =
-            extract_metadata(&tag, DEFAULT_ARTIST_FILTER).expect("metadata should be extracted");
+            extract_metadata(&tag, &Filters::default()).expect("metadata should be extracted");
 
         assert_eq!(metadata.artist, "Studio Master");
         assert_eq!(metadata.title, "Anthem");
         assert_eq!(metadata.lyrics, "Line one\n\nLine two");
+        assert_eq!(metadata.album, None);
+        assert_eq!(metadata.album_artist, None);
+    }
+
+    #[test]
+    fn captures_album_and_album_artist() {
+        let mut tag = Tag::new();
+        tag.set_artist("Studio Master");
+        tag.set_album("Greatest Hits");
+        tag.set_album_artist("Various Artists");
+        tag.add_frame(lyric("", "Words"));
+
+        let metadata =
+            extract_metadata(&tag, &Filters::default()).expect("metadata should be extracted");
+        assert_eq!(metadata.album.as_deref(), Some("Greatest Hits"));
+        assert_eq!(metadata.album_artist.as_deref(), Some("Various Artists"));
     }
 
     #[test]
@@ -142,7 +873,7 @@ mod tests {
         tag.add_frame(lyric("", "Words"));
 
         let metadata =
-            extract_metadata(&tag, DEFAULT_ARTIST_FILTER).expect("metadata should be extracted");
+            extract_metadata(&tag, &Filters::default()).expect("metadata should be extracted");
 
         assert_eq!(metadata.artist, "Audio Collective");
         assert_eq!(metadata.title, "Unknown Title");
@@ -155,7 +886,7 @@ mod tests {
         tag.set_artist("Composer");
         tag.add_frame(lyric("", "Words"));
 
-        assert!(extract_metadata(&tag, DEFAULT_ARTIST_FILTER).is_none());
+        assert!(extract_metadata(&tag, &Filters::default()).is_none());
     }
 
     #[test]
@@ -163,7 +894,7 @@ mod tests {
         let mut tag = Tag::new();
         tag.set_artist("Studio Duo");
 
-        assert!(extract_metadata(&tag, DEFAULT_ARTIST_FILTER).is_none());
+        assert!(extract_metadata(&tag, &Filters::default()).is_none());
     }
 
     #[test]
@@ -175,7 +906,7 @@ mod tests {
         tag.add_frame(lyric("lyric", "Verse"));
 
         let metadata =
-            extract_metadata(&tag, DEFAULT_ARTIST_FILTER).expect("metadata should be extracted");
+            extract_metadata(&tag, &Filters::default()).expect("metadata should be extracted");
         assert_eq!(metadata.lyrics, "Verse");
     }
 
@@ -199,7 +930,674 @@ mod tests {
         ));
 
         let metadata =
-            extract_metadata(&tag, DEFAULT_ARTIST_FILTER).expect("metadata should be extracted");
+            extract_metadata(&tag, &Filters::default()).expect("metadata should be extracted");
         assert_eq!(metadata.lyrics, "Block A");
     }
+
+    #[test]
+    fn flattens_synchronised_lyrics_in_timestamp_order() {
+        let mut tag = Tag::new();
+        tag.set_artist("Studio Duo");
+        tag.add_frame(synced(&[
+            (2000, "Second line"),
+            (0, "First line"),
+            (4000, " "),
+        ]));
+
+        let metadata =
+            extract_metadata(&tag, &Filters::default()).expect("metadata should be extracted");
+        assert_eq!(metadata.lyrics, "First line\nSecond line");
+    }
+
+    #[test]
+    fn synchronised_lyrics_matching_unsynced_text_are_deduplicated() {
+        let mut tag = Tag::new();
+        tag.set_artist("Studio Duo");
+        tag.add_frame(lyric("", "First line\nSecond line"));
+        tag.add_frame(synced(&[(0, "First line"), (1500, "Second line")]));
+
+        let metadata =
+            extract_metadata(&tag, &Filters::default()).expect("metadata should be extracted");
+        assert_eq!(metadata.lyrics, "First line\nSecond line");
+    }
+
+    #[test]
+    fn detects_lrc_timestamps() {
+        assert!(has_lrc_timestamps("[00:12.34]Hello"));
+        assert!(has_lrc_timestamps("Intro\n  [1:02]Later"));
+        assert!(!has_lrc_timestamps("[Chorus]\nSing along"));
+        assert!(!has_lrc_timestamps("[ar:Studio Duo]"));
+        assert!(!has_lrc_timestamps("Plain verse"));
+    }
+
+    #[test]
+    fn strips_lrc_timestamps_and_headers() {
+        let lrc = "[ar:Studio Duo]\n[ti:Anthem]\n[00:01.00]First line\n[00:02.50][01:10.00] Chorus\nPlain";
+        assert_eq!(strip_lrc_timestamps(lrc), "First line\nChorus\nPlain");
+        assert_eq!(strip_lrc_timestamps("No timing here"), "No timing here");
+    }
+
+    #[test]
+    fn title_filter_combines_with_artist_filter() {
+        let mut tag = Tag::new();
+        tag.set_artist("Studio Duo");
+        tag.set_title("Anthem (Live Remix)");
+        tag.add_frame(lyric("", "Verse"));
+
+        let live = Filters {
+            title: Some("LIVE".into()),
+            ..Filters::default()
+        };
+        assert!(extract_metadata(&tag, &live).is_some());
+
+        let acoustic = Filters {
+            title: Some("acoustic".into()),
+            ..Filters::default()
+        };
+        assert!(extract_metadata(&tag, &acoustic).is_none());
+
+        let wrong_artist = Filters {
+            artist: ArtistMatcher::Substring {
+                needle: "choir".into(),
+                case_sensitive: false,
+                ascii_fold: false,
+            },
+            title: Some("live".into()),
+            ..Filters::default()
+        };
+        assert!(extract_metadata(&tag, &wrong_artist).is_none());
+    }
+
+    #[test]
+    fn blank_title_filter_accepts_untitled_tracks() {
+        assert!(matches_title(None, None));
+        assert!(matches_title(None, Some("  ")));
+        assert!(!matches_title(None, Some("live")));
+    }
+
+    #[test]
+    fn fuzzy_matcher_scores_the_whole_artist_name() {
+        let fuzzy = |threshold| ArtistMatcher::Fuzzy {
+            needle: "Beyonce".into(),
+            threshold,
+            case_sensitive: false,
+        };
+
+        assert!(fuzzy(0.8).matches("Beyoncé"));
+        assert!(fuzzy(0.8).matches("BEYONCE"));
+        assert!(!fuzzy(0.95).matches("Beyoncé"));
+        assert!(!fuzzy(0.8).matches("Destiny's Child"));
+        assert!(!matches_artist_fuzzy("Studio Band", "udio", 0.8, false));
+        assert!(matches_artist_fuzzy("Anyone", "  ", 1.0, false));
+    }
+
+    #[test]
+    fn artist_matching_ignores_unicode_composition() {
+        let decomposed = "Bjo\u{308}rk";
+        let composed = "Bj\u{f6}rk";
+
+        assert!(matches_artist(decomposed, composed, false, false));
+        assert!(matches_artist(composed, decomposed, true, false));
+        assert!(matches_artist(
+            "The Bjo\u{308}rk Tribute",
+            "bj\u{f6}rk",
+            false,
+            false
+        ));
+        assert!(!matches_artist(decomposed, "Bjork", false, false));
+    }
+
+    #[test]
+    fn case_insensitive_matching_lowercases_non_ascii_letters() {
+        assert!(matches_artist(
+            "\u{c9}dith Piaf",
+            "\u{e9}dith",
+            false,
+            false
+        ));
+        assert!(matches_artist(
+            "E\u{301}DITH PIAF",
+            "\u{e9}dith",
+            false,
+            false
+        ));
+        assert!(matches_artist(
+            "\u{416}\u{415}\u{41d}\u{42f}",
+            "\u{436}\u{435}\u{43d}\u{44f}",
+            false,
+            false
+        ));
+        assert!(!matches_artist(
+            "\u{c9}dith Piaf",
+            "\u{e9}dith",
+            true,
+            false
+        ));
+    }
+
+    #[test]
+    fn ascii_fold_ignores_accents_on_either_side() {
+        assert!(matches_artist("Beyonc\u{e9}", "Beyonce", false, true));
+        assert!(matches_artist("BEYONCE", "beyonc\u{e9}", false, true));
+        assert!(matches_artist("Bjo\u{308}rk", "bjork", false, true));
+        assert!(!matches_artist("Beyonc\u{e9}", "beyonce", true, true));
+        assert!(!matches_artist("Beyonc\u{e9}", "Beyonce", false, false));
+        assert!(!matches_artist("R\u{f8}yksopp", "Royksopp", false, true));
+    }
+
+    #[test]
+    fn lyrics_source_lists_each_frame_kind_once() {
+        let mut tag = Tag::new();
+        tag.set_artist("Udio");
+        tag.add_frame(lyric("eng", "Verse"));
+        tag.add_frame(ExtendedText {
+            description: "LYRICS".to_string(),
+            value: "Bridge".to_string(),
+        });
+        tag.add_frame(lyric("deu", "Strophe"));
+
+        let collected = collect(&tag, None, false, false).expect("lyrics");
+        assert_eq!(collected.source, "uslt,txxx");
+
+        let filters = Filters {
+            lyrics_source: true,
+            ..Filters::default()
+        };
+        let track = extract_metadata(&tag, &filters).expect("track");
+        assert_eq!(track.source.as_deref(), Some("uslt,txxx"));
+        assert_eq!(
+            extract_metadata(&tag, &Filters::default()).unwrap().source,
+            None
+        );
+    }
+
+    #[test]
+    fn any_of_matcher_accepts_any_needle() {
+        let any = ArtistMatcher::AnyOf {
+            needles: vec!["udio".into(), "suno".into()],
+            case_sensitive: false,
+            ascii_fold: false,
+        };
+
+        assert!(any.matches("Udio Collective"));
+        assert!(any.matches("SUNO Studio"));
+        assert!(!any.matches("Orchestra"));
+    }
+
+    #[test]
+    fn loose_comments_recovers_blank_description_comments() {
+        let mut tag = Tag::new();
+        tag.set_artist("Studio Band");
+        tag.add_frame(Comment {
+            lang: "eng".into(),
+            description: " ".into(),
+            text: "First verse\r\nSecond verse".into(),
+        });
+        tag.add_frame(Comment {
+            lang: "eng".into(),
+            description: "iTunNORM".into(),
+            text: "00000A2B".into(),
+        });
+        assert!(extract_metadata(&tag, &Filters::default()).is_none());
+
+        let loose = Filters {
+            loose_comments: true,
+            ..Filters::default()
+        };
+        let track = extract_metadata(&tag, &loose).expect("comment used as lyrics");
+        assert_eq!(track.lyrics, "First verse\nSecond verse");
+
+        tag.add_frame(lyric("", "Real lyrics"));
+        let track = extract_metadata(&tag, &loose).expect("lyrics frame");
+        assert_eq!(track.lyrics, "Real lyrics");
+    }
+
+    #[test]
+    fn txxx_lyrics_accepts_each_default_description() {
+        for description in [
+            "LYRICS",
+            "Unsynced Lyrics",
+            "UNSYNCEDLYRICS",
+            "lyric",
+            " lyrics ",
+        ] {
+            let mut tag = Tag::new();
+            tag.add_frame(ExtendedText {
+                description: description.to_string(),
+                value: "Words".to_string(),
+            });
+            assert_eq!(
+                collect(&tag, None, false, false)
+                    .map(|lyrics| lyrics.text)
+                    .as_deref(),
+                Some("Words"),
+                "{description}"
+            );
+        }
+    }
+
+    #[test]
+    fn lyrics_tags_replace_the_default_descriptions() {
+        let mut tag = Tag::new();
+        tag.add_frame(ExtendedText {
+            description: "LYRICS".to_string(),
+            value: "Default".to_string(),
+        });
+        tag.add_frame(ExtendedText {
+            description: "Songtext".to_string(),
+            value: "Custom".to_string(),
+        });
+
+        let filters = Filters {
+            lyrics_tags: vec!["songtext".to_string()],
+            ..Filters::default()
+        };
+        assert_eq!(
+            collect_lyrics(&tag, &filters)
+                .map(|lyrics| lyrics.text)
+                .as_deref(),
+            Some("Custom")
+        );
+    }
+
+    #[test]
+    fn by_language_groups_blocks_under_language_headers() {
+        let mut tag = Tag::new();
+        tag.set_artist("Udio");
+        tag.add_frame(lyric_in("eng", "a", "Hello"));
+        tag.add_frame(lyric_in("JPN", "a", "Konnichiwa"));
+        tag.add_frame(lyric_in("eng", "b", "Goodbye"));
+        tag.add_frame(lyric_in("\0\0\0", "a", "Untagged"));
+
+        let merged = collect(&tag, None, false, false).expect("lyrics");
+        assert_eq!(merged.text, "Hello\n\nKonnichiwa\n\nGoodbye\n\nUntagged");
+
+        let grouped = collect(&tag, None, false, true).expect("lyrics");
+        assert_eq!(
+            grouped.text,
+            "[eng]\nHello\n\nGoodbye\n\n[jpn]\nKonnichiwa\n\n[und]\nUntagged"
+        );
+    }
+
+    #[test]
+    fn artist_source_reorders_artist_and_album_artist() {
+        let mut tag = Tag::new();
+        tag.set_artist("Track Singer");
+        tag.set_album_artist("Various Artists");
+        tag.add_frame(lyric("eng", "Words"));
+        let read = |tag: &Tag, artist_source: ArtistSource| {
+            let filters = Filters {
+                artist: ArtistMatcher::Substring {
+                    needle: String::new(),
+                    case_sensitive: false,
+                    ascii_fold: false,
+                },
+                artist_frames: artist_source.frames().to_vec(),
+                ..Filters::default()
+            };
+            extract_metadata(tag, &filters).map(|track| track.artist)
+        };
+
+        assert_eq!(
+            read(&tag, ArtistSource::Artist).as_deref(),
+            Some("Track Singer")
+        );
+        assert_eq!(
+            read(&tag, ArtistSource::AlbumArtist).as_deref(),
+            Some("Various Artists")
+        );
+        assert_eq!(
+            read(&tag, ArtistSource::PreferAlbumArtist).as_deref(),
+            Some("Various Artists")
+        );
+
+        tag.remove_album_artist();
+        assert_eq!(read(&tag, ArtistSource::AlbumArtist), None);
+        assert_eq!(
+            read(&tag, ArtistSource::PreferAlbumArtist).as_deref(),
+            Some("Track Singer")
+        );
+    }
+
+    #[test]
+    fn truncate_lyrics_cuts_on_char_boundaries() {
+        assert_eq!(truncate_lyrics("short", 5), None);
+        assert_eq!(truncate_lyrics("", 1), None);
+        assert_eq!(truncate_lyrics("héllo wörld", 6).as_deref(), Some("héllo…"));
+        assert_eq!(
+            truncate_lyrics("日本語の歌詞", 3).as_deref(),
+            Some("日本語…")
+        );
+    }
+
+    #[test]
+    fn collapse_blank_lines_keeps_one_blank_line_between_stanzas() {
+        let mut tag = Tag::new();
+        tag.set_artist("Studio Band");
+        tag.add_frame(lyric("a", "Verse one\n\n\n\nVerse two\n \n\t\nVerse three"));
+        tag.add_frame(lyric("b", "Second block"));
+
+        let untouched = extract_metadata(&tag, &Filters::default()).expect("lyrics");
+        assert!(untouched.lyrics.contains("Verse one\n\n\n\nVerse two"));
+
+        let collapse = Filters {
+            collapse_blank_lines: true,
+            ..Filters::default()
+        };
+        let track = extract_metadata(&tag, &collapse).expect("lyrics");
+        assert_eq!(
+            track.lyrics,
+            "Verse one\n\nVerse two\n\nVerse three\n\nSecond block"
+        );
+    }
+
+    #[test]
+    fn flatten_single_spaces_lyrics_and_frame_joins() {
+        let mut tag = Tag::new();
+        tag.set_artist("Studio Band");
+        tag.add_frame(lyric("a", "Verse one\n\n\nVerse two\n \nVerse three"));
+        tag.add_frame(lyric("b", "Second block"));
+
+        let default = extract_metadata(&tag, &Filters::default()).expect("lyrics");
+        assert_eq!(
+            default.lyrics,
+            "Verse one\n\n\nVerse two\n \nVerse three\n\nSecond block"
+        );
+
+        let flatten = Filters {
+            flatten: true,
+            ..Filters::default()
+        };
+        let track = extract_metadata(&tag, &flatten).expect("lyrics");
+        assert_eq!(
+            track.lyrics,
+            "Verse one\nVerse two\nVerse three\nSecond block"
+        );
+    }
+
+    #[test]
+    fn duration_range_is_inclusive() {
+        let filters = Filters {
+            min_duration: Some(60),
+            max_duration: Some(300),
+            ..Filters::default()
+        };
+        assert!(filters.has_duration_range());
+        assert!(!filters.matches_duration(59_999));
+        assert!(filters.matches_duration(60_000));
+        assert!(filters.matches_duration(300_000));
+        assert!(!filters.matches_duration(300_001));
+
+        let open = Filters::default();
+        assert!(!open.has_duration_range());
+        assert!(open.matches_duration(0));
+    }
+
+    #[test]
+    fn regex_matcher_replaces_substring_logic() {
+        let mut tag = Tag::new();
+        tag.set_artist("Soundcloud Rapper");
+        tag.add_frame(lyric("", "Bars"));
+
+        let alternation = Filters {
+            artist: ArtistMatcher::Regex(Regex::new("(?i)udio|oundcloud").unwrap()),
+            ..Filters::default()
+        };
+        assert!(extract_metadata(&tag, &alternation).is_some());
+        assert!(extract_metadata(&tag, &Filters::default()).is_none());
+
+        let anchored = Filters {
+            artist: ArtistMatcher::Regex(Regex::new("^Rapper").unwrap()),
+            ..Filters::default()
+        };
+        assert!(extract_metadata(&tag, &anchored).is_none());
+    }
+
+    #[test]
+    fn lyrics_lang_selects_matching_frames() {
+        let mut tag = Tag::new();
+        tag.set_artist("Studio Duo");
+        tag.add_frame(lyric_in("eng", "", "Hello"));
+        tag.add_frame(lyric_in("jpn", "", "Konnichiwa"));
+
+        assert_eq!(
+            collect(&tag, Some("ENG"), false, false)
+                .map(|lyrics| lyrics.text)
+                .as_deref(),
+            Some("Hello")
+        );
+        assert_eq!(
+            collect(&tag, None, false, false)
+                .map(|lyrics| lyrics.text)
+                .as_deref(),
+            Some("Hello\n\nKonnichiwa")
+        );
+    }
+
+    #[test]
+    fn lyrics_lang_falls_back_to_every_language() {
+        let mut tag = Tag::new();
+        tag.add_frame(lyric_in("jpn", "", "Konnichiwa"));
+
+        assert_eq!(
+            collect(&tag, Some("eng"), false, false)
+                .map(|lyrics| lyrics.text)
+                .as_deref(),
+            Some("Konnichiwa")
+        );
+    }
+
+    #[test]
+    fn keep_timestamps_emits_lrc_and_drops_plain_copy() {
+        let mut tag = Tag::new();
+        tag.set_artist("Studio Duo");
+        tag.add_frame(lyric("", "First line\nSecond line"));
+        tag.add_frame(synced(&[(65_430, "Second line"), (1_500, "First line")]));
+
+        let filters = Filters {
+            keep_timestamps: true,
+            ..Filters::default()
+        };
+        let metadata = extract_metadata(&tag, &filters).expect("metadata should be extracted");
+        assert_eq!(
+            metadata.lyrics,
+            "[00:01.50]First line\n[01:05.43]Second line"
+        );
+        assert!(has_lrc_timestamps(&metadata.lyrics));
+    }
+
+    #[test]
+    fn keep_timestamps_is_a_no_op_without_synced_lyrics() {
+        let mut tag = Tag::new();
+        tag.add_frame(lyric("", "Plain verse"));
+
+        assert_eq!(
+            collect(&tag, None, true, false)
+                .map(|lyrics| lyrics.text)
+                .as_deref(),
+            Some("Plain verse")
+        );
+    }
+
+    #[test]
+    fn year_and_genre_filters_apply_to_tags() {
+        let mut tag = Tag::new();
+        tag.set_artist("Studio Duo");
+        tag.set_year(1994);
+        tag.set_genre("Synthpop");
+        tag.add_frame(lyric("", "Verse"));
+
+        let nineties = Filters {
+            year_min: Some(1990),
+            year_max: Some(1999),
+            genre: Some("synth".into()),
+            ..Filters::default()
+        };
+        assert!(extract_metadata(&tag, &nineties).is_some());
+
+        let eighties = Filters {
+            year_max: Some(1989),
+            ..Filters::default()
+        };
+        assert!(extract_metadata(&tag, &eighties).is_none());
+
+        let jazz = Filters {
+            genre: Some("jazz".into()),
+            ..Filters::default()
+        };
+        assert!(extract_metadata(&tag, &jazz).is_none());
+    }
+
+    #[test]
+    fn year_bounds_skip_tracks_without_a_year() {
+        let filters = Filters {
+            year_min: Some(2000),
+            ..Filters::default()
+        };
+        assert!(!filters.matches_track(None, None, None));
+        assert!(filters.matches_track(None, Some(2000), None));
+        assert!(Filters::default().matches_track(None, None, None));
+    }
+
+    #[test]
+    fn case_sensitive_substring_respects_casing() {
+        assert!(matches_artist("Udio Band", "udio", false, false));
+        assert!(!matches_artist("Udio Band", "udio", true, false));
+        assert!(matches_artist("studio band", "udio", true, false));
+        assert!(matches_artist("Anyone", "  ", true, false));
+        assert!(matches_artist("Anyone", "", false, false));
+    }
+
+    #[test]
+    fn cosmetically_different_blocks_are_deduplicated() {
+        let mut tag = Tag::new();
+        tag.set_artist("Studio Duo");
+        tag.add_frame(lyric("first", "Verse one\nLine  two"));
+        tag.add_frame(lyric("second", "verse   ONE line two "));
+
+        assert_eq!(
+            collect(&tag, None, false, false)
+                .map(|lyrics| lyrics.text)
+                .as_deref(),
+            Some("Verse one\nLine  two")
+        );
+    }
+
+    #[test]
+    fn carriage_returns_are_normalized_to_line_feeds() {
+        let mut tag = Tag::new();
+        tag.set_artist("Studio Duo");
+        tag.add_frame(lyric("", "Line one\r\nLine two\rLine three\r\n"));
+
+        let metadata =
+            extract_metadata(&tag, &Filters::default()).expect("metadata should be extracted");
+        assert_eq!(metadata.lyrics, "Line one\nLine two\nLine three");
+
+        let block = crate::writer::format_block(&metadata);
+        assert!(!block.contains('\r'));
+    }
+
+    #[test]
+    fn strip_lrc_timestamps_option_cleans_embedded_lrc() {
+        let mut tag = Tag::new();
+        tag.set_artist("Udio");
+        tag.add_frame(lyric(
+            "",
+            "[ar:Udio]\n[00:01.00]First line\n[00:02.50][00:09.00]Second line",
+        ));
+
+        let plain = extract_metadata(&tag, &Filters::default()).expect("lyrics");
+        assert!(plain.lyrics.contains("[00:01.00]First line"));
+
+        let filters = Filters {
+            strip_lrc_timestamps: true,
+            ..Filters::default()
+        };
+        let stripped = extract_metadata(&tag, &filters).expect("lyrics");
+        assert_eq!(stripped.lyrics, "First line\nSecond line");
+    }
+
+    #[test]
+    fn strip_lrc_timestamps_option_drops_header_only_lyrics() {
+        let mut tag = Tag::new();
+        tag.set_artist("Udio");
+        tag.add_frame(lyric("", "[ar:Udio]\n[ti:Nothing]"));
+
+        let filters = Filters {
+            strip_lrc_timestamps: true,
+            ..Filters::default()
+        };
+        assert!(extract_metadata(&tag, &filters).is_none());
+    }
+
+    #[test]
+    fn artist_frames_fall_back_to_composer_and_original_artist() {
+        let mut tag = Tag::new();
+        tag.add_frame(Frame::text("TCOM", "Udio Composer"));
+        tag.add_frame(Frame::text("TOPE", "Udio Original"));
+        tag.add_frame(lyric("eng", "Words"));
+        let read = |tag: &Tag, frames: &[ArtistFrame]| {
+            let filters = Filters {
+                artist_frames: frames.to_vec(),
+                ..Filters::default()
+            };
+            extract_metadata(tag, &filters).map(|track| track.artist)
+        };
+
+        assert_eq!(read(&tag, ArtistSource::Artist.frames()), None);
+        assert_eq!(
+            read(
+                &tag,
+                &[
+                    ArtistFrame::Artist,
+                    ArtistFrame::AlbumArtist,
+                    ArtistFrame::Composer
+                ]
+            )
+            .as_deref(),
+            Some("Udio Composer")
+        );
+        assert_eq!(
+            read(&tag, &[ArtistFrame::OriginalArtist, ArtistFrame::Composer]).as_deref(),
+            Some("Udio Original")
+        );
+
+        tag.set_artist("  ");
+        tag.set_album_artist("Studio Album");
+        assert_eq!(
+            read(&tag, &[ArtistFrame::Artist, ArtistFrame::Composer]).as_deref(),
+            Some("Udio Composer")
+        );
+    }
+
+    #[test]
+    fn artist_frames_skip_a_present_frame_that_does_not_match() {
+        let mut tag = Tag::new();
+        tag.set_artist("Various Artists");
+        tag.add_frame(Frame::text("TCOM", "Bach"));
+        tag.add_frame(lyric("eng", "Words"));
+        let filters = |frames: &[ArtistFrame]| Filters {
+            artist: ArtistMatcher::Substring {
+                needle: "Bach".to_string(),
+                case_sensitive: false,
+                ascii_fold: false,
+            },
+            artist_frames: frames.to_vec(),
+            search_artist_frames: true,
+            ..Filters::default()
+        };
+
+        let both = filters(&[ArtistFrame::Artist, ArtistFrame::Composer]);
+        assert_eq!(
+            extract_metadata(&tag, &both).map(|track| track.artist),
+            Some("Bach".to_string())
+        );
+        let artist_only = filters(&[ArtistFrame::Artist]);
+        assert!(extract_metadata(&tag, &artist_only).is_none());
+        let first_present_only = Filters {
+            search_artist_frames: false,
+            ..both
+        };
+        assert!(extract_metadata(&tag, &first_present_only).is_none());
+    }
 }
@@ -0,0 +1,417 @@
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::ops::Range;
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+
+use crate::cli::ArtistFrame;
+use crate::metadata::{self, Filters, TrackMetadata};
+
+const ARTIST_ATOM: [u8; 4] = *b"\xa9ART";
+const ALBUM_ARTIST_ATOM: [u8; 4] = *b"aART";
+const ALBUM_ATOM: [u8; 4] = *b"\xa9alb";
+const TITLE_ATOM: [u8; 4] = *b"\xa9nam";
+const LYRICS_ATOM: [u8; 4] = *b"\xa9lyr";
+const YEAR_ATOM: [u8; 4] = *b"\xa9day";
+const GENRE_ATOM: [u8; 4] = *b"\xa9gen";
+const COMPOSER_ATOM: [u8; 4] = *b"\xa9wrt";
+
+/// iTunes-style metadata read from the `moov/udta/meta/ilst` atoms of an MP4 container.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Mp4Tag {
+    artist: Option<String>,
+    album_artist: Option<String>,
+    album: Option<String>,
+    title: Option<String>,
+    lyrics: Option<String>,
+    year: Option<String>,
+    genre: Option<String>,
+    composer: Option<String>,
+}
+
+impl Mp4Tag {
+    pub fn read_from_path(path: &Path) -> Result<Self> {
+        let mut file = File::open(path)
+            .with_context(|| format!("failed to open MP4 file '{}'", path.display()))?;
+        let moov = read_top_level_atom(&mut file, b"moov")?
+            .context("no 'moov' atom found; not an MP4 container")?;
+        Self::from_moov(&moov)
+    }
+
+    fn from_moov(moov: &[u8]) -> Result<Self> {
+        let mut tag = Self::default();
+
+        let Some(udta) = find_atom(moov, b"udta")? else {
+            return Ok(tag);
+        };
+        let Some(meta) = find_atom(udta, b"meta")? else {
+            return Ok(tag);
+        };
+        // `meta` is normally a full box with four bytes of version/flags before its children,
+        // but QuickTime-style files omit them and start straight with the `hdlr` atom.
+        let meta_children = if meta.get(4..8) == Some(b"hdlr".as_slice()) {
+            meta
+        } else {
+            meta.get(4..).unwrap_or_default()
+        };
+        let Some(ilst) = find_atom(meta_children, b"ilst")? else {
+            return Ok(tag);
+        };
+
+        for (kind, body) in parse_atoms(ilst)? {
+            let slot = match kind {
+                ARTIST_ATOM => &mut tag.artist,
+                ALBUM_ARTIST_ATOM => &mut tag.album_artist,
+                ALBUM_ATOM => &mut tag.album,
+                TITLE_ATOM => &mut tag.title,
+                LYRICS_ATOM => &mut tag.lyrics,
+                YEAR_ATOM => &mut tag.year,
+                GENRE_ATOM => &mut tag.genre,
+                COMPOSER_ATOM => &mut tag.composer,
+                _ => continue,
+            };
+            if slot.is_none() {
+                *slot = read_text_data(body)?;
+            }
+        }
+
+        Ok(tag)
+    }
+
+    pub fn artist(&self) -> Option<&str> {
+        self.artist.as_deref()
+    }
+
+    pub fn album_artist(&self) -> Option<&str> {
+        self.album_artist.as_deref()
+    }
+
+    pub fn composer(&self) -> Option<&str> {
+        self.composer.as_deref()
+    }
+
+    pub fn album(&self) -> Option<&str> {
+        self.album.as_deref()
+    }
+
+    pub fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
+    pub fn lyrics(&self) -> Option<&str> {
+        self.lyrics.as_deref()
+    }
+
+    /// Release year from the leading digits of `©day`, which may hold a full ISO date.
+    pub fn year(&self) -> Option<i32> {
+        let day = self.year.as_deref()?.trim();
+        day.get(..4)?.parse().ok()
+    }
+
+    pub fn genre(&self) -> Option<&str> {
+        self.genre.as_deref()
+    }
+}
+
+pub fn extract_metadata(tag: &Mp4Tag, filters: &Filters) -> Option<TrackMetadata> {
+    let artist = match_artist(tag, filters)?;
+    if !matches_filters(tag, filters) {
+        return None;
+    }
+    let lyrics = tag
+        .lyrics()
+        .map(str::trim)
+        .filter(|text| !text.is_empty())
+        .map(metadata::normalize_line_endings)
+        .and_then(|lyrics| filters.finish_lyrics(lyrics))?;
+
+    let mut track = track_with_lyrics(tag, artist, lyrics);
+    track.source = filters.lyrics_source.then(|| "mp4".to_string());
+    Some(track)
+}
+
+pub(crate) fn track_with_lyrics(tag: &Mp4Tag, artist: String, lyrics: String) -> TrackMetadata {
+    TrackMetadata {
+        artist,
+        title: resolve_title(tag),
+        lyrics,
+        album: metadata::non_empty(tag.album()),
+        album_artist: metadata::non_empty(tag.album_artist()),
+        audio: None,
+        source: None,
+        hash: None,
+    }
+}
+
+pub(crate) fn match_artist(tag: &Mp4Tag, filters: &Filters) -> Option<String> {
+    metadata::pick_matching_artist(filters, |frame| match frame {
+        ArtistFrame::Artist => tag.artist(),
+        ArtistFrame::AlbumArtist => tag.album_artist(),
+        ArtistFrame::Composer => tag.composer(),
+        ArtistFrame::OriginalArtist => None,
+    })
+    .map(str::to_owned)
+}
+
+pub(crate) fn matches_filters(tag: &Mp4Tag, filters: &Filters) -> bool {
+    filters.matches_track(tag.title(), tag.year(), tag.genre())
+}
+
+pub(crate) fn resolve_title(tag: &Mp4Tag) -> String {
+    tag.title()
+        .map(str::trim)
+        .filter(|title| !title.is_empty())
+        .map(|title| title.to_owned())
+        .unwrap_or_else(|| "Unknown Title".to_string())
+}
+
+fn read_top_level_atom(file: &mut File, wanted: &[u8; 4]) -> Result<Option<Vec<u8>>> {
+    let mut found = None;
+    walk_top_level_atoms(file, |kind, body| {
+        if kind == wanted {
+            found = Some(body);
+        }
+        found.is_none()
+    })?;
+    let Some(body) = found else {
+        return Ok(None);
+    };
+
+    file.seek(SeekFrom::Start(body.start))?;
+    let mut bytes = Vec::new();
+    file.take(body.end - body.start)
+        .read_to_end(&mut bytes)
+        .context("failed to read MP4 atom body")?;
+    Ok(Some(bytes))
+}
+
+/// Byte ranges of the bodies of the top-level `mdat` atoms, which hold the encoded audio, so
+/// `--hash` leaves out the `moov` metadata; fragmented files have several.
+pub(crate) fn media_data_ranges(file: &mut File) -> Result<Vec<Range<u64>>> {
+    let mut ranges = Vec::new();
+    walk_top_level_atoms(file, |kind, body| {
+        if kind == b"mdat" {
+            ranges.push(body);
+        }
+        true
+    })?;
+    Ok(ranges)
+}
+
+/// Calls `visit` with the kind and body range of each top-level atom until it returns `false`.
+fn walk_top_level_atoms(
+    file: &mut File,
+    mut visit: impl FnMut(&[u8; 4], Range<u64>) -> bool,
+) -> Result<()> {
+    let file_len = file.metadata().context("failed to stat MP4 file")?.len();
+    let mut offset = 0_u64;
+
+    while offset + 8 <= file_len {
+        file.seek(SeekFrom::Start(offset))?;
+        let mut header = [0_u8; 8];
+        file.read_exact(&mut header)
+            .context("failed to read MP4 atom header")?;
+
+        let mut size = u64::from(u32::from_be_bytes(header[..4].try_into().unwrap()));
+        let mut header_len = 8_u64;
+        if size == 1 {
+            let mut large = [0_u8; 8];
+            file.read_exact(&mut large)
+                .context("failed to read MP4 extended atom size")?;
+            size = u64::from_be_bytes(large);
+            header_len = 16;
+        } else if size == 0 {
+            size = file_len - offset;
+        }
+        // The loop condition keeps `offset` below `file_len`, so this cannot overflow the way
+        // `offset + size` would for a corrupt extended size.
+        if size < header_len || size > file_len - offset {
+            bail!("malformed MP4 atom at offset {offset}");
+        }
+
+        let kind = header[4..].try_into().unwrap();
+        if !visit(&kind, offset + header_len..offset + size) {
+            return Ok(());
+        }
+        offset += size;
+    }
+
+    Ok(())
+}
+
+fn parse_atoms(data: &[u8]) -> Result<Vec<([u8; 4], &[u8])>> {
+    let mut atoms = Vec::new();
+    let mut offset = 0_usize;
+
+    while offset + 8 <= data.len() {
+        let mut size = u32::from_be_bytes(data[offset..offset + 4].try_into().unwrap()) as usize;
+        let kind: [u8; 4] = data[offset + 4..offset + 8].try_into().unwrap();
+        let mut header_len = 8;
+        if size == 1 {
+            let large = data
+                .get(offset + 8..offset + 16)
+                .context("truncated MP4 extended atom size")?;
+            size = usize::try_from(u64::from_be_bytes(large.try_into().unwrap()))
+                .context("MP4 atom too large")?;
+            header_len = 16;
+        } else if size == 0 {
+            size = data.len() - offset;
+        }
+        if size < header_len || size > data.len() - offset {
+            bail!("malformed MP4 atom '{}'", String::from_utf8_lossy(&kind));
+        }
+
+        atoms.push((kind, &data[offset + header_len..offset + size]));
+        offset += size;
+    }
+
+    Ok(atoms)
+}
+
+fn find_atom<'a>(data: &'a [u8], wanted: &[u8; 4]) -> Result<Option<&'a [u8]>> {
+    Ok(parse_atoms(data)?
+        .into_iter()
+        .find(|(kind, _)| kind == wanted)
+        .map(|(_, body)| body))
+}
+
+fn read_text_data(item: &[u8]) -> Result<Option<String>> {
+    let Some(data) = find_atom(item, b"data")? else {
+        return Ok(None);
+    };
+    let Some(payload) = data.get(8..) else {
+        return Ok(None);
+    };
+
+    // The first four bytes hold a version byte followed by the well-known type indicator.
+    let text = match data[3] {
+        1 => String::from_utf8_lossy(payload).into_owned(),
+        2 => {
+            let units: Vec<u16> = payload
+                .chunks_exact(2)
+                .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+                .collect();
+            String::from_utf16_lossy(&units)
+        }
+        _ => return Ok(None),
+    };
+
+    Ok(Some(text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs;
+
+    use tempfile::TempDir;
+
+    fn atom(kind: &[u8], body: &[u8]) -> Vec<u8> {
+        let mut bytes = ((body.len() + 8) as u32).to_be_bytes().to_vec();
+        bytes.extend_from_slice(kind);
+        bytes.extend_from_slice(body);
+        bytes
+    }
+
+    fn text_item(kind: &[u8], text: &str) -> Vec<u8> {
+        let mut data = vec![0, 0, 0, 1, 0, 0, 0, 0];
+        data.extend_from_slice(text.as_bytes());
+        atom(kind, &atom(b"data", &data))
+    }
+
+    fn m4a_bytes(items: &[Vec<u8>]) -> Vec<u8> {
+        let ilst = atom(b"ilst", &items.concat());
+        let mut meta_body = vec![0, 0, 0, 0];
+        meta_body.extend(atom(b"hdlr", &[0; 25]));
+        meta_body.extend(ilst);
+        let moov = atom(b"moov", &atom(b"udta", &atom(b"meta", &meta_body)));
+
+        let mut file = atom(b"ftyp", b"M4A \0\0\0\0");
+        file.extend(moov);
+        file
+    }
+
+    #[test]
+    fn reads_lyrics_artist_and_title_atoms() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("song.m4a");
+        fs::write(
+            &path,
+            m4a_bytes(&[
+                text_item(&ARTIST_ATOM, "Studio Crew"),
+                text_item(&TITLE_ATOM, "Anthem"),
+                text_item(&ALBUM_ATOM, "Debut"),
+                text_item(&LYRICS_ATOM, "Line one\nLine two\n"),
+                text_item(&YEAR_ATOM, "2021-06-01T00:00:00Z"),
+                text_item(&GENRE_ATOM, "Electronic"),
+            ]),
+        )
+        .unwrap();
+
+        let tag = Mp4Tag::read_from_path(&path).expect("mp4 tag");
+        let metadata =
+            extract_metadata(&tag, &Filters::default()).expect("metadata should be extracted");
+
+        assert_eq!(metadata.artist, "Studio Crew");
+        assert_eq!(metadata.title, "Anthem");
+        assert_eq!(metadata.lyrics, "Line one\nLine two");
+        assert_eq!(metadata.album.as_deref(), Some("Debut"));
+        assert_eq!(tag.year(), Some(2021));
+        assert_eq!(tag.genre(), Some("Electronic"));
+    }
+
+    #[test]
+    fn falls_back_to_album_artist() {
+        let tag = Mp4Tag {
+            album_artist: Some("Audio Collective".into()),
+            lyrics: Some("Words".into()),
+            ..Mp4Tag::default()
+        };
+
+        let metadata =
+            extract_metadata(&tag, &Filters::default()).expect("metadata should be extracted");
+        assert_eq!(metadata.artist, "Audio Collective");
+        assert_eq!(metadata.title, "Unknown Title");
+    }
+
+    #[test]
+    fn missing_ilst_yields_empty_tag() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("bare.m4a");
+        let mut bytes = atom(b"ftyp", b"M4A \0\0\0\0");
+        bytes.extend(atom(b"moov", &atom(b"mvhd", &[0; 12])));
+        fs::write(&path, bytes).unwrap();
+
+        let tag = Mp4Tag::read_from_path(&path).expect("mp4 tag");
+        assert_eq!(tag, Mp4Tag::default());
+    }
+
+    #[test]
+    fn rejects_extended_atom_sizes_past_the_end() {
+        let mut huge = 1_u32.to_be_bytes().to_vec();
+        huge.extend_from_slice(b"free");
+        huge.extend_from_slice(&u64::MAX.to_be_bytes());
+
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("huge.m4a");
+        let mut bytes = atom(b"ftyp", b"M4A \0\0\0\0");
+        bytes.extend_from_slice(&huge);
+        fs::write(&path, bytes).unwrap();
+        let error = Mp4Tag::read_from_path(&path).unwrap_err();
+        assert!(format!("{error:#}").contains("malformed MP4 atom"));
+
+        let error = parse_atoms(&huge).unwrap_err();
+        assert!(format!("{error:#}").contains("malformed MP4 atom 'free'"));
+    }
+
+    #[test]
+    fn rejects_files_without_moov() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("corrupt.m4a");
+        fs::write(&path, b"definitely not an mp4 container").unwrap();
+
+        assert!(Mp4Tag::read_from_path(&path).is_err());
+    }
+}
@@ -0,0 +1,345 @@
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::ops::Range;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+use crate::metadata::AudioProperties;
+
+/// How far past the ID3v2 tag to look for the first MPEG frame header.
+const SYNC_SEARCH_LIMIT: usize = 64 * 1024;
+const ID3V1_LEN: u64 = 128;
+
+/// Bitrates in kbps by table row and the header's 4-bit index; 0 marks free-format or invalid.
+const BITRATES: [[u32; 16]; 5] = [
+    // MPEG-1 layer I
+    [
+        0, 32, 64, 96, 128, 160, 192, 224, 256, 288, 320, 352, 384, 416, 448, 0,
+    ],
+    // MPEG-1 layer II
+    [
+        0, 32, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 384, 0,
+    ],
+    // MPEG-1 layer III
+    [
+        0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 0,
+    ],
+    // MPEG-2/2.5 layer I
+    [
+        0, 32, 48, 56, 64, 80, 96, 112, 128, 144, 160, 176, 192, 224, 256, 0,
+    ],
+    // MPEG-2/2.5 layers II and III
+    [
+        0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160, 0,
+    ],
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Version {
+    Mpeg1,
+    Mpeg2,
+    Mpeg25,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FrameHeader {
+    version: Version,
+    layer: u8,
+    bitrate_kbps: u32,
+    sample_rate: u32,
+    padding: bool,
+    mono: bool,
+}
+
+impl FrameHeader {
+    fn parse(bytes: &[u8]) -> Option<Self> {
+        let header: [u8; 4] = bytes.get(..4)?.try_into().ok()?;
+        if header[0] != 0xFF || header[1] & 0xE0 != 0xE0 {
+            return None;
+        }
+        let version = match (header[1] >> 3) & 0b11 {
+            0 => Version::Mpeg25,
+            2 => Version::Mpeg2,
+            3 => Version::Mpeg1,
+            _ => return None,
+        };
+        let layer = match (header[1] >> 1) & 0b11 {
+            1 => 3,
+            2 => 2,
+            3 => 1,
+            _ => return None,
+        };
+        let row = match (version, layer) {
+            (Version::Mpeg1, layer) => usize::from(layer - 1),
+            (_, 1) => 3,
+            _ => 4,
+        };
+        let bitrate_kbps = BITRATES[row][usize::from(header[2] >> 4)];
+        if bitrate_kbps == 0 {
+            return None;
+        }
+        let base_rate = match (header[2] >> 2) & 0b11 {
+            0 => 44_100,
+            1 => 48_000,
+            2 => 32_000,
+            _ => return None,
+        };
+        let sample_rate = match version {
+            Version::Mpeg1 => base_rate,
+            Version::Mpeg2 => base_rate / 2,
+            Version::Mpeg25 => base_rate / 4,
+        };
+
+        Some(Self {
+            version,
+            layer,
+            bitrate_kbps,
+            sample_rate,
+            padding: header[2] & 0b10 != 0,
+            mono: header[3] >> 6 == 0b11,
+        })
+    }
+
+    fn samples_per_frame(&self) -> u32 {
+        match (self.layer, self.version) {
+            (1, _) => 384,
+            (2, _) | (3, Version::Mpeg1) => 1152,
+            _ => 576,
+        }
+    }
+
+    fn frame_len(&self) -> usize {
+        let padding = u32::from(self.padding);
+        let len = if self.layer == 1 {
+            (12 * self.bitrate_kbps * 1000 / self.sample_rate + padding) * 4
+        } else {
+            self.samples_per_frame() / 8 * self.bitrate_kbps * 1000 / self.sample_rate + padding
+        };
+        len as usize
+    }
+
+    /// Offset of a Xing/Info header from the frame start: the 4-byte header plus side info.
+    fn xing_offset(&self) -> usize {
+        let side_info = match (self.version, self.mono) {
+            (Version::Mpeg1, false) => 32,
+            (Version::Mpeg1, true) | (_, false) => 17,
+            (_, true) => 9,
+        };
+        4 + side_info
+    }
+
+    /// Whether `other` looks like the next frame of the same stream.
+    fn continues_with(&self, other: &Self) -> bool {
+        self.version == other.version
+            && self.layer == other.layer
+            && self.sample_rate == other.sample_rate
+    }
+}
+
+/// Reads duration, bitrate and sample rate from the first MPEG audio frame after the ID3v2 tag.
+/// VBR files need a Xing/Info or VBRI header for an exact duration; without one the stream is
+/// treated as constant bitrate.
+pub fn read_properties(path: &Path) -> Result<AudioProperties> {
+    let mut file =
+        File::open(path).with_context(|| format!("failed to open '{}'", path.display()))?;
+    let audio = audio_range(&mut file)
+        .with_context(|| format!("failed to inspect '{}'", path.display()))?;
+
+    file.seek(SeekFrom::Start(audio.start))
+        .context("failed to seek past the ID3v2 tag")?;
+    let mut buf = Vec::new();
+    file.by_ref()
+        .take((SYNC_SEARCH_LIMIT + 4096) as u64)
+        .read_to_end(&mut buf)
+        .context("failed to read MPEG audio data")?;
+    let (offset, header) = find_first_frame(&buf).context("no MPEG audio frame header found")?;
+    let audio_bytes = audio.end.saturating_sub(audio.start + offset as u64);
+
+    let (frames, stream_bytes) = vbr_header(&buf[offset..], &header).unwrap_or((None, None));
+    let properties = match frames {
+        Some(frames) if frames > 0 => {
+            let duration_ms = u64::from(frames) * u64::from(header.samples_per_frame()) * 1000
+                / u64::from(header.sample_rate);
+            let bytes = stream_bytes.map_or(audio_bytes, u64::from);
+            AudioProperties {
+                duration_ms,
+                bitrate_kbps: (bytes * 8 / duration_ms.max(1)) as u32,
+                sample_rate_hz: header.sample_rate,
+            }
+        }
+        _ => AudioProperties {
+            duration_ms: audio_bytes * 8 / u64::from(header.bitrate_kbps),
+            bitrate_kbps: header.bitrate_kbps,
+            sample_rate_hz: header.sample_rate,
+        },
+    };
+    Ok(properties)
+}
+
+/// Byte range of the file between a leading ID3v2 tag and a trailing ID3v1 tag, either of which
+/// may be absent.
+pub(crate) fn audio_range(file: &mut File) -> Result<Range<u64>> {
+    let file_len = file
+        .metadata()
+        .context("failed to read the file length")?
+        .len();
+    file.rewind().context("failed to seek to the file header")?;
+    let start = id3v2_len(file)?.min(file_len);
+
+    let mut end = file_len;
+    if file_len >= start + ID3V1_LEN {
+        let mut marker = [0_u8; 3];
+        file.seek(SeekFrom::Start(file_len - ID3V1_LEN))
+            .and_then(|_| file.read_exact(&mut marker))
+            .context("failed to check for an ID3v1 tag")?;
+        if &marker == b"TAG" {
+            end -= ID3V1_LEN;
+        }
+    }
+    Ok(start..end)
+}
+
+/// Size of a leading ID3v2 tag, including its header and optional footer, or 0 without one.
+fn id3v2_len(file: &mut File) -> Result<u64> {
+    let mut header = [0_u8; 10];
+    match file.read_exact(&mut header) {
+        Ok(()) => {}
+        Err(error) if error.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(0),
+        Err(error) => return Err(error).context("failed to read file header"),
+    }
+    if &header[..3] != b"ID3" {
+        return Ok(0);
+    }
+    let size = header[6..10]
+        .iter()
+        .fold(0_u64, |size, &byte| (size << 7) | u64::from(byte & 0x7F));
+    let footer = if header[5] & 0x10 != 0 { 10 } else { 0 };
+    Ok(10 + size + footer)
+}
+
+/// The first frame header whose successor, when it fits in `buf`, is also a matching header;
+/// this skips stray `0xFF` bytes that only look like a sync word.
+fn find_first_frame(buf: &[u8]) -> Option<(usize, FrameHeader)> {
+    (0..buf.len().min(SYNC_SEARCH_LIMIT)).find_map(|offset| {
+        let header = FrameHeader::parse(&buf[offset..])?;
+        let next = offset + header.frame_len();
+        if next + 4 <= buf.len() {
+            let following = FrameHeader::parse(&buf[next..])?;
+            if !header.continues_with(&following) {
+                return None;
+            }
+        }
+        Some((offset, header))
+    })
+}
+
+/// Frame and byte counts from a Xing/Info or VBRI header in the first frame, when present.
+fn vbr_header(frame: &[u8], header: &FrameHeader) -> Option<(Option<u32>, Option<u32>)> {
+    let read_u32 = |at: usize| {
+        frame
+            .get(at..at + 4)
+            .map(|bytes| u32::from_be_bytes(bytes.try_into().unwrap()))
+    };
+
+    let xing = header.xing_offset();
+    if matches!(frame.get(xing..xing + 4), Some(b"Xing" | b"Info")) {
+        let flags = read_u32(xing + 4)?;
+        let mut at = xing + 8;
+        let frames = (flags & 0x1 != 0).then(|| read_u32(at)).flatten();
+        if flags & 0x1 != 0 {
+            at += 4;
+        }
+        let bytes = (flags & 0x2 != 0).then(|| read_u32(at)).flatten();
+        return Some((frames, bytes));
+    }
+
+    const VBRI_OFFSET: usize = 4 + 32;
+    if frame.get(VBRI_OFFSET..VBRI_OFFSET + 4) == Some(b"VBRI") {
+        return Some((read_u32(VBRI_OFFSET + 14), read_u32(VBRI_OFFSET + 10)));
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs;
+
+    use tempfile::TempDir;
+
+    /// MPEG-1 layer III, 128 kbps, 44.1 kHz, joint stereo, no padding: 417-byte frames.
+    const CBR_HEADER: [u8; 4] = [0xFF, 0xFB, 0x90, 0x40];
+
+    fn frames(count: usize, first: impl FnOnce(&mut Vec<u8>)) -> Vec<u8> {
+        let mut head = CBR_HEADER.to_vec();
+        first(&mut head);
+        head.resize(417, 0);
+        let mut bytes = head;
+        for _ in 1..count {
+            bytes.extend(CBR_HEADER);
+            bytes.resize(bytes.len() + 417 - 4, 0);
+        }
+        bytes
+    }
+
+    #[test]
+    fn parses_frame_header_fields() {
+        let header = FrameHeader::parse(&CBR_HEADER).expect("valid header");
+        assert_eq!(header.bitrate_kbps, 128);
+        assert_eq!(header.sample_rate, 44_100);
+        assert_eq!(header.samples_per_frame(), 1152);
+        assert_eq!(header.frame_len(), 417);
+
+        assert!(FrameHeader::parse(&[0xFF, 0xFB, 0xF0, 0x40]).is_none());
+        assert!(FrameHeader::parse(&[0xFF, 0xF8, 0x90, 0x40]).is_none());
+    }
+
+    #[test]
+    fn derives_cbr_duration_after_id3_tags() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("cbr.mp3");
+        let mut bytes = vec![0x00; 7];
+        bytes.extend(frames(100, |_| {}));
+        fs::write(&path, bytes).unwrap();
+        let mut tag = id3::Tag::new();
+        id3::TagLike::set_title(&mut tag, "Tagged");
+        tag.write_to_path(&path, id3::Version::Id3v24).unwrap();
+
+        let properties = read_properties(&path).expect("properties");
+        assert_eq!(properties.sample_rate_hz, 44_100);
+        assert_eq!(properties.bitrate_kbps, 128);
+        assert_eq!(properties.duration_ms, 100 * 417 * 8 / 128);
+    }
+
+    #[test]
+    fn prefers_xing_frame_count_for_vbr_streams() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("vbr.mp3");
+        let bytes = frames(3, |frame| {
+            frame.resize(36, 0);
+            frame.extend_from_slice(b"Xing");
+            frame.extend_from_slice(&3_u32.to_be_bytes());
+            frame.extend_from_slice(&1000_u32.to_be_bytes());
+            frame.extend_from_slice(&4_000_000_u32.to_be_bytes());
+        });
+        fs::write(&path, bytes).unwrap();
+
+        let properties = read_properties(&path).expect("properties");
+        assert_eq!(properties.duration_ms, 1000 * 1152 * 1000 / 44_100);
+        assert_eq!(
+            properties.bitrate_kbps,
+            (4_000_000 * 8 / properties.duration_ms) as u32
+        );
+    }
+
+    #[test]
+    fn rejects_files_without_mpeg_frames() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("noise.mp3");
+        fs::write(&path, vec![0x42; 2048]).unwrap();
+
+        assert!(read_properties(&path).is_err());
+    }
+}
@@ -0,0 +1,53 @@
+use std::io::{BufRead, Write};
+
+use anyhow::{bail, Context, Result};
+
+/// Asks `question` on `output` until `input` yields `y`/`yes` or `n`/`no` (any case); an empty
+/// answer counts as no. Fails if `input` closes before an answer, so an interrupted session
+/// does not silently reject the rest of the scan.
+pub fn confirm(mut input: impl BufRead, mut output: impl Write, question: &str) -> Result<bool> {
+    loop {
+        write!(output, "{question} [y/N] ").context("failed to write prompt")?;
+        output.flush().context("failed to write prompt")?;
+
+        let mut answer = String::new();
+        if input
+            .read_line(&mut answer)
+            .context("failed to read answer")?
+            == 0
+        {
+            bail!("input closed while waiting for an answer");
+        }
+        match answer.trim().to_ascii_lowercase().as_str() {
+            "y" | "yes" => return Ok(true),
+            "" | "n" | "no" => return Ok(false),
+            _ => writeln!(output, "Please answer y or n.").context("failed to write prompt")?,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_yes_and_no_answers() {
+        let mut output = Vec::new();
+        assert!(confirm("Yes\n".as_bytes(), &mut output, "Keep?").unwrap());
+        assert!(!confirm("n\n".as_bytes(), &mut output, "Keep?").unwrap());
+        assert!(!confirm("\n".as_bytes(), &mut output, "Keep?").unwrap());
+        assert_eq!(String::from_utf8(output).unwrap(), "Keep? [y/N] ".repeat(3));
+    }
+
+    #[test]
+    fn asks_again_after_unclear_answers_and_fails_on_eof() {
+        let mut output = Vec::new();
+        assert!(confirm("maybe\ny\n".as_bytes(), &mut output, "Keep?").unwrap());
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "Keep? [y/N] Please answer y or n.\nKeep? [y/N] "
+        );
+
+        assert!(confirm("".as_bytes(), Vec::new(), "Keep?").is_err());
+    }
+}
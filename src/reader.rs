@@ -0,0 +1,475 @@
+use std::io::{self, Read};
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::Result;
+use id3::TagLike;
+
+use crate::cli::ArtistSource;
+use crate::metadata::{self, AudioProperties, Filters, TrackMetadata};
+use crate::mp4::{self, Mp4Tag};
+use crate::mpeg;
+use crate::vorbis::{self, VorbisTag};
+
+/// Tag container format, chosen from the file extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TagFormat {
+    Id3,
+    Mp4,
+    /// Vorbis comments in an Ogg Vorbis or Opus stream.
+    Vorbis,
+    /// An ID3 tag in the `id3 ` chunk of a RIFF WAVE file.
+    Wav,
+}
+
+impl TagFormat {
+    pub fn from_path(path: &Path) -> Self {
+        let ext = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.to_ascii_lowercase());
+
+        match ext.as_deref() {
+            Some("m4a" | "m4b" | "m4p" | "mp4") => Self::Mp4,
+            Some("ogg" | "oga" | "opus") => Self::Vorbis,
+            Some("wav" | "wave") => Self::Wav,
+            _ => Self::Id3,
+        }
+    }
+}
+
+/// Tag read from an audio file by the backend matching its format.
+pub enum AudioTag {
+    Id3(id3::Tag),
+    Mp4(Mp4Tag),
+    Vorbis(VorbisTag),
+}
+
+impl AudioTag {
+    /// `None` for a WAV file without an ID3 chunk; every other format reports a missing tag as an
+    /// error.
+    pub fn read_from_path(path: &Path) -> Result<Option<Self>> {
+        match TagFormat::from_path(path) {
+            TagFormat::Id3 => Ok(Some(Self::Id3(read_id3(path)?))),
+            TagFormat::Mp4 => Ok(Some(Self::Mp4(Mp4Tag::read_from_path(path)?))),
+            TagFormat::Vorbis => Ok(Some(Self::Vorbis(VorbisTag::read_from_path(path)?))),
+            TagFormat::Wav => Ok(read_wav(path)?.map(Self::Id3)),
+        }
+    }
+
+    /// `read_from_path`, tried up to `retries` more times while the failure is a transient I/O
+    /// error, waiting 50ms before the first retry and doubling the wait each time after.
+    pub fn read_with_retries(path: &Path, retries: u32) -> Result<Option<Self>> {
+        let mut delay = INITIAL_RETRY_DELAY;
+        let mut attempt = 0;
+        loop {
+            match Self::read_from_path(path) {
+                Err(error) if attempt < retries && is_transient(&error) => {
+                    attempt += 1;
+                    log::debug!(
+                        "Retrying '{}' ({attempt}/{retries}) after a transient error: {error:#}",
+                        path.display()
+                    );
+                    std::thread::sleep(delay);
+                    delay = (delay * 2).min(MAX_RETRY_DELAY);
+                }
+                result => return result,
+            }
+        }
+    }
+
+    pub fn extract_metadata(&self, filters: &Filters) -> Option<TrackMetadata> {
+        match self {
+            Self::Id3(tag) => metadata::extract_metadata(tag, filters),
+            Self::Mp4(tag) => mp4::extract_metadata(tag, filters),
+            Self::Vorbis(tag) => vorbis::extract_metadata(tag, filters),
+        }
+    }
+
+    pub fn match_artist(&self, filters: &Filters) -> Option<String> {
+        match self {
+            Self::Id3(tag) => metadata::match_artist(tag, filters),
+            Self::Mp4(tag) => mp4::match_artist(tag, filters),
+            Self::Vorbis(tag) => vorbis::match_artist(tag, filters),
+        }
+    }
+
+    /// Title, year and genre filters; the artist is checked separately by `match_artist`.
+    pub fn matches_filters(&self, filters: &Filters) -> bool {
+        match self {
+            Self::Id3(tag) => metadata::matches_filters(tag, filters),
+            Self::Mp4(tag) => mp4::matches_filters(tag, filters),
+            Self::Vorbis(tag) => vorbis::matches_filters(tag, filters),
+        }
+    }
+
+    pub fn track_with_lyrics(&self, artist: String, lyrics: String) -> TrackMetadata {
+        match self {
+            Self::Id3(tag) => metadata::track_with_lyrics(tag, artist, lyrics),
+            Self::Mp4(tag) => mp4::track_with_lyrics(tag, artist, lyrics),
+            Self::Vorbis(tag) => vorbis::track_with_lyrics(tag, artist, lyrics),
+        }
+    }
+
+    pub fn resolve_title(&self) -> String {
+        match self {
+            Self::Id3(tag) => metadata::resolve_title(tag),
+            Self::Mp4(tag) => mp4::resolve_title(tag),
+            Self::Vorbis(tag) => vorbis::resolve_title(tag),
+        }
+    }
+}
+
+const INITIAL_RETRY_DELAY: Duration = Duration::from_millis(50);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// Whether a failed read may succeed when repeated: an I/O error of the kind network mounts
+/// report for hiccups, not a missing file or a tag that cannot be parsed.
+fn is_transient(error: &anyhow::Error) -> bool {
+    error
+        .chain()
+        .filter_map(|cause| cause.downcast_ref::<io::Error>())
+        .any(|error| {
+            matches!(
+                error.kind(),
+                io::ErrorKind::Interrupted
+                    | io::ErrorKind::WouldBlock
+                    | io::ErrorKind::TimedOut
+                    | io::ErrorKind::ConnectionReset
+                    | io::ErrorKind::ConnectionAborted
+                    | io::ErrorKind::NotConnected
+                    | io::ErrorKind::BrokenPipe
+                    | io::ErrorKind::StaleNetworkFileHandle
+                    | io::ErrorKind::ResourceBusy
+            )
+        })
+}
+
+/// Reads the ID3v2 tag, falling back to an ID3v1 tag when there is no v2 tag or it carries no
+/// artist. v1 has no lyrics, so such tracks can only match as missing lyrics.
+fn read_id3(path: &Path) -> Result<id3::Tag> {
+    let mut tag = match id3::Tag::read_from_path(path) {
+        Ok(tag) => tag,
+        Err(error) if matches!(error.kind, id3::ErrorKind::NoTag) => {
+            return id3::v1::Tag::read_from_path(path)
+                .map(id3::Tag::from)
+                .map_err(|_| error.into());
+        }
+        Err(error) => return Err(error.into()),
+    };
+
+    if metadata::resolve_artist(&tag, ArtistSource::Artist.frames()).is_none() {
+        if let Ok(v1) = id3::v1::Tag::read_from_path(path) {
+            if !v1.artist.trim().is_empty() {
+                tag.set_artist(v1.artist.trim());
+            }
+            if tag.title().is_none() && !v1.title.trim().is_empty() {
+                tag.set_title(v1.title.trim());
+            }
+        }
+    }
+    Ok(tag)
+}
+
+/// Reads the ID3 chunk of a WAV file; `id3` spots the RIFF header and looks for the chunk
+/// instead of a leading tag. Many WAV files carry no tag at all, so a missing chunk yields `None`
+/// rather than a tag error. There is no ID3v1 fallback.
+fn read_wav(path: &Path) -> Result<Option<id3::Tag>> {
+    match id3::Tag::read_from_path(path) {
+        Ok(tag) => Ok(Some(tag)),
+        Err(error) if matches!(error.kind, id3::ErrorKind::NoTag) => Ok(None),
+        Err(error) => Err(error.into()),
+    }
+}
+
+/// Duration, bitrate and sample rate for files read as ID3, which are assumed to hold MPEG audio.
+/// Other formats, and files without a recognisable MPEG frame, yield `None`.
+pub fn read_audio_properties(path: &Path) -> Option<AudioProperties> {
+    if TagFormat::from_path(path) != TagFormat::Id3 {
+        return None;
+    }
+    mpeg::read_properties(path)
+        .inspect_err(|error| log::debug!("No audio properties for '{}': {error:#}", path.display()))
+        .ok()
+}
+
+/// Reads `<stem>.lrc`, then `<stem>.txt`, beside `path`, each also tried gzip-compressed as
+/// `<stem>.lrc.gz`/`<stem>.txt.gz`. A leading UTF-8 byte order mark is dropped, and LRC timing is
+/// stripped unless `keep_timestamps` is set; blank, unreadable or corrupt files are treated as
+/// absent.
+pub fn read_sidecar_lyrics(path: &Path, keep_timestamps: bool) -> Option<String> {
+    ["lrc", "lrc.gz", "txt", "txt.gz"]
+        .into_iter()
+        .find_map(|extension| {
+            let sidecar = path.with_extension(extension);
+            let mut bytes = std::fs::read(&sidecar).ok()?;
+            if extension.ends_with(".gz") {
+                bytes = gunzip(&bytes)
+                    .inspect_err(|error| {
+                        log::warn!(
+                            "Ignoring sidecar '{}': failed to decompress: {error}",
+                            sidecar.display()
+                        )
+                    })
+                    .ok()?;
+            }
+            let bytes = bytes.strip_prefix(UTF8_BOM).unwrap_or(&bytes);
+            let text = metadata::normalize_line_endings(&String::from_utf8_lossy(bytes));
+            let lyrics = if keep_timestamps {
+                text.trim().to_owned()
+            } else {
+                metadata::strip_lrc_timestamps(&text)
+            };
+            (!lyrics.is_empty()).then_some(lyrics)
+        })
+}
+
+/// Windows editors often start text files with it; it is never part of the lyrics.
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+fn gunzip(bytes: &[u8]) -> std::io::Result<Vec<u8>> {
+    let mut text = Vec::new();
+    flate2::read::GzDecoder::new(bytes).read_to_end(&mut text)?;
+    Ok(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::path::PathBuf;
+
+    #[test]
+    fn dispatches_by_extension() {
+        assert_eq!(
+            TagFormat::from_path(&PathBuf::from("a.mp3")),
+            TagFormat::Id3
+        );
+        assert_eq!(
+            TagFormat::from_path(&PathBuf::from("a.M4A")),
+            TagFormat::Mp4
+        );
+        assert_eq!(
+            TagFormat::from_path(&PathBuf::from("a.mp4")),
+            TagFormat::Mp4
+        );
+        assert_eq!(
+            TagFormat::from_path(&PathBuf::from("a.Opus")),
+            TagFormat::Vorbis
+        );
+        assert_eq!(
+            TagFormat::from_path(&PathBuf::from("a.ogg")),
+            TagFormat::Vorbis
+        );
+        assert_eq!(
+            TagFormat::from_path(&PathBuf::from("a.WAV")),
+            TagFormat::Wav
+        );
+        assert_eq!(
+            TagFormat::from_path(&PathBuf::from("a.flac")),
+            TagFormat::Id3
+        );
+        assert_eq!(
+            TagFormat::from_path(&PathBuf::from("noext")),
+            TagFormat::Id3
+        );
+    }
+
+    /// A bare 128-byte ID3v1 tag after some placeholder audio bytes.
+    fn id3v1_bytes(title: &str, artist: &str) -> Vec<u8> {
+        fn field(value: &str, len: usize) -> Vec<u8> {
+            let mut bytes = value.as_bytes().to_vec();
+            bytes.resize(len, 0);
+            bytes
+        }
+
+        let mut bytes = vec![0_u8; 64];
+        bytes.extend_from_slice(b"TAG");
+        bytes.extend(field(title, 30));
+        bytes.extend(field(artist, 30));
+        bytes.extend(field("", 30));
+        bytes.extend(field("1999", 4));
+        bytes.extend(field("", 30));
+        bytes.push(255);
+        bytes
+    }
+
+    #[test]
+    fn falls_back_to_id3v1_tags() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let path = temp.path().join("old.mp3");
+        std::fs::write(&path, id3v1_bytes("Old Song", "Studio Veterans")).unwrap();
+
+        let tag = AudioTag::read_from_path(&path)
+            .expect("v1 tag should be read")
+            .expect("mp3 files always yield a tag");
+        let filters = Filters::default();
+        assert_eq!(
+            tag.match_artist(&filters).as_deref(),
+            Some("Studio Veterans")
+        );
+        assert_eq!(tag.resolve_title(), "Old Song");
+        assert!(tag.extract_metadata(&filters).is_none());
+    }
+
+    #[test]
+    fn borrows_v1_artist_when_v2_has_none() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let path = temp.path().join("mixed.mp3");
+        std::fs::write(&path, id3v1_bytes("Old Title", "Studio Veterans")).unwrap();
+        let mut v2 = id3::Tag::new();
+        v2.set_title("New Title");
+        v2.add_frame(id3::frame::Lyrics {
+            lang: "eng".to_string(),
+            description: String::new(),
+            text: "Kept words".to_string(),
+        });
+        v2.write_to_path(&path, id3::Version::Id3v24).unwrap();
+
+        let tag = AudioTag::read_from_path(&path)
+            .expect("tag")
+            .expect("mp3 files always yield a tag");
+        let track = tag
+            .extract_metadata(&Filters::default())
+            .expect("artist comes from v1");
+        assert_eq!(track.artist, "Studio Veterans");
+        assert_eq!(track.title, "New Title");
+        assert_eq!(track.lyrics, "Kept words");
+    }
+
+    /// A RIFF WAVE file with a PCM `fmt ` chunk, four bytes of silence and, when given, an
+    /// `id3 ` chunk holding `tag`.
+    fn wav_bytes(tag: Option<&id3::Tag>) -> Vec<u8> {
+        let mut fmt = Vec::new();
+        fmt.extend_from_slice(&1_u16.to_le_bytes());
+        fmt.extend_from_slice(&1_u16.to_le_bytes());
+        fmt.extend_from_slice(&8000_u32.to_le_bytes());
+        fmt.extend_from_slice(&16000_u32.to_le_bytes());
+        fmt.extend_from_slice(&2_u16.to_le_bytes());
+        fmt.extend_from_slice(&16_u16.to_le_bytes());
+
+        let mut body = b"WAVE".to_vec();
+        body.extend_from_slice(b"fmt ");
+        body.extend_from_slice(&(fmt.len() as u32).to_le_bytes());
+        body.extend(fmt);
+        body.extend_from_slice(b"data");
+        body.extend_from_slice(&4_u32.to_le_bytes());
+        body.extend_from_slice(&[0; 4]);
+        if let Some(tag) = tag {
+            let mut chunk = Vec::new();
+            tag.write_to(&mut chunk, id3::Version::Id3v24).unwrap();
+            body.extend_from_slice(b"id3 ");
+            body.extend_from_slice(&(chunk.len() as u32).to_le_bytes());
+            if chunk.len() % 2 == 1 {
+                chunk.push(0);
+            }
+            body.extend(chunk);
+        }
+
+        let mut bytes = b"RIFF".to_vec();
+        bytes.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        bytes.extend(body);
+        bytes
+    }
+
+    #[test]
+    fn reads_id3_chunk_from_wav() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let path = temp.path().join("song.wav");
+        let mut tag = id3::Tag::new();
+        tag.set_artist("Udio Studio");
+        tag.set_title("Wave Song");
+        tag.add_frame(id3::frame::Lyrics {
+            lang: "eng".to_string(),
+            description: String::new(),
+            text: "Riff words".to_string(),
+        });
+        std::fs::write(&path, wav_bytes(Some(&tag))).unwrap();
+
+        let track = AudioTag::read_from_path(&path)
+            .expect("wav tag")
+            .expect("the id3 chunk should be found")
+            .extract_metadata(&Filters::default())
+            .expect("metadata should be extracted");
+        assert_eq!(track.artist, "Udio Studio");
+        assert_eq!(track.title, "Wave Song");
+        assert_eq!(track.lyrics, "Riff words");
+    }
+
+    #[test]
+    fn wav_without_id3_chunk_has_no_tag() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let path = temp.path().join("bare.wav");
+        std::fs::write(&path, wav_bytes(None)).unwrap();
+
+        let tag = AudioTag::read_from_path(&path).expect("missing chunk is not an error");
+        assert!(tag.is_none());
+        assert!(read_audio_properties(&path).is_none());
+    }
+
+    #[test]
+    fn sidecar_prefers_lrc_and_strips_timing() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let audio = temp.path().join("song.mp3");
+        assert_eq!(read_sidecar_lyrics(&audio, false), None);
+
+        std::fs::write(temp.path().join("song.txt"), "Plain words\n").unwrap();
+        assert_eq!(
+            read_sidecar_lyrics(&audio, false).as_deref(),
+            Some("Plain words")
+        );
+
+        std::fs::write(temp.path().join("song.lrc"), "[00:01.00]Timed words\n").unwrap();
+        assert_eq!(
+            read_sidecar_lyrics(&audio, false).as_deref(),
+            Some("Timed words")
+        );
+        assert_eq!(
+            read_sidecar_lyrics(&audio, true).as_deref(),
+            Some("[00:01.00]Timed words")
+        );
+
+        std::fs::write(
+            temp.path().join("song.lrc"),
+            b"\xEF\xBB\xBF[00:01.00]Windows words\r\n",
+        )
+        .unwrap();
+        assert_eq!(
+            read_sidecar_lyrics(&audio, false).as_deref(),
+            Some("Windows words")
+        );
+    }
+
+    #[test]
+    fn sidecar_reads_gzipped_files_and_skips_corrupt_ones() {
+        use std::io::Write;
+
+        let temp = tempfile::TempDir::new().unwrap();
+        let audio = temp.path().join("song.mp3");
+        std::fs::write(temp.path().join("song.lrc.gz"), b"not gzip at all").unwrap();
+        assert_eq!(read_sidecar_lyrics(&audio, false), None);
+
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(b"[00:01.00]Packed words\n").unwrap();
+        std::fs::write(temp.path().join("song.txt.gz"), encoder.finish().unwrap()).unwrap();
+        assert_eq!(
+            read_sidecar_lyrics(&audio, false).as_deref(),
+            Some("Packed words")
+        );
+    }
+
+    #[test]
+    fn only_io_hiccups_count_as_transient() {
+        let timeout =
+            anyhow::Error::from(id3::Error::from(io::Error::from(io::ErrorKind::TimedOut)));
+        assert!(is_transient(&timeout));
+        let wrapped = anyhow::Error::from(io::Error::from(io::ErrorKind::WouldBlock))
+            .context("failed to open Ogg file");
+        assert!(is_transient(&wrapped));
+
+        let missing = anyhow::Error::from(io::Error::from(io::ErrorKind::NotFound));
+        assert!(!is_transient(&missing));
+        let bad_tag = anyhow::Error::from(id3::Error::new(id3::ErrorKind::NoTag, "no tag"));
+        assert!(!is_transient(&bad_tag));
+    }
+}
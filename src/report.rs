@@ -1,55 +1,241 @@
-use std::path::PathBuf;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 use log::{info, warn};
 use serde::Serialize;
 
+use crate::metadata::TrackMetadata;
+
+/// Most failed paths kept per error kind; the counters keep counting past it.
+pub const MAX_ERROR_PATHS: usize = 1000;
+
 #[derive(Debug, Default)]
 pub struct Report {
     pub scanned: usize,
     pub matched: usize,
+    /// Matched tracks written without lyrics under `--include-empty`; included in `matched`.
+    pub matched_empty: usize,
     pub skipped_artist: usize,
+    pub filtered: usize,
+    pub unchanged: usize,
+    /// Files below `--min-size`, skipped without reading their tags.
+    pub too_small: usize,
+    /// Tracks outside `--min-duration`/`--max-duration`.
+    pub outside_duration: usize,
     pub missing_lyrics: usize,
+    pub duplicates: usize,
+    /// Matches not written because `--skip-existing` found them in the output already.
+    pub skipped_existing: usize,
+    /// Matches not written because they are listed in the `--exclude-from` reference.
+    pub excluded: usize,
+    /// Matches declined at the `--interactive` prompt.
+    pub rejected: usize,
+    /// Lines and characters (Unicode scalar values) of lyrics written for matched tracks.
+    pub total_lyric_lines: usize,
+    pub total_lyric_chars: usize,
+    /// Distinct artist names of the tracks written, for auditing the artist filter.
+    pub matched_artists: BTreeSet<String>,
+    /// Bytes of every scanned file whose size was known, and the biggest of them.
+    pub total_bytes: u64,
+    pub largest_file: Option<FileSize>,
     pub depth_skipped_dirs: usize,
     pub depth_skip_paths: Vec<PathBuf>,
     pub walk_errors: usize,
+    pub walk_error_paths: Vec<PathBuf>,
     pub tag_errors: usize,
+    pub tag_error_paths: Vec<PathBuf>,
+    /// Followed symlinks leading back to an ancestor directory; not counted as walk errors.
+    pub symlink_loops: usize,
+    pub symlink_loop_paths: Vec<PathBuf>,
+    /// Every walk and tag error with its message, uncapped, for `--errors-json`.
+    pub errors: Vec<ErrorRecord>,
+    /// Per lowercased file extension.
+    pub scanned_by_extension: HashMap<String, usize>,
+    pub matched_by_extension: HashMap<String, usize>,
+    pub elapsed: Duration,
 }
 
+/// Version of the `--summary-json` layout. Bumped whenever a field is removed, renamed or changes
+/// meaning; new fields may appear without a bump, so consumers should ignore unknown keys.
+pub const SUMMARY_SCHEMA_VERSION: u32 = 1;
+
+/// Serialized form of a `Report`; fields are written in declaration order.
 #[derive(Debug, Serialize)]
 pub struct Summary {
+    pub schema_version: u32,
     pub scanned: usize,
     pub matched: usize,
+    pub matched_empty: usize,
     pub skipped_artist: usize,
+    pub filtered: usize,
+    pub unchanged: usize,
+    pub too_small: usize,
+    pub outside_duration: usize,
     pub missing_lyrics: usize,
+    pub duplicates: usize,
+    pub skipped_existing: usize,
+    pub excluded: usize,
+    pub rejected: usize,
+    pub total_lyric_lines: usize,
+    pub total_lyric_chars: usize,
+    pub avg_lyric_chars: f64,
+    pub matched_artists: BTreeSet<String>,
+    pub total_bytes: u64,
+    pub largest_file: Option<FileSize>,
     pub walk_errors: usize,
+    pub walk_error_paths: Vec<PathBuf>,
     pub tag_errors: usize,
+    pub tag_error_paths: Vec<PathBuf>,
+    pub symlink_loops: usize,
+    pub symlink_loop_paths: Vec<PathBuf>,
     pub depth_skipped_dirs: usize,
     pub depth_skip_paths: Vec<PathBuf>,
+    pub scanned_by_extension: BTreeMap<String, usize>,
+    pub matched_by_extension: BTreeMap<String, usize>,
+    pub elapsed_ms: u64,
+    pub files_per_sec: f64,
+}
+
+/// One failure, as written to `--errors-json`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ErrorRecord {
+    /// Missing for traversal errors walkdir could not attribute to a path.
+    pub path: Option<PathBuf>,
+    pub kind: ErrorKind,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    /// The directory walk failed, e.g. a permission error or symlink loop.
+    Walk,
+    /// The file was found but its tags could not be read.
+    Tag,
+    /// A followed symlink pointed back at one of its ancestors and was not descended into.
+    SymlinkLoop,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FileSize {
+    pub path: PathBuf,
+    pub size: u64,
 }
 
 impl Report {
-    pub fn record_scan(&mut self) {
+    pub fn record_scan(&mut self, path: &Path, size: Option<u64>) {
         self.scanned += 1;
+        if let Some(size) = size {
+            self.total_bytes += size;
+            if self
+                .largest_file
+                .as_ref()
+                .is_none_or(|largest| size > largest.size)
+            {
+                self.largest_file = Some(FileSize {
+                    path: path.to_path_buf(),
+                    size,
+                });
+            }
+        }
+        *self
+            .scanned_by_extension
+            .entry(extension_key(path))
+            .or_default() += 1;
+    }
+
+    pub fn record_match(&mut self, path: &Path, track: &TrackMetadata) {
+        self.record_counted_match(path, &track.artist);
+        if track.lyrics.is_empty() {
+            self.matched_empty += 1;
+        }
+        self.total_lyric_lines += track.lyrics.lines().count();
+        self.total_lyric_chars += track.lyrics.chars().count();
     }
 
-    pub fn record_match(&mut self) {
+    /// A `--count-only` match: counted with its artist and extension, but without lyrics.
+    pub fn record_counted_match(&mut self, path: &Path, artist: &str) {
         self.matched += 1;
+        if !self.matched_artists.contains(artist) {
+            self.matched_artists.insert(artist.to_owned());
+        }
+        *self
+            .matched_by_extension
+            .entry(extension_key(path))
+            .or_default() += 1;
     }
 
     pub fn record_artist_skip(&mut self) {
         self.skipped_artist += 1;
     }
 
+    pub fn record_filtered(&mut self) {
+        self.filtered += 1;
+    }
+
+    pub fn record_unchanged(&mut self) {
+        self.unchanged += 1;
+    }
+
+    pub fn record_too_small(&mut self) {
+        self.too_small += 1;
+    }
+
+    pub fn record_outside_duration(&mut self) {
+        self.outside_duration += 1;
+    }
+
     pub fn record_missing_lyrics(&mut self) {
         self.missing_lyrics += 1;
     }
 
-    pub fn record_walk_error(&mut self) {
+    pub fn record_duplicate(&mut self) {
+        self.duplicates += 1;
+    }
+
+    pub fn record_skipped_existing(&mut self) {
+        self.skipped_existing += 1;
+    }
+
+    pub fn record_excluded(&mut self) {
+        self.excluded += 1;
+    }
+
+    pub fn record_rejected(&mut self) {
+        self.rejected += 1;
+    }
+
+    pub fn record_walk_error(&mut self, path: Option<&Path>, message: String) {
         self.walk_errors += 1;
+        if let Some(path) = path {
+            push_capped(&mut self.walk_error_paths, path);
+        }
+        self.errors.push(ErrorRecord {
+            path: path.map(Path::to_path_buf),
+            kind: ErrorKind::Walk,
+            message,
+        });
     }
 
-    pub fn record_tag_error(&mut self) {
+    pub fn record_tag_error(&mut self, path: &Path, message: String) {
         self.tag_errors += 1;
+        push_capped(&mut self.tag_error_paths, path);
+        self.errors.push(ErrorRecord {
+            path: Some(path.to_path_buf()),
+            kind: ErrorKind::Tag,
+            message,
+        });
+    }
+
+    pub fn record_symlink_loop(&mut self, path: &Path, ancestor: &Path) {
+        self.symlink_loops += 1;
+        push_capped(&mut self.symlink_loop_paths, path);
+        self.errors.push(ErrorRecord {
+            path: Some(path.to_path_buf()),
+            kind: ErrorKind::SymlinkLoop,
+            message: format!("symlink loops back to '{}'", ancestor.display()),
+        });
     }
 
     pub fn record_depth_skips(&mut self, count: usize, paths: Vec<PathBuf>) {
@@ -57,29 +243,131 @@ impl Report {
         self.depth_skip_paths.extend(paths);
     }
 
+    pub fn record_elapsed(&mut self, elapsed: Duration) {
+        self.elapsed = elapsed;
+    }
+
+    /// Mean lyric characters per matched track; zero when nothing matched.
+    pub fn avg_lyric_chars(&self) -> f64 {
+        if self.matched > 0 {
+            self.total_lyric_chars as f64 / self.matched as f64
+        } else {
+            0.0
+        }
+    }
+
+    /// Scanned files per second of wall-clock time; zero when nothing was timed.
+    pub fn files_per_sec(&self) -> f64 {
+        let seconds = self.elapsed.as_secs_f64();
+        if seconds > 0.0 {
+            self.scanned as f64 / seconds
+        } else {
+            0.0
+        }
+    }
+
     pub fn summary(&self) -> Summary {
         Summary {
+            schema_version: SUMMARY_SCHEMA_VERSION,
             scanned: self.scanned,
             matched: self.matched,
+            matched_empty: self.matched_empty,
             skipped_artist: self.skipped_artist,
+            filtered: self.filtered,
+            unchanged: self.unchanged,
+            too_small: self.too_small,
+            outside_duration: self.outside_duration,
             missing_lyrics: self.missing_lyrics,
+            duplicates: self.duplicates,
+            skipped_existing: self.skipped_existing,
+            excluded: self.excluded,
+            rejected: self.rejected,
+            total_lyric_lines: self.total_lyric_lines,
+            total_lyric_chars: self.total_lyric_chars,
+            avg_lyric_chars: self.avg_lyric_chars(),
+            matched_artists: self.matched_artists.clone(),
+            total_bytes: self.total_bytes,
+            largest_file: self.largest_file.clone(),
             walk_errors: self.walk_errors,
+            walk_error_paths: self.walk_error_paths.clone(),
             tag_errors: self.tag_errors,
+            tag_error_paths: self.tag_error_paths.clone(),
+            symlink_loops: self.symlink_loops,
+            symlink_loop_paths: self.symlink_loop_paths.clone(),
             depth_skipped_dirs: self.depth_skipped_dirs,
             depth_skip_paths: self.depth_skip_paths.clone(),
+            scanned_by_extension: self.scanned_by_extension.clone().into_iter().collect(),
+            matched_by_extension: self.matched_by_extension.clone().into_iter().collect(),
+            elapsed_ms: u64::try_from(self.elapsed.as_millis()).unwrap_or(u64::MAX),
+            files_per_sec: self.files_per_sec(),
         }
     }
 
+    /// `summary()` with every path made relative to the scan root containing it, for
+    /// `--relative-paths`.
+    pub fn relative_summary(&self, roots: &[PathBuf]) -> Summary {
+        let relative = |paths: &[PathBuf]| -> Vec<PathBuf> {
+            paths
+                .iter()
+                .map(|path| relative_to_roots(path, roots))
+                .collect()
+        };
+        let mut summary = self.summary();
+        summary.walk_error_paths = relative(&summary.walk_error_paths);
+        summary.tag_error_paths = relative(&summary.tag_error_paths);
+        summary.symlink_loop_paths = relative(&summary.symlink_loop_paths);
+        summary.depth_skip_paths = relative(&summary.depth_skip_paths);
+        if let Some(largest) = summary.largest_file.as_mut() {
+            largest.path = relative_to_roots(&largest.path, roots);
+        }
+        summary
+    }
+
+    /// `errors` with paths made relative as in `relative_summary`.
+    pub fn relative_errors(&self, roots: &[PathBuf]) -> Vec<ErrorRecord> {
+        self.errors
+            .iter()
+            .map(|error| ErrorRecord {
+                path: error
+                    .path
+                    .as_deref()
+                    .map(|path| relative_to_roots(path, roots)),
+                ..error.clone()
+            })
+            .collect()
+    }
+
     pub fn emit_summary(&self) {
         info!(
-            "Scanned {scanned} MP3 files -- matched {matched}, artist skips {skipped}, missing lyrics {missing}, directories at depth limit {depth_skipped}",
+            "Scanned {scanned} audio files -- matched {matched}, artist skips {skipped}, filtered {filtered}, unchanged {unchanged}, too small {too_small}, outside duration {outside_duration}, missing lyrics {missing}, duplicates {duplicates}, already written {skipped_existing}, excluded {excluded}, rejected {rejected}, lyric lines {lines} ({chars} chars), directories at depth limit {depth_skipped} in {elapsed:.2?} ({rate:.1} files/s)",
             scanned = self.scanned,
             matched = self.matched,
             skipped = self.skipped_artist,
+            filtered = self.filtered,
+            unchanged = self.unchanged,
+            too_small = self.too_small,
+            outside_duration = self.outside_duration,
             missing = self.missing_lyrics,
+            duplicates = self.duplicates,
+            skipped_existing = self.skipped_existing,
+            excluded = self.excluded,
+            rejected = self.rejected,
+            lines = self.total_lyric_lines,
+            chars = self.total_lyric_chars,
             depth_skipped = self.depth_skipped_dirs,
+            elapsed = self.elapsed,
+            rate = self.files_per_sec(),
         );
 
+        if let Some(largest) = &self.largest_file {
+            info!(
+                "Scanned {total} bytes of audio; largest file '{path}' ({size} bytes)",
+                total = self.total_bytes,
+                path = largest.path.display(),
+                size = largest.size,
+            );
+        }
+
         if !self.depth_skip_paths.is_empty() {
             for path in &self.depth_skip_paths {
                 info!(
@@ -96,7 +384,47 @@ impl Report {
                 tag_errors = self.tag_errors
             );
         }
+
+        if self.symlink_loops > 0 {
+            warn!(
+                "Skipped {count} symlink loops; the linked directories were not scanned again.",
+                count = self.symlink_loops
+            );
+        }
     }
+
+    /// Logs each distinct matched artist, for `--list-artists`.
+    pub fn emit_matched_artists(&self) {
+        info!(
+            "Matched {count} distinct artists:",
+            count = self.matched_artists.len()
+        );
+        for artist in &self.matched_artists {
+            info!("  {artist}");
+        }
+    }
+}
+
+/// `path` relative to the first of `roots` containing it; paths outside every root (e.g. reached
+/// through a symlink) stay as they are.
+pub fn relative_to_roots(path: &Path, roots: &[PathBuf]) -> PathBuf {
+    roots
+        .iter()
+        .find_map(|root| path.strip_prefix(root).ok())
+        .unwrap_or(path)
+        .to_path_buf()
+}
+
+fn push_capped(paths: &mut Vec<PathBuf>, path: &Path) {
+    if paths.len() < MAX_ERROR_PATHS {
+        paths.push(path.to_path_buf());
+    }
+}
+
+fn extension_key(path: &Path) -> String {
+    path.extension()
+        .map(|ext| ext.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default()
 }
 
 #[cfg(test)]
@@ -106,24 +434,166 @@ mod tests {
     #[test]
     fn summary_reflects_collected_counts() {
         let mut report = Report::default();
-        report.record_scan();
-        report.record_scan();
-        report.record_match();
+        report.record_scan(Path::new("a.mp3"), Some(300));
+        report.record_scan(Path::new("b.FLAC"), Some(700));
+        report.record_match(
+            Path::new("b.FLAC"),
+            &TrackMetadata {
+                artist: "Studio Band".to_string(),
+                title: "Zwei".to_string(),
+                lyrics: "Grüße\nDrei".to_string(),
+                album: None,
+                album_artist: None,
+                audio: None,
+                source: None,
+                hash: None,
+            },
+        );
         report.record_artist_skip();
+        report.record_filtered();
+        report.record_unchanged();
+        report.record_too_small();
+        report.record_outside_duration();
         report.record_missing_lyrics();
-        report.record_walk_error();
-        report.record_tag_error();
+        report.record_duplicate();
+        report.record_skipped_existing();
+        report.record_excluded();
+        report.record_rejected();
+        report.record_walk_error(None, "loop detected".into());
+        report.record_tag_error(Path::new("broken.mp3"), "no tag found".into());
+        report.record_symlink_loop(Path::new("music/loop"), Path::new("music"));
         report.record_depth_skips(1, vec![PathBuf::from("deep")]);
+        report.record_elapsed(Duration::from_millis(500));
 
         let summary = report.summary();
 
+        assert_eq!(summary.schema_version, SUMMARY_SCHEMA_VERSION);
         assert_eq!(summary.scanned, 2);
         assert_eq!(summary.matched, 1);
+        assert_eq!(summary.matched_empty, 0);
         assert_eq!(summary.skipped_artist, 1);
+        assert_eq!(summary.filtered, 1);
+        assert_eq!(summary.unchanged, 1);
+        assert_eq!(summary.too_small, 1);
+        assert_eq!(summary.outside_duration, 1);
         assert_eq!(summary.missing_lyrics, 1);
+        assert_eq!(summary.duplicates, 1);
+        assert_eq!(summary.skipped_existing, 1);
+        assert_eq!(summary.excluded, 1);
+        assert_eq!(summary.rejected, 1);
+        assert_eq!(summary.total_lyric_lines, 2);
+        assert_eq!(summary.total_lyric_chars, 10);
+        assert_eq!(summary.avg_lyric_chars, 10.0);
+        assert_eq!(Report::default().summary().avg_lyric_chars, 0.0);
+        assert_eq!(summary.total_bytes, 1000);
+        assert_eq!(
+            summary.largest_file,
+            Some(FileSize {
+                path: PathBuf::from("b.FLAC"),
+                size: 700,
+            })
+        );
+        assert_eq!(
+            summary.matched_artists,
+            BTreeSet::from(["Studio Band".to_string()])
+        );
         assert_eq!(summary.walk_errors, 1);
         assert_eq!(summary.tag_errors, 1);
+        assert!(summary.walk_error_paths.is_empty());
+        assert_eq!(summary.tag_error_paths, vec![PathBuf::from("broken.mp3")]);
+        assert_eq!(summary.symlink_loops, 1);
+        assert_eq!(
+            summary.symlink_loop_paths,
+            vec![PathBuf::from("music/loop")]
+        );
         assert_eq!(summary.depth_skipped_dirs, 1);
         assert_eq!(summary.depth_skip_paths, vec![PathBuf::from("deep")]);
+        assert_eq!(
+            summary.scanned_by_extension,
+            BTreeMap::from([("flac".to_string(), 1), ("mp3".to_string(), 1)])
+        );
+        assert_eq!(
+            summary.matched_by_extension,
+            BTreeMap::from([("flac".to_string(), 1)])
+        );
+        assert_eq!(summary.elapsed_ms, 500);
+        assert_eq!(summary.files_per_sec, 4.0);
+    }
+
+    #[test]
+    fn unknown_sizes_count_as_scanned_without_bytes() {
+        let mut report = Report::default();
+        report.record_scan(Path::new("a.mp3"), None);
+
+        let summary = report.summary();
+        assert_eq!(summary.scanned, 1);
+        assert_eq!(summary.total_bytes, 0);
+        assert_eq!(summary.largest_file, None);
+    }
+
+    #[test]
+    fn throughput_is_zero_without_elapsed_time() {
+        let mut report = Report::default();
+        report.record_scan(Path::new("a.mp3"), None);
+
+        assert_eq!(report.summary().files_per_sec, 0.0);
+    }
+
+    #[test]
+    fn error_paths_are_capped_but_counted() {
+        let mut report = Report::default();
+        for index in 0..MAX_ERROR_PATHS + 5 {
+            report.record_tag_error(Path::new(&format!("bad-{index}.mp3")), "bad".into());
+        }
+
+        let summary = report.summary();
+        assert_eq!(summary.tag_errors, MAX_ERROR_PATHS + 5);
+        assert_eq!(summary.tag_error_paths.len(), MAX_ERROR_PATHS);
+        assert_eq!(report.errors.len(), MAX_ERROR_PATHS + 5);
+    }
+
+    #[test]
+    fn error_records_serialize_with_kind_and_message() {
+        let mut report = Report::default();
+        report.record_walk_error(None, "permission denied".into());
+        report.record_tag_error(Path::new("broken.mp3"), "no tag found".into());
+
+        assert_eq!(
+            serde_json::to_value(&report.errors).unwrap(),
+            serde_json::json!([
+                {"path": null, "kind": "walk", "message": "permission denied"},
+                {"path": "broken.mp3", "kind": "tag", "message": "no tag found"},
+            ])
+        );
+    }
+
+    #[test]
+    fn relative_summary_strips_the_root_prefix() {
+        let roots = [PathBuf::from("/music")];
+        let mut report = Report::default();
+        report.record_scan(Path::new("/music/a/big.mp3"), Some(900));
+        report.record_tag_error(Path::new("/music/broken.mp3"), "bad tag".to_string());
+        report.record_tag_error(Path::new("/elsewhere/x.mp3"), "bad tag".to_string());
+
+        let summary = report.relative_summary(&roots);
+        assert_eq!(
+            summary.largest_file.map(|largest| largest.path),
+            Some(PathBuf::from("a/big.mp3"))
+        );
+        assert_eq!(
+            summary.tag_error_paths,
+            vec![
+                PathBuf::from("broken.mp3"),
+                PathBuf::from("/elsewhere/x.mp3")
+            ]
+        );
+        assert_eq!(
+            report.relative_errors(&roots)[0].path,
+            Some(PathBuf::from("broken.mp3"))
+        );
+        assert_eq!(
+            report.summary().tag_error_paths[0],
+            PathBuf::from("/music/broken.mp3")
+        );
     }
 }
@@ -1,42 +1,83 @@
+use std::io::{self, BufRead};
 use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
 
+use anyhow::{bail, Context, Result};
+use globset::GlobSet;
 use walkdir::{DirEntry, IntoIter, WalkDir};
 
+/// Extension-list entry that accepts every file, with or without an extension.
+pub const ALL_EXTENSIONS: &str = "*";
+
 pub struct Scanner {
-    root: PathBuf,
+    roots: Vec<PathBuf>,
+    min_depth: Option<usize>,
     max_depth: Option<usize>,
     follow_symlinks: bool,
     extensions: Arc<Vec<String>>,
+    exclude: Arc<GlobSet>,
+    include: Arc<GlobSet>,
+    modified_since: Option<SystemTime>,
     skipped_due_to_depth: Arc<AtomicUsize>,
     skipped_paths: Arc<Mutex<Vec<PathBuf>>>,
 }
 
 impl Scanner {
+    /// Walks each of `roots` in turn; depth limits apply per root.
     pub fn new(
-        root: &Path,
+        roots: &[PathBuf],
+        min_depth: Option<usize>,
         max_depth: Option<usize>,
         follow_symlinks: bool,
         extensions: Vec<String>,
+        exclude: GlobSet,
+        include: GlobSet,
     ) -> Self {
         Self {
-            root: root.to_path_buf(),
+            roots: roots.to_vec(),
+            min_depth,
             max_depth,
             follow_symlinks,
             extensions: Arc::new(extensions),
+            exclude: Arc::new(exclude),
+            include: Arc::new(include),
+            modified_since: None,
             skipped_due_to_depth: Arc::new(AtomicUsize::new(0)),
             skipped_paths: Arc::new(Mutex::new(Vec::new())),
         }
     }
 
+    /// Only yield files modified strictly after `since`.
+    pub fn with_modified_since(mut self, since: Option<SystemTime>) -> Self {
+        self.modified_since = since;
+        self
+    }
+
     pub fn walk(&self) -> ScannerIter {
-        let walkdir = WalkDir::new(&self.root).follow_links(self.follow_symlinks);
+        let mut walkers = self
+            .roots
+            .iter()
+            .map(|root| {
+                WalkDir::new(root)
+                    .follow_links(self.follow_symlinks)
+                    .sort_by_file_name()
+                    .into_iter()
+            })
+            .collect::<Vec<_>>()
+            .into_iter();
 
         ScannerIter {
-            inner: walkdir.into_iter(),
+            inner: walkers.next(),
+            remaining: walkers,
+            min_depth: self.min_depth,
             max_depth: self.max_depth,
             extensions: Arc::clone(&self.extensions),
+            exclude: Arc::clone(&self.exclude),
+            include: Arc::clone(&self.include),
+            modified_since: self.modified_since,
             skipped_due_to_depth: Arc::clone(&self.skipped_due_to_depth),
             skipped_paths: Arc::clone(&self.skipped_paths),
         }
@@ -55,21 +96,243 @@ impl Scanner {
     }
 }
 
+/// A file the scanner selected, with its size when the walk could stat it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FoundFile {
+    pub path: PathBuf,
+    pub size: Option<u64>,
+}
+
+/// A path the scan could not visit: a traversal failure, or a `--from-file` entry that could
+/// not be stat'ed.
+#[derive(Debug)]
+pub struct VisitError {
+    path: Option<PathBuf>,
+    /// Directory a followed symlink at `path` leads back to, when the failure is a loop.
+    loop_ancestor: Option<PathBuf>,
+    source: io::Error,
+}
+
+impl VisitError {
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Set when `--follow-symlinks` reached a symlink pointing at one of its own ancestors.
+    pub fn loop_ancestor(&self) -> Option<&Path> {
+        self.loop_ancestor.as_deref()
+    }
+}
+
+impl std::fmt::Display for VisitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.source.fmt(f)
+    }
+}
+
+impl std::error::Error for VisitError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+impl From<walkdir::Error> for VisitError {
+    fn from(error: walkdir::Error) -> Self {
+        Self {
+            path: error.path().map(Path::to_path_buf),
+            loop_ancestor: error.loop_ancestor().map(Path::to_path_buf),
+            source: io::Error::from(error),
+        }
+    }
+}
+
+/// Reads `--from-file`: one path per line, `-` meaning stdin. Blank lines are skipped and
+/// relative paths resolve against the working directory.
+pub fn read_file_list(list: &Path) -> Result<Vec<PathBuf>> {
+    let reader: Box<dyn BufRead> = if list == Path::new("-") {
+        Box::new(io::stdin().lock())
+    } else {
+        let file = std::fs::File::open(list)
+            .with_context(|| format!("failed to open file list '{}'", list.display()))?;
+        Box::new(io::BufReader::new(file))
+    };
+
+    let mut paths = Vec::new();
+    for line in reader.lines() {
+        let line =
+            line.with_context(|| format!("failed to read file list '{}'", list.display()))?;
+        let line = line.trim_end_matches('\r');
+        if line.trim().is_empty() {
+            continue;
+        }
+        paths.push(
+            std::path::absolute(line)
+                .with_context(|| format!("failed to resolve listed path '{line}'"))?,
+        );
+    }
+    Ok(paths)
+}
+
+/// `--git-since`: the files under each root that `git diff --name-only` reports as changed since
+/// `rev`, as absolute paths. Committed and uncommitted changes to tracked files count; deleted
+/// files are left out, and git does not report untracked ones.
+pub fn git_changed_files(roots: &[PathBuf], rev: &str) -> Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    for root in roots {
+        let inside = Command::new("git")
+            .arg("-C")
+            .arg(root)
+            .args(["rev-parse", "--is-inside-work-tree"])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .context("failed to run git for --git-since")?;
+        if !inside.success() {
+            bail!(
+                "--git-since needs a git repository, but '{}' is not inside one",
+                root.display()
+            );
+        }
+
+        // `--relative` limits the diff to the root and makes the names relative to it.
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(root)
+            .args(["diff", "--name-only", "-z", "--relative", "--diff-filter=d"])
+            .arg(rev)
+            .arg("--")
+            .output()
+            .context("failed to run git for --git-since")?;
+        if !output.status.success() {
+            bail!(
+                "git diff against '{rev}' failed in '{}': {}",
+                root.display(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        paths.extend(
+            output
+                .stdout
+                .split(|&byte| byte == 0)
+                .filter(|name| !name.is_empty())
+                .map(|name| root.join(path_from_bytes(name.to_vec()))),
+        );
+    }
+    Ok(paths)
+}
+
+/// Streams `--stdin0`: NUL-separated paths read from stdin as they arrive, so `find -print0`
+/// output can be processed without collecting it first.
+pub fn stdin_paths_nul() -> impl Iterator<Item = io::Result<PathBuf>> + Send {
+    nul_separated_paths(io::BufReader::new(io::stdin()))
+}
+
+/// Splits `reader` on NUL bytes. Empty entries are skipped and relative paths resolve against the
+/// working directory; a read error is yielded once and ends the stream.
+fn nul_separated_paths(
+    reader: impl BufRead + Send,
+) -> impl Iterator<Item = io::Result<PathBuf>> + Send {
+    let mut entries = reader.split(b'\0');
+    let mut failed = false;
+    std::iter::from_fn(move || loop {
+        if failed {
+            return None;
+        }
+        match entries.next()? {
+            Ok(bytes) if bytes.is_empty() => continue,
+            Ok(bytes) => return Some(std::path::absolute(path_from_bytes(bytes))),
+            Err(error) => {
+                failed = true;
+                return Some(Err(error));
+            }
+        }
+    })
+}
+
+#[cfg(unix)]
+fn path_from_bytes(bytes: Vec<u8>) -> PathBuf {
+    use std::os::unix::ffi::OsStringExt;
+    PathBuf::from(std::ffi::OsString::from_vec(bytes))
+}
+
+#[cfg(not(unix))]
+fn path_from_bytes(bytes: Vec<u8>) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// Yields the listed paths that are files with an allowed extension, in list order, instead of
+/// walking a tree. Depth, glob and modification-time limits do not apply. Paths are stat'ed as
+/// they are pulled, so a streamed list is never held in memory; a failed read of the list itself
+/// surfaces as a path-less `VisitError`.
+pub fn listed_files(
+    paths: impl IntoIterator<Item = io::Result<PathBuf>, IntoIter: Send>,
+    extensions: Vec<String>,
+) -> impl Iterator<Item = Result<FoundFile, VisitError>> + Send {
+    paths.into_iter().filter_map(move |path| {
+        let path = match path {
+            Ok(path) => path,
+            Err(source) => {
+                return Some(Err(VisitError {
+                    path: None,
+                    loop_ancestor: None,
+                    source,
+                }))
+            }
+        };
+        stat_listed(path, &extensions)
+    })
+}
+
+fn stat_listed(path: PathBuf, extensions: &[String]) -> Option<Result<FoundFile, VisitError>> {
+    match std::fs::metadata(&path) {
+        Ok(metadata) if metadata.is_file() && has_allowed_extension(&path, extensions) => {
+            Some(Ok(FoundFile {
+                path,
+                size: Some(metadata.len()),
+            }))
+        }
+        Ok(_) => None,
+        Err(source) => Some(Err(VisitError {
+            path: Some(path),
+            loop_ancestor: None,
+            source,
+        })),
+    }
+}
+
 pub struct ScannerIter {
-    inner: IntoIter,
+    /// Walk of the root currently being scanned; `remaining` holds the roots after it.
+    inner: Option<IntoIter>,
+    remaining: std::vec::IntoIter<IntoIter>,
+    min_depth: Option<usize>,
     max_depth: Option<usize>,
     extensions: Arc<Vec<String>>,
+    exclude: Arc<GlobSet>,
+    include: Arc<GlobSet>,
+    modified_since: Option<SystemTime>,
     skipped_due_to_depth: Arc<AtomicUsize>,
     skipped_paths: Arc<Mutex<Vec<PathBuf>>>,
 }
 
 impl Iterator for ScannerIter {
-    type Item = Result<PathBuf, walkdir::Error>;
+    type Item = Result<FoundFile, VisitError>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        while let Some(entry) = self.inner.next() {
+        loop {
+            let inner = self.inner.as_mut()?;
+            let Some(entry) = inner.next() else {
+                self.inner = self.remaining.next();
+                continue;
+            };
             match entry {
                 Ok(entry) => {
+                    if entry.depth() > 0 && is_excluded(&entry, &self.exclude) {
+                        if entry.file_type().is_dir() {
+                            inner.skip_current_dir();
+                        }
+                        continue;
+                    }
+
                     if let Some(limit) = self.max_depth {
                         if entry.depth() > limit {
                             continue;
@@ -79,27 +342,77 @@ impl Iterator for ScannerIter {
                             if let Ok(mut paths) = self.skipped_paths.lock() {
                                 paths.push(entry.path().to_path_buf());
                             }
-                            self.inner.skip_current_dir();
+                            inner.skip_current_dir();
                             continue;
                         }
                     }
 
-                    if is_target(&entry, &self.extensions) {
-                        return Some(Ok(entry.into_path()));
+                    if let Some(min) = self.min_depth {
+                        if entry.depth() < min {
+                            continue;
+                        }
                     }
+
+                    if !is_target(&entry, &self.extensions, &self.include) {
+                        continue;
+                    }
+                    if let Some(since) = self.modified_since {
+                        if !modified_after(&entry, since) {
+                            continue;
+                        }
+                    }
+                    // walkdir has usually stat'ed the entry already, so this rarely costs a syscall.
+                    let size = entry.metadata().ok().map(|metadata| metadata.len());
+                    return Some(Ok(FoundFile {
+                        path: entry.into_path(),
+                        size,
+                    }));
                 }
-                Err(error) => return Some(Err(error)),
+                Err(error) => return Some(Err(error.into())),
             }
         }
-        None
     }
 }
 
-fn is_target(entry: &DirEntry, extensions: &[String]) -> bool {
-    entry.file_type().is_file() && has_allowed_extension(entry.path(), extensions)
+/// Globs are tried against both the full path and the bare file or directory name, so `@eaDir`
+/// and `**/@eaDir` both prune every such folder.
+fn matches_globs(entry: &DirEntry, globs: &GlobSet) -> bool {
+    globs.is_match(entry.path()) || globs.is_match(entry.file_name())
+}
+
+fn is_excluded(entry: &DirEntry, exclude: &GlobSet) -> bool {
+    !exclude.is_empty() && matches_globs(entry, exclude)
+}
+
+/// An empty include set accepts every file with an allowed extension.
+fn is_target(entry: &DirEntry, extensions: &[String], include: &GlobSet) -> bool {
+    entry.file_type().is_file()
+        && has_allowed_extension(entry.path(), extensions)
+        && (include.is_empty() || matches_globs(entry, include))
+}
+
+/// Files whose modification time cannot be read are kept, with a warning, rather than dropped.
+fn modified_after(entry: &DirEntry, since: SystemTime) -> bool {
+    let modified = entry
+        .metadata()
+        .map_err(std::io::Error::from)
+        .and_then(|metadata| metadata.modified());
+    match modified {
+        Ok(modified) => modified > since,
+        Err(error) => {
+            log::warn!(
+                "Could not read modification time of '{}', scanning it anyway: {error}",
+                entry.path().display()
+            );
+            true
+        }
+    }
 }
 
 fn has_allowed_extension(path: &Path, extensions: &[String]) -> bool {
+    if extensions.iter().any(|allowed| allowed == ALL_EXTENSIONS) {
+        return true;
+    }
     let ext = match path.extension().and_then(|ext| ext.to_str()) {
         Some(value) => value.to_ascii_lowercase(),
         None => return false,
@@ -116,6 +429,14 @@ mod tests {
 
     use tempfile::TempDir;
 
+    fn globs(patterns: &[&str]) -> GlobSet {
+        let mut builder = globset::GlobSetBuilder::new();
+        for pattern in patterns {
+            builder.add(globset::Glob::new(pattern).unwrap());
+        }
+        builder.build().unwrap()
+    }
+
     #[test]
     fn collects_mp3_files_recursively() {
         let temp = TempDir::new().unwrap();
@@ -130,8 +451,17 @@ mod tests {
         fs::write(temp.path().join("readme.txt"), b"ignore").unwrap();
         fs::create_dir(temp.path().join("not_audio.mp3")).unwrap();
 
-        let scanner = Scanner::new(temp.path(), None, false, vec!["mp3".into()]);
-        let mut collected: Vec<PathBuf> = scanner.walk().map(|res| res.expect("entry")).collect();
+        let scanner = Scanner::new(
+            &[temp.path().to_path_buf()],
+            None,
+            None,
+            false,
+            vec!["mp3".into()],
+            GlobSet::empty(),
+            GlobSet::empty(),
+        );
+        let mut collected: Vec<PathBuf> =
+            scanner.walk().map(|res| res.expect("entry").path).collect();
         collected.sort();
 
         assert_eq!(collected, vec![song1, song2]);
@@ -153,8 +483,17 @@ mod tests {
         fs::write(&mid, b"fake").unwrap();
         fs::write(&deep, b"fake").unwrap();
 
-        let scanner = Scanner::new(temp.path(), Some(2), false, vec!["mp3".into()]);
-        let mut collected: Vec<PathBuf> = scanner.walk().map(|res| res.expect("entry")).collect();
+        let scanner = Scanner::new(
+            &[temp.path().to_path_buf()],
+            None,
+            Some(2),
+            false,
+            vec!["mp3".into()],
+            GlobSet::empty(),
+            GlobSet::empty(),
+        );
+        let mut collected: Vec<PathBuf> =
+            scanner.walk().map(|res| res.expect("entry").path).collect();
         collected.sort();
 
         assert_eq!(collected, vec![shallow, mid]);
@@ -170,7 +509,15 @@ mod tests {
         let file = root.join("music.mp3");
         fs::write(&file, b"fake").unwrap();
 
-        let scanner = Scanner::new(&root, None, false, vec!["mp3".into()]);
+        let scanner = Scanner::new(
+            std::slice::from_ref(&root),
+            None,
+            None,
+            false,
+            vec!["mp3".into()],
+            GlobSet::empty(),
+            GlobSet::empty(),
+        );
         drop(temp);
 
         let mut iter = scanner.walk();
@@ -178,6 +525,27 @@ mod tests {
         assert!(first.is_err());
     }
 
+    #[test]
+    fn all_extensions_accepts_every_file() {
+        let temp = TempDir::new().unwrap();
+        let named = temp.path().join("track.bin");
+        let bare = temp.path().join("README");
+        fs::write(&named, b"fake").unwrap();
+        fs::write(&bare, b"fake").unwrap();
+
+        let scanner = Scanner::new(
+            &[temp.path().to_path_buf()],
+            None,
+            None,
+            false,
+            vec![ALL_EXTENSIONS.into()],
+            GlobSet::empty(),
+            GlobSet::empty(),
+        );
+        let collected: Vec<PathBuf> = scanner.walk().map(|res| res.expect("entry").path).collect();
+        assert_eq!(collected, vec![bare, named]);
+    }
+
     #[test]
     fn filters_multiple_extensions() {
         let temp = TempDir::new().unwrap();
@@ -188,12 +556,268 @@ mod tests {
         fs::write(&flac, b"fake").unwrap();
         fs::write(&txt, b"fake").unwrap();
 
-        let scanner = Scanner::new(temp.path(), None, false, vec!["mp3".into(), "flac".into()]);
-        let mut collected: Vec<PathBuf> = scanner.walk().map(|res| res.expect("entry")).collect();
+        let scanner = Scanner::new(
+            &[temp.path().to_path_buf()],
+            None,
+            None,
+            false,
+            vec!["mp3".into(), "flac".into()],
+            GlobSet::empty(),
+            GlobSet::empty(),
+        );
+        let mut collected: Vec<PathBuf> =
+            scanner.walk().map(|res| res.expect("entry").path).collect();
         collected.sort();
 
         let mut expected = vec![flac, mp3];
         expected.sort();
         assert_eq!(collected, expected);
     }
+
+    #[test]
+    fn min_depth_composes_with_max_depth() {
+        let temp = TempDir::new().unwrap();
+        let artist = temp.path().join("artist");
+        let album = artist.join("album");
+        let disc = album.join("disc");
+        fs::create_dir_all(&disc).unwrap();
+
+        let top = temp.path().join("top.mp3");
+        let level1 = artist.join("one.mp3");
+        let level2 = album.join("two.mp3");
+        let level3 = disc.join("three.mp3");
+        for path in [&top, &level1, &level2, &level3] {
+            fs::write(path, b"fake").unwrap();
+        }
+
+        let scanner = Scanner::new(
+            &[temp.path().to_path_buf()],
+            Some(2),
+            Some(3),
+            false,
+            vec!["mp3".into()],
+            GlobSet::empty(),
+            GlobSet::empty(),
+        );
+        let mut collected: Vec<PathBuf> =
+            scanner.walk().map(|res| res.expect("entry").path).collect();
+        collected.sort();
+
+        let mut expected = vec![level1, level2];
+        expected.sort();
+        assert_eq!(collected, expected);
+    }
+
+    #[test]
+    fn exclude_globs_prune_directories_and_files() {
+        let temp = TempDir::new().unwrap();
+        let trash = temp.path().join(".trash");
+        let synology = temp.path().join("album").join("@eaDir");
+        fs::create_dir_all(&trash).unwrap();
+        fs::create_dir_all(&synology).unwrap();
+
+        let kept = temp.path().join("album").join("keep.mp3");
+        let skipped = temp.path().join("album").join("skip-me.mp3");
+        for path in [
+            &kept,
+            &skipped,
+            &trash.join("old.mp3"),
+            &synology.join("thumb.mp3"),
+        ] {
+            fs::write(path, b"fake").unwrap();
+        }
+
+        let scanner = Scanner::new(
+            &[temp.path().to_path_buf()],
+            None,
+            None,
+            false,
+            vec!["mp3".into()],
+            globs(&[".trash", "@eaDir", "skip-*"]),
+            GlobSet::empty(),
+        );
+        let collected: Vec<PathBuf> = scanner.walk().map(|res| res.expect("entry").path).collect();
+
+        assert_eq!(collected, vec![kept]);
+    }
+
+    #[test]
+    fn include_globs_narrow_targets_and_exclude_wins() {
+        let temp = TempDir::new().unwrap();
+        let singles = temp.path().join("artist").join("Singles");
+        let albums = temp.path().join("artist").join("Albums");
+        fs::create_dir_all(&singles).unwrap();
+        fs::create_dir_all(&albums).unwrap();
+
+        let single = singles.join("hit.mp3");
+        let demo = singles.join("demo.mp3");
+        let album_track = albums.join("track.mp3");
+        let single_text = singles.join("notes.txt");
+        for path in [&single, &demo, &album_track, &single_text] {
+            fs::write(path, b"fake").unwrap();
+        }
+
+        let scanner = Scanner::new(
+            &[temp.path().to_path_buf()],
+            None,
+            None,
+            false,
+            vec!["mp3".into()],
+            globs(&["demo.*"]),
+            globs(&["*/Singles/*"]),
+        );
+        let collected: Vec<PathBuf> = scanner.walk().map(|res| res.expect("entry").path).collect();
+
+        assert_eq!(collected, vec![single]);
+    }
+
+    #[test]
+    fn modified_since_skips_older_files() {
+        let temp = TempDir::new().unwrap();
+        let old = temp.path().join("old.mp3");
+        let fresh = temp.path().join("fresh.mp3");
+        fs::write(&old, b"fake").unwrap();
+        fs::write(&fresh, b"fake").unwrap();
+
+        let now = SystemTime::now();
+        let day = std::time::Duration::from_secs(24 * 60 * 60);
+        fs::File::options()
+            .write(true)
+            .open(&old)
+            .unwrap()
+            .set_modified(now - 2 * day)
+            .unwrap();
+
+        let scanner = Scanner::new(
+            &[temp.path().to_path_buf()],
+            None,
+            None,
+            false,
+            vec!["mp3".into()],
+            GlobSet::empty(),
+            GlobSet::empty(),
+        )
+        .with_modified_since(Some(now - day));
+        let collected: Vec<PathBuf> = scanner.walk().map(|res| res.expect("entry").path).collect();
+
+        assert_eq!(collected, vec![fresh]);
+    }
+
+    #[test]
+    fn walks_each_root_with_its_own_depth() {
+        let temp = TempDir::new().unwrap();
+        let music = temp.path().join("music");
+        let downloads = temp.path().join("downloads");
+        fs::create_dir_all(music.join("deep")).unwrap();
+        fs::create_dir_all(downloads.join("deep")).unwrap();
+
+        let first = music.join("a.mp3");
+        let second = downloads.join("b.mp3");
+        for path in [
+            &first,
+            &second,
+            &music.join("deep").join("x.mp3"),
+            &downloads.join("deep").join("y.mp3"),
+        ] {
+            fs::write(path, b"fake").unwrap();
+        }
+
+        let scanner = Scanner::new(
+            &[music.clone(), downloads.clone()],
+            None,
+            Some(1),
+            false,
+            vec!["mp3".into()],
+            GlobSet::empty(),
+            GlobSet::empty(),
+        );
+        let collected: Vec<PathBuf> = scanner.walk().map(|res| res.expect("entry").path).collect();
+
+        assert_eq!(collected, vec![first, second]);
+        assert_eq!(
+            scanner.depth_skipped_paths(),
+            vec![music.join("deep"), downloads.join("deep")]
+        );
+    }
+
+    #[test]
+    fn reports_file_sizes() {
+        let temp = TempDir::new().unwrap();
+        let song = temp.path().join("song.mp3");
+        fs::write(&song, b"twelve bytes").unwrap();
+
+        let scanner = Scanner::new(
+            &[temp.path().to_path_buf()],
+            None,
+            None,
+            false,
+            vec!["mp3".into()],
+            GlobSet::empty(),
+            GlobSet::empty(),
+        );
+        let found: Vec<FoundFile> = scanner.walk().map(|res| res.expect("entry")).collect();
+
+        assert_eq!(
+            found,
+            vec![FoundFile {
+                path: song,
+                size: Some(12),
+            }]
+        );
+    }
+
+    #[test]
+    fn listed_files_filter_by_extension_and_report_missing_paths() {
+        let temp = TempDir::new().unwrap();
+        let song = temp.path().join("song.mp3");
+        let notes = temp.path().join("notes.txt");
+        let missing = temp.path().join("gone.mp3");
+        fs::write(&song, b"fake").unwrap();
+        fs::write(&notes, b"text").unwrap();
+
+        let list = temp.path().join("list.txt");
+        fs::write(
+            &list,
+            format!(
+                "{}\r\n\n{}\n{}\n",
+                song.display(),
+                notes.display(),
+                missing.display()
+            ),
+        )
+        .unwrap();
+
+        let paths = read_file_list(&list).expect("list");
+        assert_eq!(paths, vec![song.clone(), notes, missing.clone()]);
+
+        let results: Vec<_> = listed_files(paths.into_iter().map(Ok), vec!["mp3".into()]).collect();
+        assert_eq!(results.len(), 2);
+        assert_eq!(
+            results[0].as_ref().unwrap(),
+            &FoundFile {
+                path: song,
+                size: Some(4),
+            }
+        );
+        assert_eq!(
+            results[1].as_ref().unwrap_err().path(),
+            Some(missing.as_path())
+        );
+    }
+
+    #[test]
+    fn nul_separated_paths_skip_empty_entries() {
+        let input = b"/music/a song.mp3\0\0/music/line\nbreak.mp3\0";
+        let paths: Vec<_> = nul_separated_paths(&input[..])
+            .map(|path| path.unwrap())
+            .collect();
+
+        assert_eq!(
+            paths,
+            vec![
+                PathBuf::from("/music/a song.mp3"),
+                PathBuf::from("/music/line\nbreak.mp3")
+            ]
+        );
+    }
 }
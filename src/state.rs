@@ -0,0 +1,110 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+/// Modification time and size of a file as last processed; a file whose stamp is unchanged is
+/// skipped on the next `--state` run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileStamp {
+    pub modified_secs: u64,
+    pub modified_nanos: u32,
+    pub size: u64,
+}
+
+impl FileStamp {
+    /// Returns `None` when the file cannot be stat'ed; such files are always processed.
+    pub fn read(path: &Path) -> Option<Self> {
+        let metadata = std::fs::metadata(path).ok()?;
+        let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+        Some(Self {
+            modified_secs: modified.as_secs(),
+            modified_nanos: modified.subsec_nanos(),
+            size: metadata.len(),
+        })
+    }
+}
+
+/// Manifest read from and written to `--state`, keyed by absolute file path.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Manifest {
+    files: BTreeMap<PathBuf, FileStamp>,
+}
+
+impl Manifest {
+    /// Loads the manifest at `path`, starting empty when it does not exist yet.
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let raw = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read state file '{}'", path.display()))?;
+        serde_json::from_str(&raw)
+            .with_context(|| format!("failed to parse state file '{}'", path.display()))
+    }
+
+    pub fn is_unchanged(&self, path: &Path, stamp: &FileStamp) -> bool {
+        self.files.get(path) == Some(stamp)
+    }
+
+    pub fn record(&mut self, path: PathBuf, stamp: FileStamp) {
+        self.files.insert(path, stamp);
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).with_context(|| {
+                format!(
+                    "failed to create directories for state file '{}'",
+                    parent.display()
+                )
+            })?;
+        }
+        let json = serde_json::to_string_pretty(self).context("failed to serialize state")?;
+        std::fs::write(path, json)
+            .with_context(|| format!("failed to write state file '{}'", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs;
+
+    use tempfile::TempDir;
+
+    #[test]
+    fn round_trips_and_detects_changes() {
+        let temp = TempDir::new().unwrap();
+        let song = temp.path().join("song.mp3");
+        fs::write(&song, b"fake").unwrap();
+        let stamp = FileStamp::read(&song).expect("stamp");
+
+        let state_path = temp.path().join("state").join("manifest.json");
+        let mut manifest = Manifest::load(&state_path).expect("missing file loads empty");
+        assert!(!manifest.is_unchanged(&song, &stamp));
+
+        manifest.record(song.clone(), stamp);
+        manifest.save(&state_path).unwrap();
+
+        let reloaded = Manifest::load(&state_path).unwrap();
+        assert!(reloaded.is_unchanged(&song, &stamp));
+
+        fs::write(&song, b"longer fake").unwrap();
+        let changed = FileStamp::read(&song).unwrap();
+        assert!(!reloaded.is_unchanged(&song, &changed));
+    }
+
+    #[test]
+    fn malformed_state_names_path() {
+        let temp = TempDir::new().unwrap();
+        let state_path = temp.path().join("state.json");
+        fs::write(&state_path, "not json").unwrap();
+
+        let error = Manifest::load(&state_path).unwrap_err();
+        assert!(format!("{error:#}").contains(state_path.to_str().unwrap()));
+    }
+}
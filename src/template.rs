@@ -0,0 +1,139 @@
+use std::path::Path;
+
+use anyhow::{bail, Result};
+
+use crate::metadata::TrackMetadata;
+
+/// Placeholders accepted by `--template`.
+const FIELDS: [&str; 5] = ["title", "artist", "album", "lyrics", "path"];
+
+/// A `--template` string parsed into literal text and `{field}` placeholders. `{{` and `}}`
+/// stand for literal braces.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputTemplate {
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Literal(String),
+    Field(Field),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Title,
+    Artist,
+    Album,
+    Lyrics,
+    Path,
+}
+
+impl OutputTemplate {
+    /// Rejects unknown or unterminated placeholders so typos fail before the scan starts.
+    pub fn parse(raw: &str) -> Result<Self> {
+        let mut segments = Vec::new();
+        let mut literal = String::new();
+        let mut chars = raw.chars().peekable();
+
+        while let Some(ch) = chars.next() {
+            match ch {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let mut name = String::new();
+                    let mut closed = false;
+                    for ch in chars.by_ref() {
+                        if ch == '}' {
+                            closed = true;
+                            break;
+                        }
+                        name.push(ch);
+                    }
+                    if !closed {
+                        bail!("unterminated placeholder '{{{name}' in --template");
+                    }
+                    let field = match name.as_str() {
+                        "title" => Field::Title,
+                        "artist" => Field::Artist,
+                        "album" => Field::Album,
+                        "lyrics" => Field::Lyrics,
+                        "path" => Field::Path,
+                        _ => bail!(
+                            "unknown placeholder '{{{name}}}' in --template; expected one of {}",
+                            FIELDS.map(|field| format!("{{{field}}}")).join(", ")
+                        ),
+                    };
+                    if !literal.is_empty() {
+                        segments.push(Segment::Literal(std::mem::take(&mut literal)));
+                    }
+                    segments.push(Segment::Field(field));
+                }
+                '}' => bail!("unmatched '}}' in --template; write '}}}}' for a literal brace"),
+                _ => literal.push(ch),
+            }
+        }
+        if !literal.is_empty() {
+            segments.push(Segment::Literal(literal));
+        }
+
+        Ok(Self { segments })
+    }
+
+    /// Substitutes the track's fields; a missing album renders as an empty string and lyrics
+    /// lose their trailing newlines, as in the default text block.
+    pub fn render(&self, source: &Path, metadata: &TrackMetadata) -> String {
+        let mut out = String::new();
+        for segment in &self.segments {
+            match segment {
+                Segment::Literal(text) => out.push_str(text),
+                Segment::Field(Field::Title) => out.push_str(&metadata.title),
+                Segment::Field(Field::Artist) => out.push_str(&metadata.artist),
+                Segment::Field(Field::Album) => {
+                    out.push_str(metadata.album.as_deref().unwrap_or_default())
+                }
+                Segment::Field(Field::Lyrics) => {
+                    out.push_str(metadata.lyrics.trim_end_matches(['\n', '\r']))
+                }
+                Segment::Field(Field::Path) => out.push_str(&source.to_string_lossy()),
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::metadata::sample_metadata;
+
+    #[test]
+    fn renders_every_placeholder() {
+        let template =
+            OutputTemplate::parse("{artist} - {title} [{album}] ({path})\n{lyrics}\n{{end}}\n")
+                .unwrap();
+
+        assert_eq!(
+            template.render(Path::new("/music/echoes.mp3"), &sample_metadata()),
+            "Studio Band - Echoes [] (/music/echoes.mp3)\nLine one\nLine two\n{end}\n"
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_and_unbalanced_placeholders() {
+        let unknown = OutputTemplate::parse("{title} {genre}").unwrap_err();
+        assert!(format!("{unknown:#}").contains("unknown placeholder '{genre}'"));
+
+        let unterminated = OutputTemplate::parse("{title").unwrap_err();
+        assert!(format!("{unterminated:#}").contains("unterminated placeholder"));
+
+        assert!(OutputTemplate::parse("title}").is_err());
+    }
+}
@@ -0,0 +1,384 @@
+use std::fs::File;
+use std::io::{BufReader, ErrorKind, Read};
+use std::ops::Range;
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+
+use crate::cli::ArtistFrame;
+use crate::metadata::{self, Filters, TrackMetadata};
+
+const VORBIS_COMMENT_MAGIC: &[u8] = b"\x03vorbis";
+const OPUS_TAGS_MAGIC: &[u8] = b"OpusTags";
+
+/// Metadata read from the Vorbis comment header of an Ogg Vorbis or Opus stream.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VorbisTag {
+    artist: Option<String>,
+    album_artist: Option<String>,
+    album: Option<String>,
+    title: Option<String>,
+    lyrics: Option<String>,
+    unsynced_lyrics: Option<String>,
+    date: Option<String>,
+    genre: Option<String>,
+    composer: Option<String>,
+    original_artist: Option<String>,
+}
+
+impl VorbisTag {
+    pub fn read_from_path(path: &Path) -> Result<Self> {
+        let file = File::open(path)
+            .with_context(|| format!("failed to open Ogg file '{}'", path.display()))?;
+        let packet = read_comment_packet(BufReader::new(file))?;
+        Self::from_comment_packet(&packet)
+    }
+
+    fn from_comment_packet(packet: &[u8]) -> Result<Self> {
+        let body = packet
+            .strip_prefix(VORBIS_COMMENT_MAGIC)
+            .or_else(|| packet.strip_prefix(OPUS_TAGS_MAGIC))
+            .context("second Ogg packet is not a Vorbis comment header")?;
+        let mut tag = Self::default();
+
+        let mut cursor = body;
+        let vendor_len = take_u32(&mut cursor)? as usize;
+        take_bytes(&mut cursor, vendor_len)?;
+        let count = take_u32(&mut cursor)?;
+        for _ in 0..count {
+            let len = take_u32(&mut cursor)? as usize;
+            let comment = String::from_utf8_lossy(take_bytes(&mut cursor, len)?);
+            let Some((key, value)) = comment.split_once('=') else {
+                continue;
+            };
+            let slot = match key.to_ascii_uppercase().as_str() {
+                "ARTIST" => &mut tag.artist,
+                "ALBUMARTIST" | "ALBUM ARTIST" | "ALBUM_ARTIST" => &mut tag.album_artist,
+                "ALBUM" => &mut tag.album,
+                "TITLE" => &mut tag.title,
+                "LYRICS" => &mut tag.lyrics,
+                "UNSYNCEDLYRICS" => &mut tag.unsynced_lyrics,
+                "DATE" | "YEAR" => &mut tag.date,
+                "GENRE" => &mut tag.genre,
+                "COMPOSER" => &mut tag.composer,
+                "ORIGINALARTIST" | "ORIGINAL ARTIST" => &mut tag.original_artist,
+                _ => continue,
+            };
+            if slot.is_none() {
+                *slot = Some(value.to_owned());
+            }
+        }
+
+        Ok(tag)
+    }
+
+    pub fn artist(&self) -> Option<&str> {
+        self.artist.as_deref()
+    }
+
+    pub fn album_artist(&self) -> Option<&str> {
+        self.album_artist.as_deref()
+    }
+
+    pub fn composer(&self) -> Option<&str> {
+        self.composer.as_deref()
+    }
+
+    pub fn original_artist(&self) -> Option<&str> {
+        self.original_artist.as_deref()
+    }
+
+    pub fn album(&self) -> Option<&str> {
+        self.album.as_deref()
+    }
+
+    pub fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
+    /// `LYRICS`, falling back to the `UNSYNCEDLYRICS` key some taggers write instead.
+    pub fn lyrics(&self) -> Option<&str> {
+        self.lyrics.as_deref().or(self.unsynced_lyrics.as_deref())
+    }
+
+    /// Release year from the leading digits of `DATE`, which may hold a full ISO date.
+    pub fn year(&self) -> Option<i32> {
+        let date = self.date.as_deref()?.trim();
+        date.get(..4)?.parse().ok()
+    }
+
+    pub fn genre(&self) -> Option<&str> {
+        self.genre.as_deref()
+    }
+}
+
+pub fn extract_metadata(tag: &VorbisTag, filters: &Filters) -> Option<TrackMetadata> {
+    let artist = match_artist(tag, filters)?;
+    if !matches_filters(tag, filters) {
+        return None;
+    }
+    let lyrics = tag
+        .lyrics()
+        .map(str::trim)
+        .filter(|text| !text.is_empty())
+        .map(metadata::normalize_line_endings)
+        .and_then(|lyrics| filters.finish_lyrics(lyrics))?;
+
+    let mut track = track_with_lyrics(tag, artist, lyrics);
+    track.source = filters.lyrics_source.then(|| "vorbis".to_string());
+    Some(track)
+}
+
+pub(crate) fn track_with_lyrics(tag: &VorbisTag, artist: String, lyrics: String) -> TrackMetadata {
+    TrackMetadata {
+        artist,
+        title: resolve_title(tag),
+        lyrics,
+        album: metadata::non_empty(tag.album()),
+        album_artist: metadata::non_empty(tag.album_artist()),
+        audio: None,
+        source: None,
+        hash: None,
+    }
+}
+
+pub(crate) fn match_artist(tag: &VorbisTag, filters: &Filters) -> Option<String> {
+    metadata::pick_matching_artist(filters, |frame| match frame {
+        ArtistFrame::Artist => tag.artist(),
+        ArtistFrame::AlbumArtist => tag.album_artist(),
+        ArtistFrame::Composer => tag.composer(),
+        ArtistFrame::OriginalArtist => tag.original_artist(),
+    })
+    .map(str::to_owned)
+}
+
+pub(crate) fn matches_filters(tag: &VorbisTag, filters: &Filters) -> bool {
+    filters.matches_track(tag.title(), tag.year(), tag.genre())
+}
+
+pub(crate) fn resolve_title(tag: &VorbisTag) -> String {
+    tag.title()
+        .map(str::trim)
+        .filter(|title| !title.is_empty())
+        .map(|title| title.to_owned())
+        .unwrap_or_else(|| "Unknown Title".to_string())
+}
+
+/// Reassembles the second packet of the first logical stream, which holds the comment header
+/// in both Vorbis and Opus. Page checksums are not verified.
+fn read_comment_packet(mut reader: impl Read) -> Result<Vec<u8>> {
+    let mut serial = None;
+    let mut packet = Vec::new();
+    let mut packets_done = 0;
+
+    loop {
+        let mut header = [0_u8; 27];
+        match reader.read_exact(&mut header) {
+            Ok(()) => {}
+            Err(error) if error.kind() == ErrorKind::UnexpectedEof => {
+                bail!("Ogg stream ended before the comment header")
+            }
+            Err(error) => return Err(error).context("failed to read Ogg page header"),
+        }
+        if &header[..4] != b"OggS" {
+            bail!("missing Ogg page signature; not an Ogg container");
+        }
+        let page_serial = u32::from_le_bytes(header[14..18].try_into().unwrap());
+        let mut segments = vec![0_u8; usize::from(header[26])];
+        reader
+            .read_exact(&mut segments)
+            .context("failed to read Ogg segment table")?;
+        let mut data = vec![0_u8; segments.iter().map(|&len| usize::from(len)).sum()];
+        reader
+            .read_exact(&mut data)
+            .context("failed to read Ogg page body")?;
+
+        if *serial.get_or_insert(page_serial) != page_serial {
+            continue;
+        }
+
+        let mut offset = 0;
+        for len in segments {
+            let len = usize::from(len);
+            if packets_done == 1 {
+                packet.extend_from_slice(&data[offset..offset + len]);
+            }
+            offset += len;
+            // A lacing value below 255 ends the packet; 255 continues it.
+            if len < 255 {
+                if packets_done == 1 {
+                    return Ok(packet);
+                }
+                packets_done += 1;
+            }
+        }
+    }
+}
+
+/// Byte ranges of the page bodies of the first logical stream that carry audio, i.e. have a
+/// non-zero granule position. Header pages, the comments among them, always have position 0.
+pub(crate) fn audio_page_ranges(file: &mut File) -> Result<Vec<Range<u64>>> {
+    let mut reader = BufReader::new(file);
+    let mut serial = None;
+    let mut ranges = Vec::new();
+    let mut offset = 0_u64;
+
+    loop {
+        let mut header = [0_u8; 27];
+        match reader.read_exact(&mut header) {
+            Ok(()) => {}
+            Err(error) if error.kind() == ErrorKind::UnexpectedEof => return Ok(ranges),
+            Err(error) => return Err(error).context("failed to read Ogg page header"),
+        }
+        if &header[..4] != b"OggS" {
+            bail!("missing Ogg page signature at offset {offset}");
+        }
+        let granule = u64::from_le_bytes(header[6..14].try_into().unwrap());
+        let page_serial = u32::from_le_bytes(header[14..18].try_into().unwrap());
+        let mut segments = vec![0_u8; usize::from(header[26])];
+        reader
+            .read_exact(&mut segments)
+            .context("failed to read Ogg segment table")?;
+        let body_len: u64 = segments.iter().map(|&len| u64::from(len)).sum();
+        let body = offset + (header.len() + segments.len()) as u64;
+
+        if *serial.get_or_insert(page_serial) == page_serial && granule != 0 {
+            ranges.push(body..body + body_len);
+        }
+        reader
+            .seek_relative(body_len as i64)
+            .context("failed to skip Ogg page body")?;
+        offset = body + body_len;
+    }
+}
+
+fn take_u32(cursor: &mut &[u8]) -> Result<u32> {
+    let bytes = take_bytes(cursor, 4)?;
+    Ok(u32::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+fn take_bytes<'a>(cursor: &mut &'a [u8], len: usize) -> Result<&'a [u8]> {
+    if cursor.len() < len {
+        bail!("truncated Vorbis comment header");
+    }
+    let (bytes, rest) = cursor.split_at(len);
+    *cursor = rest;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs;
+
+    use tempfile::TempDir;
+
+    fn comment_packet(magic: &[u8], comments: &[&str]) -> Vec<u8> {
+        let mut packet = magic.to_vec();
+        packet.extend_from_slice(&6_u32.to_le_bytes());
+        packet.extend_from_slice(b"vendor");
+        packet.extend_from_slice(&(comments.len() as u32).to_le_bytes());
+        for comment in comments {
+            packet.extend_from_slice(&(comment.len() as u32).to_le_bytes());
+            packet.extend_from_slice(comment.as_bytes());
+        }
+        packet
+    }
+
+    /// Lays `packets` out across Ogg pages holding at most `max_segments` lacing values each.
+    fn ogg_bytes(packets: &[Vec<u8>], max_segments: usize) -> Vec<u8> {
+        let mut lacing = Vec::new();
+        let mut data = Vec::new();
+        for packet in packets {
+            let mut remaining = packet.len();
+            while remaining >= 255 {
+                lacing.push(255_u8);
+                remaining -= 255;
+            }
+            lacing.push(remaining as u8);
+            data.extend_from_slice(packet);
+        }
+
+        let mut bytes = Vec::new();
+        let mut offset = 0;
+        for (sequence, chunk) in lacing.chunks(max_segments).enumerate() {
+            bytes.extend_from_slice(b"OggS");
+            bytes.extend_from_slice(&[0, 0]);
+            bytes.extend_from_slice(&[0; 8]);
+            bytes.extend_from_slice(&7_u32.to_le_bytes());
+            bytes.extend_from_slice(&(sequence as u32).to_le_bytes());
+            bytes.extend_from_slice(&[0; 4]);
+            bytes.push(chunk.len() as u8);
+            bytes.extend_from_slice(chunk);
+            let len: usize = chunk.iter().map(|&len| usize::from(len)).sum();
+            bytes.extend_from_slice(&data[offset..offset + len]);
+            offset += len;
+        }
+        bytes
+    }
+
+    #[test]
+    fn reads_vorbis_comments() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("song.ogg");
+        let comments = comment_packet(
+            VORBIS_COMMENT_MAGIC,
+            &[
+                "artist=Studio Crew",
+                "TITLE=Anthem",
+                "ALBUM=Debut",
+                "DATE=2021-06-01",
+                "GENRE=Electronic",
+                "LYRICS=Line one\r\nLine two\n",
+            ],
+        );
+        fs::write(
+            &path,
+            ogg_bytes(&[b"\x01vorbis-ident".to_vec(), comments], 255),
+        )
+        .unwrap();
+
+        let tag = VorbisTag::read_from_path(&path).expect("vorbis tag");
+        let metadata =
+            extract_metadata(&tag, &Filters::default()).expect("metadata should be extracted");
+
+        assert_eq!(metadata.artist, "Studio Crew");
+        assert_eq!(metadata.title, "Anthem");
+        assert_eq!(metadata.lyrics, "Line one\nLine two");
+        assert_eq!(metadata.album.as_deref(), Some("Debut"));
+        assert_eq!(tag.year(), Some(2021));
+        assert_eq!(tag.genre(), Some("Electronic"));
+    }
+
+    #[test]
+    fn reads_opus_tags_split_across_pages() {
+        let long_lyrics = format!("UNSYNCEDLYRICS={}", "la ".repeat(400));
+        let comments = comment_packet(OPUS_TAGS_MAGIC, &["ARTIST=Audio Duo", &long_lyrics]);
+        let bytes = ogg_bytes(&[b"OpusHead".to_vec(), comments], 1);
+
+        let packet = read_comment_packet(bytes.as_slice()).expect("comment packet");
+        let tag = VorbisTag::from_comment_packet(&packet).expect("opus tags");
+
+        assert_eq!(tag.artist(), Some("Audio Duo"));
+        assert_eq!(tag.lyrics().map(str::trim), Some("la ".repeat(400).trim()));
+    }
+
+    #[test]
+    fn missing_lyrics_comment_yields_none() {
+        let comments = comment_packet(VORBIS_COMMENT_MAGIC, &["ARTIST=Studio Crew"]);
+        let tag = VorbisTag::from_comment_packet(&comments).expect("vorbis tag");
+
+        assert!(match_artist(&tag, &Filters::default()).is_some());
+        assert!(extract_metadata(&tag, &Filters::default()).is_none());
+    }
+
+    #[test]
+    fn rejects_non_ogg_files() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("corrupt.ogg");
+        fs::write(&path, b"definitely not an ogg container, no sir").unwrap();
+
+        assert!(VorbisTag::read_from_path(&path).is_err());
+    }
+}
@@ -1,48 +1,529 @@
+use std::borrow::Cow;
+use std::collections::{hash_map::Entry, HashMap, HashSet, VecDeque};
 use std::fs::{File, OpenOptions};
-use std::io::{BufWriter, Write};
-use std::path::Path;
+use std::hash::Hash;
+use std::io::{self, BufWriter, StdoutLock, Write};
+use std::path::{Path, PathBuf};
 
 use anyhow::{Context, Result};
+use serde::Serialize;
 
-use crate::{cli::OutputFormat, metadata::TrackMetadata};
+use crate::{
+    cli::{OutputEncoding, OutputFormat},
+    metadata::{self, TrackMetadata},
+    report::relative_to_roots,
+    template::OutputTemplate,
+};
+
+/// `--output` value that streams to stdout instead of a file.
+pub const STDOUT_SENTINEL: &str = "-";
 
 pub struct OutputWriter {
-    writer: Option<BufWriter<File>>,
+    writer: Option<BufWriter<Sink>>,
     format: OutputFormat,
+    /// `--template` replacing the default text block.
+    template: Option<OutputTemplate>,
+    encoding: OutputEncoding,
+    /// Nothing has been written to the destination yet, so a byte order mark may go first.
+    fresh: bool,
+    header_pending: bool,
+    /// Elements written so far while a `json-array` is open; `None` once closed or unused.
+    array_elements: Option<usize>,
+    split: Option<SplitTargets>,
+    /// `--split-by-artist`: entries are routed to one writer per artist instead of `writer`.
+    by_artist: Option<ArtistFiles>,
+    /// `--mirror-output`: entries are routed to one writer per source directory instead.
+    mirror: Option<MirrorFiles>,
+    /// `--relative-paths`: scan roots stripped from the `path` of JSON and YAML records.
+    relative_to: Option<Vec<PathBuf>>,
+    /// `--json-pretty`: indent each `json-array` element over several lines.
+    json_pretty: bool,
+    /// `--atomic`: the temp file being written, renamed over the target by `flush`.
+    replace_on_flush: Option<PendingRename>,
+}
+
+/// Temp file awaiting its rename; dropping it unrenamed (e.g. on an error) deletes the file.
+struct PendingRename {
+    temp: PathBuf,
+    target: PathBuf,
+}
+
+impl Drop for PendingRename {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.temp);
+    }
+}
+
+/// JSON/YAML shape of one output record: the track fields plus the file they were read from.
+#[derive(Serialize)]
+struct Record<'a> {
+    #[serde(flatten)]
+    track: &'a TrackMetadata,
+    path: String,
+}
+
+impl<'a> Record<'a> {
+    fn new(source: &Path, track: &'a TrackMetadata) -> Self {
+        Self {
+            track,
+            path: source.to_string_lossy().into_owned(),
+        }
+    }
+}
+
+/// Destination of the combined output.
+enum Sink {
+    File(File),
+    Stdout(StdoutLock<'static>),
+    /// `--dry-run-show`: stderr, with `[dry-run] ` at the start of every line.
+    Preview {
+        at_line_start: bool,
+    },
+}
+
+impl Write for Sink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::File(file) => file.write(buf),
+            Self::Stdout(stdout) => stdout.write(buf),
+            Self::Preview { at_line_start } => {
+                write_prefixed(&mut io::stderr().lock(), buf, at_line_start)?;
+                Ok(buf.len())
+            }
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::File(file) => file.flush(),
+            Self::Stdout(stdout) => stdout.flush(),
+            Self::Preview { .. } => io::stderr().flush(),
+        }
+    }
+}
+
+const PREVIEW_PREFIX: &[u8] = b"[dry-run] ";
+
+/// Copies `buf` to `out`, inserting the preview prefix wherever a line begins.
+fn write_prefixed(out: &mut impl Write, buf: &[u8], at_line_start: &mut bool) -> io::Result<()> {
+    for line in buf.split_inclusive(|&byte| byte == b'\n') {
+        if *at_line_start {
+            out.write_all(PREVIEW_PREFIX)?;
+        }
+        out.write_all(line)?;
+        *at_line_start = line.ends_with(b"\n");
+    }
+    Ok(())
+}
+
+/// Per-artist output files in one directory, each opened when its artist's first track arrives.
+struct ArtistFiles {
+    dir: PathBuf,
+    overwrite: bool,
+    /// Keyed by the lowercased file stem, so artists differing only in case share a file even on
+    /// case-insensitive filesystems.
+    writers: WriterPool<String>,
+}
+
+/// One output file per source directory, in a tree under `dir` mirroring the scan roots. Each
+/// directory is created when its first track arrives.
+struct MirrorFiles {
+    dir: PathBuf,
+    roots: Vec<PathBuf>,
+    overwrite: bool,
+    /// Keyed by the source directory relative to its scan root.
+    writers: WriterPool<PathBuf>,
+}
+
+impl MirrorFiles {
+    /// The directory of `source` relative to the first root containing it; sources outside
+    /// every root (e.g. from `--from-file`) go to the top of the mirror.
+    fn relative_dir(&self, source: &Path) -> PathBuf {
+        let parent = source.parent().unwrap_or(Path::new(""));
+        self.roots
+            .iter()
+            .find_map(|root| parent.strip_prefix(root).ok())
+            .map(Path::to_path_buf)
+            .unwrap_or_default()
+    }
+}
+
+/// Most per-artist or mirrored output files kept open at once.
+const MAX_OPEN_FILES: usize = 32;
+
+/// Per-artist or mirrored writers, of which at most `capacity` hold their file open. When another
+/// is needed the least recently used one is closed, and reopened for appending if more of its
+/// tracks arrive, so a library with thousands of album directories stays under the file
+/// descriptor limit. A closed writer keeps its `json-array` and header state.
+struct WriterPool<K> {
+    writers: HashMap<K, PooledWriter>,
+    /// Keys of the writers whose file is open, least recently used first.
+    open: VecDeque<K>,
+    capacity: usize,
+}
+
+struct PooledWriter {
+    path: PathBuf,
+    writer: OutputWriter,
+}
+
+impl<K: Clone + Eq + Hash> WriterPool<K> {
+    fn new(capacity: usize) -> Self {
+        Self {
+            writers: HashMap::new(),
+            open: VecDeque::new(),
+            capacity,
+        }
+    }
+
+    /// The writer for `key` with its file open; the first time `key` is seen, `create` opens the
+    /// writer and returns it along with its path.
+    fn get_or_open(
+        &mut self,
+        key: K,
+        create: impl FnOnce() -> Result<(PathBuf, OutputWriter)>,
+    ) -> Result<&mut OutputWriter> {
+        if let Some(position) = self.open.iter().position(|open| *open == key) {
+            self.open.remove(position);
+        } else {
+            if self.open.len() >= self.capacity {
+                if let Some(oldest) = self.open.pop_front() {
+                    if let Some(pooled) = self.writers.get_mut(&oldest) {
+                        pooled.writer.close_file()?;
+                    }
+                }
+            }
+            match self.writers.entry(key.clone()) {
+                Entry::Occupied(mut entry) => {
+                    let pooled = entry.get_mut();
+                    pooled.writer.reopen_file(&pooled.path)?;
+                }
+                Entry::Vacant(entry) => {
+                    let (path, writer) = create()?;
+                    entry.insert(PooledWriter { path, writer });
+                }
+            }
+        }
+        self.open.push_back(key.clone());
+        let pooled = self.writers.get_mut(&key).expect("writer was just opened");
+        Ok(&mut pooled.writer)
+    }
+
+    /// Finishes every file, reopening closed `json-array` files for their closing bracket.
+    fn flush(&mut self) -> Result<()> {
+        for pooled in self.writers.values_mut() {
+            if pooled.writer.writer.is_none() && pooled.writer.array_elements.is_some() {
+                pooled.writer.reopen_file(&pooled.path)?;
+            }
+            pooled.writer.flush()?;
+            pooled.writer.close_file()?;
+        }
+        self.open.clear();
+        Ok(())
+    }
+}
+
+/// Per-track files written next to each source, remembering which names this run claimed.
+#[derive(Default)]
+struct SplitTargets {
+    claimed: HashSet<PathBuf>,
 }
 
 impl OutputWriter {
-    pub fn create(path: &Path, format: OutputFormat, dry_run: bool) -> Result<Self> {
+    /// Opens `path` for appending, or truncates it once up front when `overwrite` is set. A path
+    /// of `-` writes to stdout instead.
+    pub fn create(
+        path: &Path,
+        format: OutputFormat,
+        dry_run: bool,
+        overwrite: bool,
+    ) -> Result<Self> {
         if dry_run {
             return Ok(Self {
                 writer: None,
                 format,
+                template: None,
+                encoding: OutputEncoding::Utf8,
+                fresh: false,
+                header_pending: false,
+                array_elements: None,
+                split: None,
+                by_artist: None,
+                mirror: None,
+                relative_to: None,
+                json_pretty: false,
+                replace_on_flush: None,
             });
         }
 
-        let file = OpenOptions::new()
-            .create(true)
-            .append(true)
+        if path == Path::new(STDOUT_SENTINEL) {
+            return Ok(Self {
+                writer: Some(BufWriter::new(Sink::Stdout(io::stdout().lock()))),
+                format,
+                template: None,
+                encoding: OutputEncoding::Utf8,
+                fresh: true,
+                header_pending: has_header_row(format),
+                array_elements: (format == OutputFormat::JsonArray).then_some(0),
+                split: None,
+                by_artist: None,
+                mirror: None,
+                relative_to: None,
+                json_pretty: false,
+                replace_on_flush: None,
+            });
+        }
+
+        let mut options = OpenOptions::new();
+        options.create(true);
+        if overwrite {
+            options.write(true).truncate(true);
+        } else {
+            options.append(true);
+        }
+        let file = options
             .open(path)
             .with_context(|| format!("failed to open output file '{}'", path.display()))?;
+        // Only a freshly created (empty) file gets a header row; appends continue the table.
+        let is_empty = file
+            .metadata()
+            .with_context(|| format!("failed to inspect output file '{}'", path.display()))?
+            .len()
+            == 0;
 
         Ok(Self {
-            writer: Some(BufWriter::new(file)),
+            writer: Some(BufWriter::new(Sink::File(file))),
             format,
+            template: None,
+            encoding: OutputEncoding::Utf8,
+            fresh: is_empty,
+            header_pending: is_empty && has_header_row(format),
+            array_elements: (format == OutputFormat::JsonArray).then_some(0),
+            split: None,
+            by_artist: None,
+            mirror: None,
+            relative_to: None,
+            json_pretty: false,
+            replace_on_flush: None,
         })
     }
 
-    pub fn write_entry(&mut self, metadata: &TrackMetadata) -> Result<()> {
+    /// Writes to a temp file beside `path` and only replaces `path` with it once `flush` succeeds,
+    /// so an interrupted run leaves any previous output untouched.
+    pub fn create_atomic(path: &Path, format: OutputFormat) -> Result<Self> {
+        let name = path
+            .file_name()
+            .with_context(|| format!("output path '{}' has no file name", path.display()))?;
+        let temp = path.with_file_name(format!(
+            ".{}.{}.tmp",
+            name.to_string_lossy(),
+            std::process::id()
+        ));
+        let file = File::create(&temp)
+            .with_context(|| format!("failed to create temp output file '{}'", temp.display()))?;
+
+        Ok(Self {
+            writer: Some(BufWriter::new(Sink::File(file))),
+            format,
+            template: None,
+            encoding: OutputEncoding::Utf8,
+            fresh: true,
+            header_pending: has_header_row(format),
+            array_elements: (format == OutputFormat::JsonArray).then_some(0),
+            split: None,
+            by_artist: None,
+            mirror: None,
+            relative_to: None,
+            json_pretty: false,
+            replace_on_flush: Some(PendingRename {
+                temp,
+                target: path.to_path_buf(),
+            }),
+        })
+    }
+
+    /// Formats entries as usual but prints them to stderr, prefixed with `[dry-run]`, instead of
+    /// touching any output file.
+    pub fn preview(format: OutputFormat) -> Self {
+        // No buffering, so each entry appears alongside the log lines for the same file.
+        let sink = Sink::Preview {
+            at_line_start: true,
+        };
+        Self {
+            writer: Some(BufWriter::with_capacity(0, sink)),
+            format,
+            template: None,
+            encoding: OutputEncoding::Utf8,
+            fresh: false,
+            header_pending: has_header_row(format),
+            array_elements: (format == OutputFormat::JsonArray).then_some(0),
+            split: None,
+            by_artist: None,
+            mirror: None,
+            relative_to: None,
+            json_pretty: false,
+            replace_on_flush: None,
+        }
+    }
+
+    /// Renders text entries with `template` instead of the default block.
+    pub fn with_template(mut self, template: Option<OutputTemplate>) -> Self {
+        self.template = template;
+        self
+    }
+
+    /// Writes the `path` of JSON and YAML records relative to the scan root containing it.
+    pub fn with_relative_paths(mut self, roots: Option<Vec<PathBuf>>) -> Self {
+        self.relative_to = roots;
+        self
+    }
+
+    /// Pretty-prints `json-array` elements instead of writing each on one line.
+    pub fn with_json_pretty(mut self, pretty: bool) -> Self {
+        self.json_pretty = pretty;
+        self
+    }
+
+    /// Encodes text entries as `encoding`; other formats are always written as UTF-8.
+    pub fn with_encoding(mut self, encoding: OutputEncoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    /// Writes each track's lyrics to `<stem>.lrc` (timestamped lyrics) or `<stem>.txt` beside
+    /// its source file instead of a combined output file.
+    pub fn split(dry_run: bool) -> Self {
+        Self {
+            writer: None,
+            format: OutputFormat::Text,
+            template: None,
+            encoding: OutputEncoding::Utf8,
+            fresh: false,
+            header_pending: false,
+            array_elements: None,
+            split: (!dry_run).then(SplitTargets::default),
+            by_artist: None,
+            mirror: None,
+            relative_to: None,
+            json_pretty: false,
+            replace_on_flush: None,
+        }
+    }
+
+    /// Writes each artist's tracks to `<dir>/<artist>.<ext>` in `format`, appending to existing
+    /// files unless `overwrite` is set, in which case each file is truncated when first opened.
+    pub fn by_artist(
+        dir: &Path,
+        format: OutputFormat,
+        dry_run: bool,
+        overwrite: bool,
+    ) -> Result<Self> {
+        let mut writer = Self::create(dir, format, true, overwrite)?;
+        if !dry_run {
+            std::fs::create_dir_all(dir).with_context(|| {
+                format!(
+                    "failed to create artist output directory '{}'",
+                    dir.display()
+                )
+            })?;
+            writer.by_artist = Some(ArtistFiles {
+                dir: dir.to_path_buf(),
+                overwrite,
+                writers: WriterPool::new(MAX_OPEN_FILES),
+            });
+        }
+        Ok(writer)
+    }
+
+    /// Writes the tracks of each source directory to `<dir>/<relative dir>/lyrics.<ext>` in
+    /// `format`, where the relative directory is taken against the scan root in `roots` holding
+    /// the source. Appends unless `overwrite` is set, as with `by_artist`.
+    pub fn mirror(
+        dir: &Path,
+        roots: &[PathBuf],
+        format: OutputFormat,
+        dry_run: bool,
+        overwrite: bool,
+    ) -> Result<Self> {
+        let mut writer = Self::create(dir, format, true, overwrite)?;
+        if !dry_run {
+            writer.mirror = Some(MirrorFiles {
+                dir: dir.to_path_buf(),
+                roots: roots.to_vec(),
+                overwrite,
+                writers: WriterPool::new(MAX_OPEN_FILES),
+            });
+        }
+        Ok(writer)
+    }
+
+    pub fn write_entry(&mut self, source: &Path, metadata: &TrackMetadata) -> Result<()> {
+        if let Some(split) = self.split.as_mut() {
+            return split.write(source, metadata);
+        }
+        if let Some(by_artist) = self.by_artist.as_mut() {
+            let stem = artist_file_stem(&metadata.artist);
+            let writer = by_artist.writers.get_or_open(stem.to_lowercase(), || {
+                let path = by_artist
+                    .dir
+                    .join(format!("{stem}.{}", file_extension(self.format)));
+                let writer = Self::create(&path, self.format, false, by_artist.overwrite)?
+                    .with_template(self.template.clone())
+                    .with_encoding(self.encoding)
+                    .with_relative_paths(self.relative_to.clone())
+                    .with_json_pretty(self.json_pretty);
+                Ok((path, writer))
+            })?;
+            return writer.write_entry(source, metadata);
+        }
+        if let Some(mirror) = self.mirror.as_mut() {
+            let relative = mirror.relative_dir(source);
+            let dir = mirror.dir.join(&relative);
+            let writer = mirror.writers.get_or_open(relative, || {
+                std::fs::create_dir_all(&dir).with_context(|| {
+                    format!(
+                        "failed to create mirror output directory '{}'",
+                        dir.display()
+                    )
+                })?;
+                let path = dir.join(format!("lyrics.{}", file_extension(self.format)));
+                let writer = Self::create(&path, self.format, false, mirror.overwrite)?
+                    .with_template(self.template.clone())
+                    .with_encoding(self.encoding)
+                    .with_relative_paths(self.relative_to.clone())
+                    .with_json_pretty(self.json_pretty);
+                Ok((path, writer))
+            })?;
+            return writer.write_entry(source, metadata);
+        }
+
+        let record_source = match &self.relative_to {
+            Some(roots) => Cow::Owned(relative_to_roots(source, roots)),
+            None => Cow::Borrowed(source),
+        };
         if let Some(writer) = self.writer.as_mut() {
             match self.format {
                 OutputFormat::Text => {
-                    let block = format_block(metadata);
-                    writer
-                        .write_all(block.as_bytes())
-                        .context("failed to append lyrics to output file")?;
+                    let block = match &self.template {
+                        Some(template) => template.render(source, metadata),
+                        None => format_block(metadata),
+                    };
+                    if self.encoding == OutputEncoding::Utf16le {
+                        let bom: &[u8] = if self.fresh { &[0xFF, 0xFE] } else { &[] };
+                        let encoded: Vec<u8> =
+                            block.encode_utf16().flat_map(u16::to_le_bytes).collect();
+                        writer
+                            .write_all(bom)
+                            .and_then(|_| writer.write_all(&encoded))
+                            .context("failed to append UTF-16 lyrics to output file")?;
+                    } else {
+                        writer
+                            .write_all(block.as_bytes())
+                            .context("failed to append lyrics to output file")?;
+                    }
                 }
                 OutputFormat::Json => {
-                    let json = serde_json::to_string(metadata)
+                    let json = serde_json::to_string(&Record::new(&record_source, metadata))
                         .context("failed to serialize track metadata as JSON")?;
                     writer
                         .write_all(json.as_bytes())
@@ -51,47 +532,377 @@ impl OutputWriter {
                         .write_all(b"\n")
                         .context("failed to append newline to JSON lyrics output")?;
                 }
+                OutputFormat::JsonArray => {
+                    let record = Record::new(&record_source, metadata);
+                    let json = if self.json_pretty {
+                        serde_json::to_string_pretty(&record)
+                    } else {
+                        serde_json::to_string(&record)
+                    }
+                    .context("failed to serialize track metadata as JSON")?;
+                    let written = self.array_elements.get_or_insert(0);
+                    let separator: &[u8] = if *written == 0 { b"[\n" } else { b",\n" };
+                    *written += 1;
+                    writer
+                        .write_all(separator)
+                        .and_then(|_| writer.write_all(json.as_bytes()))
+                        .context("failed to append JSON array element to output file")?;
+                }
+                OutputFormat::Yaml => {
+                    let yaml = serde_yaml::to_string(&Record::new(&record_source, metadata))
+                        .context("failed to serialize track metadata as YAML")?;
+                    writer
+                        .write_all(b"---\n")
+                        .and_then(|_| writer.write_all(yaml.as_bytes()))
+                        .context("failed to append YAML document to output file")?;
+                }
+                OutputFormat::Csv => {
+                    if self.header_pending {
+                        writer
+                            .write_all(&csv_row(&["artist", "title", "lyrics"])?)
+                            .context("failed to write CSV header to output file")?;
+                        self.header_pending = false;
+                    }
+                    let row = csv_row(&[&metadata.artist, &metadata.title, &metadata.lyrics])?;
+                    writer
+                        .write_all(&row)
+                        .context("failed to append CSV row to output file")?;
+                }
+                OutputFormat::Tsv => {
+                    if self.header_pending {
+                        writer
+                            .write_all(b"artist\ttitle\tlyrics\n")
+                            .context("failed to write TSV header to output file")?;
+                        self.header_pending = false;
+                    }
+                    let row = tsv_row(&[&metadata.artist, &metadata.title, &metadata.lyrics]);
+                    writer
+                        .write_all(row.as_bytes())
+                        .context("failed to append TSV row to output file")?;
+                }
             }
+            self.fresh = false;
         }
         Ok(())
     }
 
+    /// Flushes buffered output; for `json-array` this also writes the closing bracket, and with
+    /// `create_atomic` it moves the finished file into place, so it should be called once when
+    /// the scan is done.
     pub fn flush(&mut self) -> Result<()> {
+        if let Some(by_artist) = self.by_artist.as_mut() {
+            by_artist.writers.flush()?;
+        }
+        if let Some(mirror) = self.mirror.as_mut() {
+            mirror.writers.flush()?;
+        }
         if let Some(writer) = self.writer.as_mut() {
+            if let Some(written) = self.array_elements.take() {
+                let closing: &[u8] = if written == 0 { b"[]\n" } else { b"\n]\n" };
+                writer
+                    .write_all(closing)
+                    .context("failed to close JSON array in output file")?;
+            }
             writer
                 .flush()
                 .context("failed to flush buffered lyrics to output file")?;
+            if let Some(pending) = self.replace_on_flush.take() {
+                if let Sink::File(file) = writer.get_ref() {
+                    file.sync_all().with_context(|| {
+                        format!(
+                            "failed to sync temp output file '{}'",
+                            pending.temp.display()
+                        )
+                    })?;
+                }
+                std::fs::rename(&pending.temp, &pending.target).with_context(|| {
+                    format!(
+                        "failed to move temp output file into place at '{}'",
+                        pending.target.display()
+                    )
+                })?;
+            }
         }
         Ok(())
     }
+
+    /// Flushes and closes the output file, keeping what `reopen_file` needs to carry on writing
+    /// it; unlike `flush`, a `json-array` is left open.
+    fn close_file(&mut self) -> Result<()> {
+        if let Some(mut writer) = self.writer.take() {
+            writer
+                .flush()
+                .context("failed to flush buffered lyrics to output file")?;
+        }
+        Ok(())
+    }
+
+    /// Reopens a file closed by `close_file` for appending.
+    fn reopen_file(&mut self, path: &Path) -> Result<()> {
+        let file = OpenOptions::new()
+            .append(true)
+            .open(path)
+            .with_context(|| format!("failed to reopen output file '{}'", path.display()))?;
+        self.writer = Some(BufWriter::new(Sink::File(file)));
+        Ok(())
+    }
+}
+
+/// File extension for a per-artist or mirrored output file in `format`.
+fn file_extension(format: OutputFormat) -> &'static str {
+    match format {
+        OutputFormat::Text => "txt",
+        OutputFormat::Json => "jsonl",
+        OutputFormat::JsonArray => "json",
+        OutputFormat::Yaml => "yaml",
+        OutputFormat::Csv => "csv",
+        OutputFormat::Tsv => "tsv",
+    }
 }
 
+/// Longest file stem, in bytes, kept from an artist name; well under common 255-byte limits.
+const MAX_STEM_BYTES: usize = 200;
+
+/// Makes `artist` safe as a file stem: path separators, characters Windows rejects and control
+/// characters become `_`, and leading or trailing dots and spaces are dropped. A name with
+/// nothing left becomes `Unknown`.
+fn artist_file_stem(artist: &str) -> String {
+    let mut stem = String::new();
+    for ch in artist.trim_matches(['.', ' ']).chars() {
+        let ch = if ch.is_control()
+            || matches!(ch, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|')
+        {
+            '_'
+        } else {
+            ch
+        };
+        if stem.len() + ch.len_utf8() > MAX_STEM_BYTES {
+            break;
+        }
+        stem.push(ch);
+    }
+    let stem = stem.trim_end_matches(['.', ' ']);
+    if stem.is_empty() {
+        "Unknown".to_string()
+    } else {
+        stem.to_string()
+    }
+}
+
+impl SplitTargets {
+    fn write(&mut self, source: &Path, metadata: &TrackMetadata) -> Result<()> {
+        let extension = if metadata::has_lrc_timestamps(&metadata.lyrics) {
+            "lrc"
+        } else {
+            "txt"
+        };
+        let target = self.claim(source, extension);
+        let contents = format!("{}\n", metadata.lyrics.trim_end_matches(['\n', '\r']));
+
+        std::fs::write(&target, contents)
+            .with_context(|| format!("failed to write lyrics file '{}'", target.display()))
+    }
+
+    /// Picks `<stem>.<ext>`, or `<stem>-2.<ext>`, `<stem>-3.<ext>`, ... when an earlier track
+    /// in this run already produced that name.
+    fn claim(&mut self, source: &Path, extension: &str) -> PathBuf {
+        let stem = source
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "lyrics".to_string());
+        let mut candidate = source.with_file_name(format!("{stem}.{extension}"));
+        let mut counter = 2;
+        while self.claimed.contains(&candidate) {
+            candidate = source.with_file_name(format!("{stem}-{counter}.{extension}"));
+            counter += 1;
+        }
+        self.claimed.insert(candidate.clone());
+        candidate
+    }
+}
+
+/// (artist, title) pairs already in an earlier run's `format` output at `path`, for
+/// `--skip-existing`. Text output is read from the `=== title ===` / `Artist:` block headers
+/// and JSON lines are parsed whole. A missing file holds no tracks.
+pub fn read_written_tracks(path: &Path, format: OutputFormat) -> Result<HashSet<(String, String)>> {
+    let contents = match read_output_text(path) {
+        Ok(contents) => contents,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(HashSet::new()),
+        Err(error) => {
+            return Err(error)
+                .with_context(|| format!("failed to read existing output '{}'", path.display()))
+        }
+    };
+    let parse_error = || format!("failed to parse existing output '{}'", path.display());
+
+    let tracks = match format {
+        OutputFormat::Text => {
+            let mut tracks = HashSet::new();
+            let mut title = None;
+            for line in contents.lines() {
+                if let Some(heading) = line
+                    .strip_prefix("=== ")
+                    .and_then(|rest| rest.strip_suffix(" ==="))
+                {
+                    title = Some(heading.to_string());
+                } else if let (Some(artist), Some(title)) =
+                    (line.strip_prefix("Artist: "), title.take())
+                {
+                    tracks.insert((artist.to_string(), title));
+                }
+            }
+            tracks
+        }
+        OutputFormat::Json => contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| serde_json::from_str::<TrackMetadata>(line).with_context(parse_error))
+            .map(|track| track.map(|track| (track.artist, track.title)))
+            .collect::<Result<_>>()?,
+        // Appending a second `json-array` run would not leave a parseable file to read back.
+        OutputFormat::JsonArray | OutputFormat::Yaml | OutputFormat::Csv | OutputFormat::Tsv => {
+            anyhow::bail!("--skip-existing only supports text and json output")
+        }
+    };
+    Ok(tracks)
+}
+
+/// (artist, title) pairs listed in an `--exclude-from` reference file, read as JSON lines when
+/// its extension is `json`, `jsonl` or `ndjson` and as text output otherwise. Unlike
+/// `read_written_tracks`, a missing file is an error.
+/// Reads earlier output as text, decoding UTF-16LE when the file starts with the byte order mark
+/// `--output-encoding utf16le` writes.
+fn read_output_text(path: &Path) -> io::Result<String> {
+    let bytes = std::fs::read(path)?;
+    let invalid = |error| io::Error::new(io::ErrorKind::InvalidData, error);
+    match bytes.strip_prefix(&[0xFF, 0xFE]) {
+        Some(utf16) => {
+            let units: Vec<u16> = utf16
+                .chunks_exact(2)
+                .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+                .collect();
+            String::from_utf16(&units).map_err(|error| invalid(error.to_string()))
+        }
+        None => String::from_utf8(bytes).map_err(|error| invalid(error.to_string())),
+    }
+}
+
+pub fn read_reference_tracks(path: &Path) -> Result<HashSet<(String, String)>> {
+    anyhow::ensure!(
+        path.is_file(),
+        "--exclude-from reference '{}' does not exist",
+        path.display()
+    );
+    let extension = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_ascii_lowercase());
+    let format = match extension.as_deref() {
+        Some("json" | "jsonl" | "ndjson") => OutputFormat::Json,
+        _ => OutputFormat::Text,
+    };
+    read_written_tracks(path, format)
+        .with_context(|| format!("invalid --exclude-from reference '{}'", path.display()))
+}
+
+/// `--matched-list` sink: the source path of every written track, one per line.
+pub struct MatchedList {
+    path: PathBuf,
+    writer: BufWriter<File>,
+}
+
+impl MatchedList {
+    /// Truncates `path`, so the list only ever holds the current run's matches.
+    pub fn create(path: &Path) -> Result<Self> {
+        let file = File::create(path)
+            .with_context(|| format!("failed to create matched list '{}'", path.display()))?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            writer: BufWriter::new(file),
+        })
+    }
+
+    pub fn record(&mut self, source: &Path) -> Result<()> {
+        writeln!(self.writer, "{}", source.display())
+            .with_context(|| format!("failed to append to matched list '{}'", self.path.display()))
+    }
+
+    pub fn flush(&mut self) -> Result<()> {
+        self.writer
+            .flush()
+            .with_context(|| format!("failed to flush matched list '{}'", self.path.display()))
+    }
+}
+
+/// Stands in for the lyrics of an `--include-empty` track in text blocks.
+const NO_LYRICS_PLACEHOLDER: &str = "(no lyrics)";
+
 pub fn format_block(metadata: &TrackMetadata) -> String {
-    let normalized_lyrics = metadata.lyrics.trim_end_matches(['\n', '\r']).to_string();
+    let mut normalized_lyrics = metadata.lyrics.trim_end_matches(['\n', '\r']).to_string();
+    if normalized_lyrics.is_empty() {
+        // Only `--include-empty` lets a track through without lyrics.
+        normalized_lyrics.push_str(NO_LYRICS_PLACEHOLDER);
+    }
+    let album = metadata
+        .album
+        .as_deref()
+        .map(|album| format!("Album: {album}\n"))
+        .unwrap_or_default();
 
     format!(
-        "=== {title} ===\nArtist: {artist}\n{lyrics}\n\n",
+        "=== {title} ===\nArtist: {artist}\n{album}{lyrics}\n\n",
         title = metadata.title,
         artist = metadata.artist,
+        album = album,
         lyrics = normalized_lyrics
     )
 }
 
+/// Formats that start a new file with a column header row.
+fn has_header_row(format: OutputFormat) -> bool {
+    matches!(format, OutputFormat::Csv | OutputFormat::Tsv)
+}
+
+/// Joins `fields` with tabs, escaping backslashes, tabs and line breaks inside each field as
+/// `\\`, `\t`, `\n` and `\r` so every record stays on one line without any quoting.
+fn tsv_row(fields: &[&str]) -> String {
+    let mut row = String::new();
+    for (index, field) in fields.iter().enumerate() {
+        if index > 0 {
+            row.push('\t');
+        }
+        for ch in field.chars() {
+            match ch {
+                '\\' => row.push_str("\\\\"),
+                '\t' => row.push_str("\\t"),
+                '\n' => row.push_str("\\n"),
+                '\r' => row.push_str("\\r"),
+                _ => row.push(ch),
+            }
+        }
+    }
+    row.push('\n');
+    row
+}
+
+/// Encodes one RFC 4180 record, quoting fields that contain delimiters, quotes, or newlines.
+fn csv_row(fields: &[&str]) -> Result<Vec<u8>> {
+    let mut csv = csv::WriterBuilder::new().from_writer(Vec::new());
+    csv.write_record(fields)
+        .context("failed to encode CSV record")?;
+    csv.into_inner()
+        .map_err(|error| anyhow::anyhow!("failed to encode CSV record: {}", error.error()))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     use std::fs;
 
+    use serde::Deserialize;
     use tempfile::NamedTempFile;
 
-    fn sample_metadata() -> TrackMetadata {
-        TrackMetadata {
-            artist: "Studio Band".to_string(),
-            title: "Echoes".to_string(),
-            lyrics: "Line one\nLine two\n".to_string(),
-        }
-    }
+    use crate::metadata::sample_metadata;
 
     #[test]
     fn dry_run_does_not_create_file() {
@@ -99,19 +910,55 @@ mod tests {
         let path = temp.into_temp_path();
         std::fs::remove_file(&path).unwrap();
 
-        OutputWriter::create(&path, OutputFormat::Text, true).expect("create dry-run writer");
+        OutputWriter::create(&path, OutputFormat::Text, true, false)
+            .expect("create dry-run writer");
         assert!(!path.exists(), "dry-run should not touch the filesystem");
     }
 
+    #[test]
+    fn preview_prefixes_every_line() {
+        let mut out = Vec::new();
+        let mut at_line_start = true;
+        write_prefixed(&mut out, b"=== Echoes ===\nLine ", &mut at_line_start).unwrap();
+        write_prefixed(&mut out, b"one\n\n", &mut at_line_start).unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "[dry-run] === Echoes ===\n[dry-run] Line one\n[dry-run] \n"
+        );
+        assert!(at_line_start);
+    }
+
+    #[test]
+    fn matched_list_truncates_and_lists_paths() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let path = temp.path().join("matched.txt");
+        fs::write(&path, "stale\n").unwrap();
+
+        let mut list = MatchedList::create(&path).unwrap();
+        list.record(Path::new("/music/a.mp3")).unwrap();
+        list.record(Path::new("/music/b.mp3")).unwrap();
+        list.flush().unwrap();
+
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "/music/a.mp3\n/music/b.mp3\n"
+        );
+    }
+
     #[test]
     fn writes_blocks_and_appends() {
         let temp = NamedTempFile::new().unwrap();
         let path = temp.path();
 
         {
-            let mut writer = OutputWriter::create(path, OutputFormat::Text, false).unwrap();
-            writer.write_entry(&sample_metadata()).unwrap();
-            writer.write_entry(&sample_metadata()).unwrap();
+            let mut writer = OutputWriter::create(path, OutputFormat::Text, false, false).unwrap();
+            writer
+                .write_entry(Path::new("song.mp3"), &sample_metadata())
+                .unwrap();
+            writer
+                .write_entry(Path::new("song.mp3"), &sample_metadata())
+                .unwrap();
             writer.flush().unwrap();
         }
 
@@ -120,6 +967,106 @@ mod tests {
         assert_eq!(contents, format!("{expected}{expected}"));
     }
 
+    #[test]
+    fn utf16le_output_round_trips_with_a_single_bom() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let path = temp.path().join("lyrics.txt");
+        let mut metadata = sample_metadata();
+        metadata.title = "Café".to_string();
+
+        for _ in 0..2 {
+            let mut writer = OutputWriter::create(&path, OutputFormat::Text, false, false)
+                .unwrap()
+                .with_encoding(OutputEncoding::Utf16le);
+            writer
+                .write_entry(Path::new("song.mp3"), &metadata)
+                .unwrap();
+            writer.flush().unwrap();
+        }
+
+        let bytes = fs::read(&path).unwrap();
+        assert_eq!(&bytes[..2], &[0xFF, 0xFE]);
+        let units: Vec<u16> = bytes[2..]
+            .chunks_exact(2)
+            .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+            .collect();
+        let expected = format_block(&metadata);
+        assert_eq!(
+            String::from_utf16(&units).unwrap(),
+            format!("{expected}{expected}")
+        );
+
+        let tracks = read_written_tracks(&path, OutputFormat::Text).unwrap();
+        assert!(tracks.contains(&("Studio Band".to_string(), "Café".to_string())));
+    }
+
+    #[test]
+    fn atomic_output_replaces_target_only_on_flush() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let path = temp.path().join("lyrics.txt");
+        fs::write(&path, "previous run\n").unwrap();
+        let entries = |dir: &Path| fs::read_dir(dir).unwrap().count();
+
+        let mut writer = OutputWriter::create_atomic(&path, OutputFormat::Text).unwrap();
+        writer
+            .write_entry(Path::new("song.mp3"), &sample_metadata())
+            .unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "previous run\n");
+        assert_eq!(entries(temp.path()), 2);
+        writer.flush().unwrap();
+        drop(writer);
+
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            format_block(&sample_metadata())
+        );
+        assert_eq!(entries(temp.path()), 1);
+
+        let mut abandoned = OutputWriter::create_atomic(&path, OutputFormat::Text).unwrap();
+        abandoned
+            .write_entry(Path::new("other.mp3"), &sample_metadata())
+            .unwrap();
+        drop(abandoned);
+        assert_eq!(entries(temp.path()), 1, "temp file should be removed");
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            format_block(&sample_metadata())
+        );
+    }
+
+    #[test]
+    fn overwrite_truncates_once_at_create() {
+        let temp = NamedTempFile::new().unwrap();
+        let path = temp.path();
+        fs::write(path, "stale lyrics\n").unwrap();
+
+        {
+            let mut writer = OutputWriter::create(path, OutputFormat::Csv, false, true).unwrap();
+            writer
+                .write_entry(Path::new("song.mp3"), &sample_metadata())
+                .unwrap();
+            writer
+                .write_entry(Path::new("song.mp3"), &sample_metadata())
+                .unwrap();
+            writer.flush().unwrap();
+        }
+
+        let contents = fs::read_to_string(path).unwrap();
+        assert!(contents.starts_with("artist,title,lyrics\n"));
+        assert_eq!(contents.matches("Studio Band,Echoes").count(), 2);
+        assert!(!contents.contains("stale lyrics"));
+    }
+
+    #[test]
+    fn overwrite_with_dry_run_keeps_existing_contents() {
+        let temp = NamedTempFile::new().unwrap();
+        let path = temp.path();
+        fs::write(path, "keep me\n").unwrap();
+
+        OutputWriter::create(path, OutputFormat::Text, true, true).unwrap();
+        assert_eq!(fs::read_to_string(path).unwrap(), "keep me\n");
+    }
+
     #[test]
     fn formats_block_with_clean_trailing_newline() {
         let metadata = sample_metadata();
@@ -127,6 +1074,13 @@ mod tests {
         assert!(formatted.ends_with("\n\n"));
         assert!(formatted.contains("=== Echoes ==="));
         assert!(formatted.contains("Artist: Studio Band"));
+        assert!(!formatted.contains("Album:"));
+
+        let with_album = TrackMetadata {
+            album: Some("Reflections".to_string()),
+            ..sample_metadata()
+        };
+        assert!(format_block(&with_album).contains("Artist: Studio Band\nAlbum: Reflections\n"));
     }
 
     #[test]
@@ -135,9 +1089,13 @@ mod tests {
         let path = temp.path();
 
         {
-            let mut writer = OutputWriter::create(path, OutputFormat::Json, false).unwrap();
-            writer.write_entry(&sample_metadata()).unwrap();
-            writer.write_entry(&sample_metadata()).unwrap();
+            let mut writer = OutputWriter::create(path, OutputFormat::Json, false, false).unwrap();
+            writer
+                .write_entry(Path::new("song.mp3"), &sample_metadata())
+                .unwrap();
+            writer
+                .write_entry(Path::new("song.mp3"), &sample_metadata())
+                .unwrap();
             writer.flush().unwrap();
         }
 
@@ -150,5 +1108,364 @@ mod tests {
 
         assert_eq!(first, sample_metadata());
         assert_eq!(second, sample_metadata());
+
+        let raw: serde_json::Value =
+            serde_json::from_str(contents.lines().next().unwrap()).unwrap();
+        assert_eq!(raw["path"], "song.mp3");
+    }
+
+    #[test]
+    fn writes_a_single_json_array() {
+        let temp = NamedTempFile::new().unwrap();
+        let path = temp.path();
+
+        {
+            let mut writer =
+                OutputWriter::create(path, OutputFormat::JsonArray, false, false).unwrap();
+            writer
+                .write_entry(Path::new("a.mp3"), &sample_metadata())
+                .unwrap();
+            writer
+                .write_entry(Path::new("b.mp3"), &sample_metadata())
+                .unwrap();
+            writer.flush().unwrap();
+        }
+
+        let contents = fs::read_to_string(path).unwrap();
+        let records: Vec<serde_json::Value> = serde_json::from_str(&contents).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[1]["path"], "b.mp3");
+        assert_eq!(records[0]["title"], "Echoes");
+    }
+
+    #[test]
+    fn empty_json_array_is_still_valid() {
+        let temp = NamedTempFile::new().unwrap();
+        let path = temp.path();
+
+        let mut writer = OutputWriter::create(path, OutputFormat::JsonArray, false, false).unwrap();
+        writer.flush().unwrap();
+        drop(writer);
+
+        assert_eq!(fs::read_to_string(path).unwrap(), "[]\n");
+
+        let mut dry = OutputWriter::create(path, OutputFormat::JsonArray, true, true).unwrap();
+        dry.write_entry(Path::new("a.mp3"), &sample_metadata())
+            .unwrap();
+        dry.flush().unwrap();
+        assert_eq!(fs::read_to_string(path).unwrap(), "[]\n");
+    }
+
+    #[test]
+    fn writes_yaml_document_stream_with_block_lyrics() {
+        let temp = NamedTempFile::new().unwrap();
+        let path = temp.path();
+
+        {
+            let mut writer = OutputWriter::create(path, OutputFormat::Yaml, false, false).unwrap();
+            writer
+                .write_entry(Path::new("a.mp3"), &sample_metadata())
+                .unwrap();
+            writer
+                .write_entry(Path::new("b.mp3"), &sample_metadata())
+                .unwrap();
+            writer.flush().unwrap();
+        }
+
+        let contents = fs::read_to_string(path).unwrap();
+        assert_eq!(contents.matches("---\n").count(), 2);
+        assert!(
+            contents.contains("lyrics: |"),
+            "unexpected YAML: {contents}"
+        );
+
+        let documents: Vec<TrackMetadata> = serde_yaml::Deserializer::from_str(&contents)
+            .map(|document| TrackMetadata::deserialize(document).unwrap())
+            .collect();
+        assert_eq!(documents, vec![sample_metadata(), sample_metadata()]);
+    }
+
+    #[test]
+    fn writes_csv_header_only_for_new_files() {
+        let temp = NamedTempFile::new().unwrap();
+        let path = temp.path();
+
+        for _ in 0..2 {
+            let mut writer = OutputWriter::create(path, OutputFormat::Csv, false, false).unwrap();
+            writer
+                .write_entry(Path::new("song.mp3"), &sample_metadata())
+                .unwrap();
+            writer.flush().unwrap();
+        }
+
+        let contents = fs::read_to_string(path).unwrap();
+        let row = "Studio Band,Echoes,\"Line one\nLine two\n\"\n";
+        assert_eq!(contents, format!("artist,title,lyrics\n{row}{row}"));
+
+        let mut reader = csv::Reader::from_path(path).unwrap();
+        let records: Vec<csv::StringRecord> = reader.records().map(|r| r.unwrap()).collect();
+        assert_eq!(records.len(), 2);
+        assert_eq!(&records[0][2], "Line one\nLine two\n");
+    }
+
+    #[test]
+    fn split_output_writes_per_track_files_and_resolves_collisions() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let mp3 = temp.path().join("song.mp3");
+        let m4a = temp.path().join("song.m4a");
+        let synced = TrackMetadata {
+            lyrics: "[00:01.00]Line one\n[00:02.50]Line two".to_string(),
+            ..sample_metadata()
+        };
+
+        let mut writer = OutputWriter::split(false);
+        writer.write_entry(&mp3, &sample_metadata()).unwrap();
+        writer.write_entry(&m4a, &sample_metadata()).unwrap();
+        writer.write_entry(&mp3, &synced).unwrap();
+        writer.flush().unwrap();
+
+        let txt = fs::read_to_string(temp.path().join("song.txt")).unwrap();
+        assert_eq!(txt, "Line one\nLine two\n");
+        assert!(temp.path().join("song-2.txt").exists());
+        let lrc = fs::read_to_string(temp.path().join("song.lrc")).unwrap();
+        assert_eq!(lrc, "[00:01.00]Line one\n[00:02.50]Line two\n");
+    }
+
+    #[test]
+    fn split_output_dry_run_writes_nothing() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let mut writer = OutputWriter::split(true);
+        writer
+            .write_entry(&temp.path().join("song.mp3"), &sample_metadata())
+            .unwrap();
+
+        assert_eq!(fs::read_dir(temp.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn by_artist_writes_one_file_per_artist() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let dir = temp.path().join("artists");
+        let source = temp.path().join("song.mp3");
+        let slashed = TrackMetadata {
+            artist: "AC/DC".to_string(),
+            ..sample_metadata()
+        };
+        let shouted = TrackMetadata {
+            artist: "STUDIO BAND".to_string(),
+            title: "Loud".to_string(),
+            ..sample_metadata()
+        };
+
+        let mut writer = OutputWriter::by_artist(&dir, OutputFormat::Csv, false, false).unwrap();
+        writer.write_entry(&source, &sample_metadata()).unwrap();
+        writer.write_entry(&source, &slashed).unwrap();
+        writer.write_entry(&source, &shouted).unwrap();
+        writer.flush().unwrap();
+
+        let band = fs::read_to_string(dir.join("Studio Band.csv")).unwrap();
+        assert_eq!(
+            band.lines()
+                .filter(|line| line.starts_with("artist,"))
+                .count(),
+            1
+        );
+        assert!(band.contains("Echoes") && band.contains("Loud"));
+        assert!(dir.join("AC_DC.csv").exists());
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn mirror_writes_one_file_per_source_directory() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let root = temp.path().join("music");
+        let dir = temp.path().join("mirror");
+
+        let mut writer = OutputWriter::mirror(
+            &dir,
+            std::slice::from_ref(&root),
+            OutputFormat::Text,
+            false,
+            false,
+        )
+        .unwrap();
+        assert!(!dir.exists());
+        writer
+            .write_entry(&root.join("Album A/one.mp3"), &sample_metadata())
+            .unwrap();
+        writer
+            .write_entry(&root.join("Album A/two.mp3"), &sample_metadata())
+            .unwrap();
+        writer
+            .write_entry(&root.join("top.mp3"), &sample_metadata())
+            .unwrap();
+        writer
+            .write_entry(&temp.path().join("elsewhere/x.mp3"), &sample_metadata())
+            .unwrap();
+        writer.flush().unwrap();
+
+        let album = fs::read_to_string(dir.join("Album A/lyrics.txt")).unwrap();
+        assert_eq!(album.matches("=== Echoes ===").count(), 2);
+        let top = fs::read_to_string(dir.join("lyrics.txt")).unwrap();
+        assert_eq!(top.matches("=== Echoes ===").count(), 2);
+    }
+
+    #[test]
+    fn pooled_files_reopen_without_losing_array_or_header_state() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let dir = temp.path().join("artists");
+        let source = temp.path().join("song.mp3");
+        let other = TrackMetadata {
+            artist: "Other Band".to_string(),
+            ..sample_metadata()
+        };
+
+        let mut writer =
+            OutputWriter::by_artist(&dir, OutputFormat::JsonArray, false, false).unwrap();
+        writer.by_artist.as_mut().unwrap().writers = WriterPool::new(1);
+        for track in [&sample_metadata(), &other, &sample_metadata(), &other] {
+            writer.write_entry(&source, track).unwrap();
+        }
+        writer.flush().unwrap();
+
+        for name in ["Studio Band.json", "Other Band.json"] {
+            let contents = fs::read_to_string(dir.join(name)).unwrap();
+            let records: Vec<serde_json::Value> = serde_json::from_str(&contents).unwrap();
+            assert_eq!(records.len(), 2, "{name}");
+        }
+
+        let root = temp.path().join("music");
+        let mirror_dir = temp.path().join("mirror");
+        let mut writer = OutputWriter::mirror(
+            &mirror_dir,
+            std::slice::from_ref(&root),
+            OutputFormat::Csv,
+            false,
+            false,
+        )
+        .unwrap();
+        writer.mirror.as_mut().unwrap().writers = WriterPool::new(1);
+        for album in ["A", "B", "A", "B"] {
+            writer
+                .write_entry(&root.join(album).join("song.mp3"), &sample_metadata())
+                .unwrap();
+        }
+        writer.flush().unwrap();
+
+        for album in ["A", "B"] {
+            let contents = fs::read_to_string(mirror_dir.join(album).join("lyrics.csv")).unwrap();
+            assert_eq!(contents.matches("artist,title,lyrics").count(), 1);
+            assert_eq!(contents.matches("Echoes").count(), 2);
+        }
+    }
+
+    #[test]
+    fn artist_file_stem_strips_unsafe_characters() {
+        assert_eq!(artist_file_stem("AC/DC"), "AC_DC");
+        assert_eq!(artist_file_stem("What? <Live>"), "What_ _Live_");
+        assert_eq!(artist_file_stem("..hidden.."), "hidden");
+        assert_eq!(artist_file_stem(" . "), "Unknown");
+        assert_eq!(artist_file_stem(&"é".repeat(300)).len(), MAX_STEM_BYTES);
+    }
+
+    #[test]
+    fn tsv_escapes_tabs_and_newlines_inside_fields() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let path = temp.path().join("lyrics.tsv");
+        let track = TrackMetadata {
+            title: "Tab\there".to_string(),
+            lyrics: "Line one\nC:\\path\r\n".to_string(),
+            ..sample_metadata()
+        };
+
+        for _ in 0..2 {
+            let mut writer = OutputWriter::create(&path, OutputFormat::Tsv, false, false).unwrap();
+            writer.write_entry(&path, &track).unwrap();
+            writer.flush().unwrap();
+        }
+
+        let row = "Studio Band\tTab\\there\tLine one\\nC:\\\\path\\r\\n\n";
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            format!("artist\ttitle\tlyrics\n{row}{row}")
+        );
+    }
+
+    #[test]
+    fn text_block_marks_tracks_without_lyrics() {
+        let track = TrackMetadata {
+            lyrics: String::new(),
+            ..sample_metadata()
+        };
+
+        assert_eq!(
+            format_block(&track),
+            "=== Echoes ===\nArtist: Studio Band\n(no lyrics)\n\n"
+        );
+    }
+
+    #[test]
+    fn reads_written_tracks_back_from_text_and_json() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let source = temp.path().join("song.mp3");
+        let expected = HashSet::from([("Studio Band".to_string(), "Echoes".to_string())]);
+
+        for format in [OutputFormat::Text, OutputFormat::Json] {
+            let path = temp.path().join(format!("{format:?}.out"));
+            assert!(read_written_tracks(&path, format).unwrap().is_empty());
+
+            let mut writer = OutputWriter::create(&path, format, false, false).unwrap();
+            writer.write_entry(&source, &sample_metadata()).unwrap();
+            writer.flush().unwrap();
+
+            assert_eq!(read_written_tracks(&path, format).unwrap(), expected);
+        }
+
+        let csv = temp.path().join("lyrics.csv");
+        fs::write(&csv, "artist,title,lyrics\n").unwrap();
+        assert!(read_written_tracks(&csv, OutputFormat::Csv).is_err());
+    }
+
+    #[test]
+    fn json_pretty_spreads_array_elements_over_lines() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let path = temp.path().join("lyrics.json");
+        let source = temp.path().join("song.mp3");
+
+        let mut writer = OutputWriter::create(&path, OutputFormat::JsonArray, false, false)
+            .unwrap()
+            .with_json_pretty(true);
+        writer.write_entry(&source, &sample_metadata()).unwrap();
+        writer.write_entry(&source, &sample_metadata()).unwrap();
+        writer.flush().unwrap();
+
+        let contents = fs::read_to_string(&path).unwrap();
+        assert!(contents.contains("{\n  \"artist\": \"Studio Band\""));
+        let records: Vec<serde_json::Value> = serde_json::from_str(&contents).unwrap();
+        assert_eq!(records.len(), 2);
+    }
+
+    #[test]
+    fn reference_tracks_pick_the_format_from_the_extension() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let missing = read_reference_tracks(&temp.path().join("known.txt")).unwrap_err();
+        assert!(format!("{missing:#}").contains("does not exist"));
+
+        let text = temp.path().join("known.txt");
+        fs::write(&text, format_block(&sample_metadata())).unwrap();
+        let jsonl = temp.path().join("known.JSONL");
+        fs::write(
+            &jsonl,
+            r#"{"artist":"Studio Band","title":"Echoes","lyrics":"x"}"#,
+        )
+        .unwrap();
+        let expected = HashSet::from([("Studio Band".to_string(), "Echoes".to_string())]);
+        assert_eq!(read_reference_tracks(&text).unwrap(), expected);
+        assert_eq!(read_reference_tracks(&jsonl).unwrap(), expected);
+
+        let broken = temp.path().join("known.json");
+        fs::write(&broken, "not json\n").unwrap();
+        let error = read_reference_tracks(&broken).unwrap_err();
+        assert!(format!("{error:#}").contains("invalid --exclude-from reference"));
     }
 }
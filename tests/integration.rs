@@ -1,4 +1,5 @@
 use std::fs;
+use std::io::Write;
 use std::path::{Path, PathBuf};
 
 use id3::frame::Lyrics;
@@ -87,6 +88,292 @@ fn dry_run_does_not_create_output_file() {
     );
 }
 
+#[test]
+fn dry_run_show_prints_entries_to_stderr() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+    write_track(
+        &root.join("song.mp3"),
+        Some("Audio Stars"),
+        None,
+        Some("Demo"),
+        &["First line", "Second line"],
+    );
+
+    assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+        .current_dir(root)
+        .arg("--dry-run-show")
+        .assert()
+        .success()
+        .stdout("")
+        .stderr(contains(
+            "[dry-run] === Demo ===\n[dry-run] Artist: Audio Stars\n",
+        ));
+
+    assert!(
+        !root.join("lyrics.txt").exists(),
+        "--dry-run-show should not create the output file"
+    );
+}
+
+#[test]
+fn matched_list_records_source_paths_of_written_tracks() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+    write_track(
+        &root.join("match.mp3"),
+        Some("Studio Heroes"),
+        None,
+        Some("Hit Single"),
+        &["Verse one"],
+    );
+    write_track(
+        &root.join("other.mp3"),
+        Some("Composer"),
+        None,
+        Some("Ambient"),
+        &["Nope"],
+    );
+
+    assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+        .current_dir(root)
+        .args(["--matched-list", "matched.txt", "--dry-run"])
+        .assert()
+        .success();
+    assert!(
+        !root.join("matched.txt").exists(),
+        "--dry-run should not create the matched list"
+    );
+
+    assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+        .current_dir(root)
+        .args(["--matched-list", "matched.txt"])
+        .assert()
+        .success();
+
+    let listed = fs::read_to_string(root.join("matched.txt")).expect("matched list written");
+    assert_eq!(listed, format!("{}\n", root.join("match.mp3").display()));
+    assert!(root.join("lyrics.txt").exists());
+}
+
+#[test]
+fn template_replaces_default_text_block() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+    let track = write_track(
+        &root.join("song.mp3"),
+        Some("Audio Stars"),
+        None,
+        Some("Demo"),
+        &["Lyrics"],
+    );
+
+    assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+        .current_dir(root)
+        .arg("--template")
+        .arg("{artist}|{title}|{path}\n{lyrics}\n")
+        .assert()
+        .success();
+
+    let contents = fs::read_to_string(root.join("lyrics.txt")).expect("lyrics written");
+    assert_eq!(
+        contents,
+        format!(
+            "Audio Stars|Demo|{}\nLyrics\n",
+            track.canonicalize().unwrap().display()
+        )
+    );
+}
+
+#[test]
+fn json_log_format_emits_one_object_per_line() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+    write_track(
+        &root.join("song.mp3"),
+        Some("Audio Stars"),
+        None,
+        Some("Demo"),
+        &["Lyrics"],
+    );
+
+    let assert = assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+        .current_dir(root)
+        .args(["--dry-run", "--log-format", "json"])
+        .assert()
+        .success();
+
+    let stderr = String::from_utf8(assert.get_output().stderr.clone()).unwrap();
+    let records: Vec<serde_json::Value> = stderr
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("every log line is JSON"))
+        .collect();
+    assert!(records
+        .iter()
+        .all(|record| record["timestamp"].is_string() && record["level"].is_string()));
+    assert!(records.iter().any(|record| record["message"]
+        .as_str()
+        .unwrap()
+        .starts_with("Scanned 1 audio files")));
+}
+
+#[test]
+fn verbose_logs_why_files_were_skipped() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+    write_track(
+        &root.join("other.mp3"),
+        Some("Composer"),
+        None,
+        Some("Ambient"),
+        &["Nope"],
+    );
+
+    assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+        .current_dir(root)
+        .assert()
+        .success()
+        .stderr(contains("does not match the filter").not());
+
+    assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+        .current_dir(root)
+        .arg("-v")
+        .env_remove("RUST_LOG")
+        .assert()
+        .success()
+        .stderr(contains(
+            "other.mp3': no artist or the artist does not match the filter.",
+        ));
+}
+
+#[test]
+fn interactive_requires_a_terminal() {
+    let temp = TempDir::new().unwrap();
+
+    assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+        .current_dir(temp.path())
+        .arg("--interactive")
+        .write_stdin("y\n")
+        .assert()
+        .failure()
+        .stderr(contains("--interactive needs a terminal"));
+    assert!(!temp.path().join("lyrics.txt").exists());
+}
+
+#[test]
+fn log_file_keeps_info_records_under_quiet() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+    write_track(
+        &root.join("song.mp3"),
+        Some("Udio"),
+        None,
+        Some("Logged"),
+        &["Words"],
+    );
+    let log_path = root.join("run.log");
+
+    assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+        .current_dir(root)
+        .args(["--dry-run", "--log-file"])
+        .arg(&log_path)
+        .env_remove("RUST_LOG")
+        .assert()
+        .success()
+        .stderr(contains("Captured lyrics for 'Logged'"));
+
+    assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+        .current_dir(root)
+        .args(["--dry-run", "--quiet", "--log-format", "json", "--log-file"])
+        .arg(&log_path)
+        .env_remove("RUST_LOG")
+        .assert()
+        .success()
+        .stderr(contains("Captured lyrics").not());
+
+    let log = fs::read_to_string(&log_path).unwrap();
+    let lines: Vec<&str> = log.lines().collect();
+    assert_eq!(
+        lines
+            .iter()
+            .filter(|line| line.contains("Captured lyrics for 'Logged'"))
+            .count(),
+        2
+    );
+    assert!(lines[0].starts_with('[') && lines[0].contains("INFO  mdlyricgetter]"));
+    let json: serde_json::Value = serde_json::from_str(lines.last().unwrap()).unwrap();
+    assert_eq!(json["level"], "INFO");
+}
+
+#[test]
+fn preview_logs_leading_lyric_lines() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+    write_track(
+        &root.join("song.mp3"),
+        Some("Studio Heroes"),
+        None,
+        Some("Hit Single"),
+        &["First line\nSecond line\nThird line"],
+    );
+
+    assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+        .current_dir(root)
+        .args(["--preview", "2"])
+        .env_remove("RUST_LOG")
+        .assert()
+        .success()
+        .stderr(contains("Preview of 'Hit Single':"))
+        .stderr(contains("    First line\n"))
+        .stderr(contains("    Second line\n"))
+        .stderr(contains("Third line").not());
+
+    assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+        .current_dir(root)
+        .args(["--preview", "2", "--quiet"])
+        .env_remove("RUST_LOG")
+        .assert()
+        .success()
+        .stderr(contains("Preview of").not());
+
+    let contents = fs::read_to_string(root.join("lyrics.txt")).unwrap();
+    assert!(contents.contains("Third line"));
+}
+
+#[test]
+fn verbose_explains_rejected_lyrics_frames() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+    let path = write_track(
+        &root.join("hidden.mp3"),
+        Some("Studio Heroes"),
+        None,
+        Some("Hidden Words"),
+        &["   "],
+    );
+    let mut tag = Tag::read_from_path(&path).unwrap();
+    tag.add_frame(id3::frame::Comment {
+        lang: "eng".to_string(),
+        description: "notes".to_string(),
+        text: "Verse in the wrong place".to_string(),
+    });
+    tag.write_to_path(&path, Version::Id3v24).unwrap();
+
+    assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+        .current_dir(root)
+        .arg("-v")
+        .env_remove("RUST_LOG")
+        .assert()
+        .success()
+        .stderr(contains(
+            "Examining 1 USLT, 0 TXXX, 1 COMM and 0 SYLT frame(s)",
+        ))
+        .stderr(contains("Ignoring USLT frame: empty text"))
+        .stderr(contains(
+            "Ignoring COMM frame with non-lyrics description 'notes'",
+        ));
+}
+
 #[test]
 fn respects_custom_artist_filter() {
     let temp = TempDir::new().unwrap();
@@ -152,11 +439,69 @@ fn emits_json_when_requested() {
     assert_eq!(first["title"], "Sunrise");
     assert_eq!(first["artist"], "Audio Ensemble");
     assert_eq!(first["lyrics"], "Golden light");
+    let first_path = PathBuf::from(first["path"].as_str().unwrap());
+    assert!(first_path.is_absolute());
+    assert!(first_path.ends_with("one.mp3"));
 
     assert_eq!(second["title"], "Midday");
     assert_eq!(second["lyrics"], "Bright sky");
 }
 
+#[test]
+fn lyrics_source_is_added_to_json_records() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+    write_track(
+        &root.join("song.mp3"),
+        Some("Udio"),
+        None,
+        Some("Song"),
+        &["Words"],
+    );
+
+    assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+        .current_dir(root)
+        .args(["--lyrics-source", "--format", "json", "--output", "-"])
+        .assert()
+        .success()
+        .stdout(contains(r#""source":"uslt""#));
+}
+
+#[test]
+fn audio_properties_are_added_to_json_records() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+    let path = write_track(
+        &root.join("song.mp3"),
+        Some("Studio Heroes"),
+        None,
+        Some("Timed"),
+        &["Words"],
+    );
+    // 100 MPEG-1 layer III frames at 128 kbps / 44.1 kHz, 417 bytes each.
+    let tag = Tag::read_from_path(&path).unwrap();
+    let mut audio = Vec::new();
+    for _ in 0..100 {
+        audio.extend_from_slice(&[0xFF, 0xFB, 0x90, 0x40]);
+        audio.resize(audio.len() + 413, 0);
+    }
+    fs::write(&path, audio).unwrap();
+    tag.write_to_path(&path, Version::Id3v24).unwrap();
+
+    assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+        .current_dir(root)
+        .args(["--format", "json", "--audio-properties"])
+        .assert()
+        .success();
+
+    let contents = fs::read_to_string(root.join("lyrics.txt")).expect("lyrics written");
+    let record: serde_json::Value = serde_json::from_str(contents.trim()).unwrap();
+    assert_eq!(
+        record["audio"],
+        serde_json::json!({"duration_ms": 2606, "bitrate_kbps": 128, "sample_rate_hz": 44100})
+    );
+}
+
 #[test]
 fn scans_additional_extensions() {
     let temp = TempDir::new().unwrap();
@@ -185,6 +530,41 @@ fn scans_additional_extensions() {
     );
 }
 
+#[test]
+fn all_extensions_reads_misnamed_tracks() {
+    let temp = TempDir::new().unwrap();
+    let music = temp.path().join("music");
+    fs::create_dir(&music).unwrap();
+    write_track(
+        &music.join("ripped_track"),
+        Some("Studio Heroes"),
+        None,
+        Some("No Extension"),
+        &["Found anyway"],
+    );
+    let notes = music.join("notes.txt");
+    fs::write(&notes, "not audio").unwrap();
+    let output = temp.path().join("lyrics.txt");
+
+    let assert = assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+        .current_dir(temp.path())
+        .args(["--root", "music", "--all-extensions", "--summary-json", "-"])
+        .arg("--output")
+        .arg(&output)
+        .assert()
+        .success();
+
+    let summary: serde_json::Value =
+        serde_json::from_slice(&assert.get_output().stdout).expect("stdout holds the summary");
+    assert_eq!(summary["matched"], 1);
+    assert_eq!(
+        summary["tag_error_paths"],
+        serde_json::json!([notes.to_str().unwrap()])
+    );
+    let contents = fs::read_to_string(&output).expect("lyrics written");
+    assert!(contents.contains("=== No Extension ==="));
+}
+
 #[test]
 fn honors_max_depth_limit() {
     let temp = TempDir::new().unwrap();
@@ -225,87 +605,1889 @@ fn honors_max_depth_limit() {
     assert!(!contents.contains("Buried"));
 }
 
-#[cfg(unix)]
 #[test]
-fn follows_symlinks_when_requested() {
-    use std::os::unix::fs::symlink;
-
+fn no_recurse_scans_only_root_files() {
     let temp = TempDir::new().unwrap();
     let root = temp.path();
-    let target_dir = TempDir::new().unwrap();
-    let target = target_dir.path();
-    std::fs::create_dir_all(target).unwrap();
 
     write_track(
-        &target.join("linked.mp3"),
+        &root.join("surface.mp3"),
         Some("Audio Layer"),
         None,
-        Some("Linked Song"),
-        &["Inside symlinked dir"],
+        Some("Surface"),
+        &["Top level"],
     );
-
-    let link = root.join("link");
-    symlink(target, &link).unwrap();
-
-    let plain_output = root.join("plain.txt");
-    assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
-        .current_dir(root)
-        .arg("--output")
-        .arg(&plain_output)
-        .assert()
-        .success();
-
-    let plain_contents = std::fs::read_to_string(&plain_output).unwrap();
-    assert!(
-        !plain_contents.contains("Linked Song"),
-        "should not traverse symlinks without flag"
+    write_track(
+        &root.join("sub").join("nested.mp3"),
+        Some("Audio Layer"),
+        None,
+        Some("Nested"),
+        &["One level down"],
     );
 
-    let follow_output = root.join("follow.txt");
     assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
         .current_dir(root)
-        .arg("--output")
-        .arg(&follow_output)
-        .arg("--follow-symlinks")
+        .env_remove("RUST_LOG")
+        .arg("--no-recurse")
         .assert()
-        .success();
+        .success()
+        .stderr(
+            contains("WARN")
+                .not()
+                .and(contains("depth limit: ").not())
+                .and(contains("directories at depth limit 0")),
+        );
 
-    let follow_contents = std::fs::read_to_string(&follow_output).unwrap();
-    assert!(
-        follow_contents.contains("Linked Song"),
-        "expected to include file reached via symlink"
-    );
+    let contents = fs::read_to_string(root.join("lyrics.txt")).expect("lyrics written");
+    assert!(contents.contains("Surface"));
+    assert!(!contents.contains("Nested"));
 }
 
 #[test]
-fn writes_summary_json_file() {
+fn from_file_processes_listed_paths_only() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+    let listed = write_track(
+        &root.join("listed.mp3"),
+        Some("Audio Layer"),
+        None,
+        Some("Listed"),
+        &["On the list"],
+    );
+    write_track(
+        &root.join("unlisted.mp3"),
+        Some("Audio Layer"),
+        None,
+        Some("Unlisted"),
+        &["Not on the list"],
+    );
+    fs::write(
+        root.join("list.txt"),
+        format!(
+            "{}\n{}\n",
+            listed.display(),
+            root.join("gone.mp3").display()
+        ),
+    )
+    .unwrap();
+
+    let summary_path = root.join("summary.json");
+    assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+        .current_dir(root)
+        .args(["--from-file", "list.txt", "--summary-json"])
+        .arg(&summary_path)
+        .assert()
+        .success()
+        .stderr(contains("gone.mp3"));
+
+    let contents = fs::read_to_string(root.join("lyrics.txt")).expect("lyrics written");
+    assert!(contents.contains("Listed"));
+    assert!(!contents.contains("Unlisted"));
+
+    let summary: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&summary_path).unwrap()).unwrap();
+    assert_eq!(summary["scanned"], 1);
+    assert_eq!(summary["walk_errors"], 1);
+}
+
+#[test]
+fn stdin0_streams_nul_separated_paths() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+    let listed = root.join("listed.mp3");
+    write_track(
+        &listed,
+        Some("Udio"),
+        None,
+        Some("Listed"),
+        &["Streamed words"],
+    );
+    write_track(
+        &root.join("unlisted.mp3"),
+        Some("Udio"),
+        None,
+        Some("Unlisted"),
+        &["Other"],
+    );
+    fs::write(root.join("notes.txt"), b"not audio").unwrap();
+
+    let mut input = Vec::new();
+    for path in [&listed, &root.join("notes.txt"), &root.join("gone.mp3")] {
+        input.extend_from_slice(path.to_str().unwrap().as_bytes());
+        input.push(0);
+    }
+
+    let summary_path = root.join("summary.json");
+    assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+        .current_dir(root)
+        .args(["--stdin0", "--output", "-", "--summary-json"])
+        .arg(&summary_path)
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout(contains("Streamed words").and(contains("Unlisted").not()));
+
+    let summary: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&summary_path).unwrap()).unwrap();
+    assert_eq!(summary["scanned"], 1);
+    assert_eq!(summary["walk_errors"], 1);
+}
+
+fn git(root: &Path, args: &[&str]) {
+    let status = std::process::Command::new("git")
+        .current_dir(root)
+        .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+        .args(args)
+        .status()
+        .expect("git runs");
+    assert!(status.success(), "git {args:?} failed");
+}
+
+#[test]
+fn git_since_scans_only_changed_files() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+    git(root, &["init", "--quiet"]);
+    write_track(
+        &root.join("old.mp3"),
+        Some("Udio"),
+        None,
+        Some("Old"),
+        &["Old words"],
+    );
+    write_track(
+        &root.join("edited.mp3"),
+        Some("Udio"),
+        None,
+        Some("Edited"),
+        &["First"],
+    );
+    git(root, &["add", "."]);
+    git(root, &["commit", "--quiet", "-m", "initial"]);
+
+    write_track(
+        &root.join("edited.mp3"),
+        Some("Udio"),
+        None,
+        Some("Edited"),
+        &["Second"],
+    );
+    write_track(
+        &root.join("added.mp3"),
+        Some("Udio"),
+        None,
+        Some("Added"),
+        &["New words"],
+    );
+    fs::write(root.join("notes.txt"), b"not audio").unwrap();
+    git(root, &["add", "added.mp3", "notes.txt"]);
+
+    let summary_path = root.join("summary.json");
+    assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+        .current_dir(root)
+        .args(["--git-since", "HEAD", "--output", "-", "--summary-json"])
+        .arg(&summary_path)
+        .assert()
+        .success()
+        .stdout(contains("Second").and(contains("New words")))
+        .stdout(contains("Old words").not());
+
+    let summary: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&summary_path).unwrap()).unwrap();
+    assert_eq!(summary["scanned"], 2);
+    assert_eq!(summary["walk_errors"], 0);
+
+    assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+        .current_dir(root)
+        .args(["--git-since", "no-such-ref", "--output", "-"])
+        .assert()
+        .failure()
+        .stderr(contains("git diff against 'no-such-ref' failed"));
+}
+
+#[test]
+fn git_since_outside_a_repository_fails() {
+    let temp = TempDir::new().unwrap();
+
+    assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+        .current_dir(temp.path())
+        .env("GIT_CEILING_DIRECTORIES", temp.path().parent().unwrap())
+        .args(["--git-since", "HEAD"])
+        .assert()
+        .failure()
+        .stderr(contains("is not inside one"));
+}
+
+#[test]
+fn include_empty_writes_tracks_without_lyrics() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+    write_track(
+        &root.join("song.mp3"),
+        Some("Udio"),
+        None,
+        Some("Sung"),
+        &["Words"],
+    );
+    write_track(
+        &root.join("inst.mp3"),
+        Some("Udio"),
+        None,
+        Some("Instrumental"),
+        &[],
+    );
+
+    let summary_path = root.join("summary.json");
+    assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+        .current_dir(root)
+        .args(["--include-empty", "--format", "json", "--summary-json"])
+        .arg(&summary_path)
+        .assert()
+        .success();
+
+    let records: Vec<serde_json::Value> = fs::read_to_string(root.join("lyrics.txt"))
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    let instrumental = records
+        .iter()
+        .find(|record| record["title"] == "Instrumental")
+        .expect("instrumental listed");
+    assert_eq!(instrumental["lyrics"], "");
+
+    let summary: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&summary_path).unwrap()).unwrap();
+    assert_eq!(summary["matched"], 2);
+    assert_eq!(summary["matched_empty"], 1);
+    assert_eq!(summary["missing_lyrics"], 0);
+}
+
+#[cfg(unix)]
+#[test]
+fn filter_cmd_rewrites_lyrics_and_falls_back_on_failure() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+    write_track(
+        &root.join("song.mp3"),
+        Some("Udio"),
+        None,
+        Some("Song"),
+        &["quiet words"],
+    );
+
+    assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+        .current_dir(root)
+        .args(["--filter-cmd", "tr a-z A-Z", "--output", "upper.txt"])
+        .assert()
+        .success();
+    let upper = fs::read_to_string(root.join("upper.txt")).unwrap();
+    assert!(upper.contains("QUIET WORDS"));
+    assert!(upper.contains("=== Song ==="));
+
+    assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+        .current_dir(root)
+        .args(["--filter-cmd", "exit 1", "--output", "kept.txt"])
+        .assert()
+        .success()
+        .stderr(contains("Keeping the original lyrics"));
+    let kept = fs::read_to_string(root.join("kept.txt")).unwrap();
+    assert!(kept.contains("quiet words"));
+}
+
+#[test]
+fn min_depth_skips_shallow_files() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+    let album = root.join("Artist").join("Album");
+
+    write_track(
+        &root.join("loose.mp3"),
+        Some("Audio Layer"),
+        None,
+        Some("Loose"),
+        &["Shallow"],
+    );
+    write_track(
+        &album.join("track.mp3"),
+        Some("Audio Layer"),
+        None,
+        Some("Album Track"),
+        &["Deep enough"],
+    );
+
+    let summary_path = root.join("summary.json");
+    assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+        .current_dir(root)
+        .arg("--min-depth")
+        .arg("2")
+        .arg("--summary-json")
+        .arg(&summary_path)
+        .assert()
+        .success();
+
+    let contents = fs::read_to_string(root.join("lyrics.txt")).expect("lyrics written");
+    assert!(contents.contains("Album Track"));
+    assert!(!contents.contains("Loose"));
+
+    let summary: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&summary_path).unwrap()).unwrap();
+    assert_eq!(summary["scanned"], 1);
+}
+
+#[cfg(unix)]
+#[test]
+fn follows_symlinks_when_requested() {
+    use std::os::unix::fs::symlink;
+
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+    let target_dir = TempDir::new().unwrap();
+    let target = target_dir.path();
+    std::fs::create_dir_all(target).unwrap();
+
+    write_track(
+        &target.join("linked.mp3"),
+        Some("Audio Layer"),
+        None,
+        Some("Linked Song"),
+        &["Inside symlinked dir"],
+    );
+
+    let link = root.join("link");
+    symlink(target, &link).unwrap();
+
+    let plain_output = root.join("plain.txt");
+    assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+        .current_dir(root)
+        .arg("--output")
+        .arg(&plain_output)
+        .assert()
+        .success();
+
+    let plain_contents = std::fs::read_to_string(&plain_output).unwrap();
+    assert!(
+        !plain_contents.contains("Linked Song"),
+        "should not traverse symlinks without flag"
+    );
+
+    let follow_output = root.join("follow.txt");
+    assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+        .current_dir(root)
+        .arg("--output")
+        .arg(&follow_output)
+        .arg("--follow-symlinks")
+        .assert()
+        .success();
+
+    let follow_contents = std::fs::read_to_string(&follow_output).unwrap();
+    assert!(
+        follow_contents.contains("Linked Song"),
+        "expected to include file reached via symlink"
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn symlink_loops_are_reported_separately() {
+    use std::os::unix::fs::symlink;
+
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+    let album = root.join("album");
+    fs::create_dir(&album).unwrap();
+    write_track(
+        &album.join("song.mp3"),
+        Some("Udio"),
+        None,
+        Some("Song"),
+        &["Words"],
+    );
+    symlink(root, album.join("again")).unwrap();
+
+    let summary_path = root.join("summary.json");
+    assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+        .current_dir(root)
+        .arg("--follow-symlinks")
+        .arg("--summary-json")
+        .arg(&summary_path)
+        .assert()
+        .success()
+        .stderr(contains("Symlink loop").and(contains("again")));
+
+    let summary: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&summary_path).unwrap()).unwrap();
+    assert_eq!(summary["symlink_loops"], 1);
+    assert_eq!(summary["walk_errors"], 0);
+    assert_eq!(summary["matched"], 1);
+}
+
+#[test]
+fn writes_summary_json_file() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+
+    write_track(
+        &root.join("song.mp3"),
+        Some("Audio Ensemble"),
+        None,
+        Some("Summary Tune"),
+        &["Lines"],
+    );
+
+    let summary_path = root.join("summary").join("run.json");
+    assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+        .current_dir(root)
+        .arg("--summary-json")
+        .arg("summary/run.json")
+        .arg("--output")
+        .arg("out.txt")
+        .assert()
+        .success();
+
+    let summary = std::fs::read_to_string(&summary_path).expect("summary written");
+    let json: serde_json::Value = serde_json::from_str(&summary).expect("valid json");
+    assert!(summary
+        .trim_start()
+        .starts_with("{\n  \"schema_version\": 1,"));
+    assert_eq!(json["matched"], 1);
+    assert_eq!(json["scanned"], 1);
+    assert_eq!(json["matched_by_extension"]["mp3"], 1);
+    assert!(json["total_bytes"].as_u64().unwrap() > 0);
+    assert_eq!(json["largest_file"]["size"], json["total_bytes"]);
+    assert!(json["elapsed_ms"].is_u64());
+    assert!(json["files_per_sec"].is_number());
+    assert_eq!(json["depth_skipped_dirs"], 0);
+    assert!(json["depth_skip_paths"].as_array().unwrap().is_empty());
+}
+
+#[test]
+fn summary_json_can_go_to_stdout() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+    write_track(
+        &root.join("song.mp3"),
+        Some("Audio Stars"),
+        None,
+        Some("Demo"),
+        &["Lyrics"],
+    );
+
+    let assert = assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+        .current_dir(root)
+        .args(["--summary-json", "-"])
+        .assert()
+        .success();
+
+    let summary: serde_json::Value =
+        serde_json::from_slice(&assert.get_output().stdout).expect("stdout holds the summary");
+    assert_eq!(summary["matched"], 1);
+    assert!(!root.join("-").exists());
+}
+
+#[test]
+fn print_count_ends_stdout_even_when_quiet() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+    write_track(
+        &root.join("song.mp3"),
+        Some("Studio Heroes"),
+        None,
+        Some("Hit Single"),
+        &["Verse one"],
+    );
+
+    let assert = assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+        .current_dir(root)
+        .args(["--output", "-", "--print-count", "--quiet"])
+        .assert()
+        .success();
+
+    let stdout = String::from_utf8(assert.get_output().stdout.clone()).unwrap();
+    assert!(stdout.starts_with("=== Hit Single ==="), "{stdout}");
+    assert!(stdout.ends_with("\nmatched=1\n"), "{stdout}");
+}
+
+#[test]
+fn min_size_skips_empty_files_without_tag_errors() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+    fs::write(root.join("partial.mp3"), b"").unwrap();
+    write_track(
+        &root.join("song.mp3"),
+        Some("Studio Heroes"),
+        None,
+        Some("Hit Single"),
+        &["Verse one"],
+    );
+
+    let assert = assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+        .current_dir(root)
+        .args(["--min-size", "1", "--summary-json", "-", "--dry-run"])
+        .assert()
+        .success();
+
+    let summary: serde_json::Value =
+        serde_json::from_slice(&assert.get_output().stdout).expect("stdout holds the summary");
+    assert_eq!(summary["too_small"], 1);
+    assert_eq!(summary["tag_errors"], 0);
+    assert_eq!(summary["matched"], 1);
+}
+
+#[test]
+fn relative_paths_strip_the_root_from_reports_and_records() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+    fs::create_dir(root.join("album")).unwrap();
+    write_track(
+        &root.join("album/song.mp3"),
+        Some("Udio"),
+        None,
+        Some("Song"),
+        &["Words"],
+    );
+    fs::write(root.join("broken.mp3"), b"not an id3 tag").unwrap();
+
+    assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+        .current_dir(root)
+        .args(["--relative-paths", "--format", "json", "--output", "-"])
+        .args(["--summary-json", "summary.json"])
+        .assert()
+        .success()
+        .stdout(contains(r#""path":"album/song.mp3""#));
+
+    let summary: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(root.join("summary.json")).unwrap()).unwrap();
+    assert_eq!(
+        summary["tag_error_paths"],
+        serde_json::json!(["broken.mp3"])
+    );
+}
+
+#[test]
+fn summary_lists_files_that_failed_to_read() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+    let broken = root.join("broken.mp3");
+    fs::write(&broken, b"not an id3 tag").unwrap();
+
+    let summary_path = root.join("summary.json");
+    assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+        .current_dir(root)
+        .arg("--summary-json")
+        .arg(&summary_path)
+        .assert()
+        .success();
+
+    let summary: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&summary_path).unwrap()).unwrap();
+    assert_eq!(summary["tag_errors"], 1);
+    assert_eq!(
+        summary["tag_error_paths"],
+        serde_json::json!([broken.to_str().unwrap()])
+    );
+}
+
+#[test]
+fn errors_json_lists_each_failure_with_its_message() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+    let broken = root.join("broken.mp3");
+    fs::write(&broken, b"not an id3 tag").unwrap();
+
+    assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+        .current_dir(root)
+        .args(["--errors-json", "reports/errors.json"])
+        .assert()
+        .success();
+
+    let errors: serde_json::Value = serde_json::from_str(
+        &fs::read_to_string(root.join("reports/errors.json")).expect("errors written"),
+    )
+    .unwrap();
+    let errors = errors.as_array().expect("array of errors");
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0]["path"], broken.to_str().unwrap());
+    assert_eq!(errors[0]["kind"], "tag");
+    assert!(!errors[0]["message"].as_str().unwrap().is_empty());
+}
+
+#[test]
+fn list_artists_reports_distinct_matched_artists() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+    write_track(
+        &root.join("a.mp3"),
+        Some("Studio Ghibli"),
+        None,
+        Some("One"),
+        &["x"],
+    );
+    write_track(
+        &root.join("b.mp3"),
+        Some("Audio Stars"),
+        None,
+        Some("Two"),
+        &["y"],
+    );
+    write_track(
+        &root.join("c.mp3"),
+        Some("Audio Stars"),
+        None,
+        Some("Three"),
+        &["z"],
+    );
+
+    let summary_path = root.join("summary.json");
+    assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+        .current_dir(root)
+        .arg("--dry-run")
+        .arg("--list-artists")
+        .arg("--summary-json")
+        .arg(&summary_path)
+        .assert()
+        .success()
+        .stderr(contains("Matched 2 distinct artists").and(contains("Studio Ghibli")));
+
+    let summary: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&summary_path).unwrap()).unwrap();
+    assert_eq!(
+        summary["matched_artists"],
+        serde_json::json!(["Audio Stars", "Studio Ghibli"])
+    );
+}
+
+#[test]
+fn extracts_lyrics_from_m4a_files() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+
+    write_m4a(
+        &root.join("aac.m4a"),
+        "Studio Heroes",
+        "AAC Song",
+        "Container lyrics",
+    );
+    fs::write(root.join("broken.m4a"), b"not an mp4").unwrap();
+
+    let summary_path = root.join("summary.json");
+    assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+        .current_dir(root)
+        .arg("--extensions")
+        .arg("m4a,mp3")
+        .arg("--summary-json")
+        .arg(&summary_path)
+        .assert()
+        .success();
+
+    let contents = fs::read_to_string(root.join("lyrics.txt")).expect("lyrics written");
+    assert!(contents.contains("=== AAC Song ==="));
+    assert!(contents.contains("Artist: Studio Heroes"));
+    assert!(contents.contains("Container lyrics"));
+
+    let summary: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&summary_path).unwrap()).unwrap();
+    assert_eq!(summary["matched"], 1);
+    assert_eq!(summary["tag_errors"], 1);
+}
+
+#[test]
+fn extracts_lyrics_from_ogg_and_opus_files() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+
+    write_ogg(
+        &root.join("vorbis.ogg"),
+        b"\x03vorbis",
+        &[
+            "ARTIST=Studio Heroes",
+            "TITLE=Ogg Song",
+            "LYRICS=Vorbis words",
+        ],
+    );
+    write_ogg(
+        &root.join("voice.opus"),
+        b"OpusTags",
+        &["ARTIST=Studio Heroes", "TITLE=Opus Song"],
+    );
+
+    let summary_path = root.join("summary.json");
+    assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+        .current_dir(root)
+        .arg("--extensions")
+        .arg("ogg,opus")
+        .arg("--summary-json")
+        .arg(&summary_path)
+        .assert()
+        .success();
+
+    let contents = fs::read_to_string(root.join("lyrics.txt")).expect("lyrics written");
+    assert!(contents.contains("=== Ogg Song ==="));
+    assert!(contents.contains("Vorbis words"));
+
+    let summary: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&summary_path).unwrap()).unwrap();
+    assert_eq!(summary["matched"], 1);
+    assert_eq!(summary["missing_lyrics"], 1);
+}
+
+#[test]
+fn wav_without_id3_chunk_counts_as_missing_lyrics() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+    let mut fmt = Vec::new();
+    for field in [1u16, 1] {
+        fmt.extend_from_slice(&field.to_le_bytes());
+    }
+    fmt.extend_from_slice(&8000u32.to_le_bytes());
+    fmt.extend_from_slice(&8000u32.to_le_bytes());
+    fmt.extend_from_slice(&1u16.to_le_bytes());
+    fmt.extend_from_slice(&8u16.to_le_bytes());
+    let mut wav = b"RIFF".to_vec();
+    wav.extend_from_slice(&(4 + 8 + fmt.len() as u32 + 8 + 4).to_le_bytes());
+    wav.extend_from_slice(b"WAVEfmt ");
+    wav.extend_from_slice(&(fmt.len() as u32).to_le_bytes());
+    wav.extend_from_slice(&fmt);
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&4u32.to_le_bytes());
+    wav.extend_from_slice(&[128; 4]);
+    fs::write(root.join("bare.wav"), wav).unwrap();
+
+    let summary_path = root.join("summary.json");
+    assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+        .current_dir(root)
+        .args(["--extensions", "wav", "--dry-run", "--summary-json"])
+        .arg(&summary_path)
+        .assert()
+        .success();
+
+    let summary: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&summary_path).unwrap()).unwrap();
+    assert_eq!(summary["missing_lyrics"], 1);
+    assert_eq!(summary["skipped_artist"], 0);
+    assert_eq!(summary["tag_errors"], 0);
+}
+
+#[test]
+fn split_output_writes_lyrics_beside_tracks() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+    let album = root.join("album");
+
+    write_track(
+        &album.join("opener.mp3"),
+        Some("Studio Heroes"),
+        None,
+        Some("Opener"),
+        &["First verse"],
+    );
+
+    assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+        .current_dir(root)
+        .arg("--split-output")
+        .assert()
+        .success();
+
+    let sidecar = fs::read_to_string(album.join("opener.txt")).expect("per-track file");
+    assert_eq!(sidecar, "First verse\n");
+    assert!(!root.join("lyrics.txt").exists());
+}
+
+#[test]
+fn mirror_output_writes_a_file_per_source_directory() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+    let album = root.join("Band/Album");
+    fs::create_dir_all(&album).unwrap();
+    write_track(
+        &album.join("01.mp3"),
+        Some("Udio"),
+        None,
+        Some("One"),
+        &["First words"],
+    );
+    write_track(
+        &album.join("02.mp3"),
+        Some("Udio"),
+        None,
+        Some("Two"),
+        &["Second words"],
+    );
+    write_track(
+        &root.join("loose.mp3"),
+        Some("Udio"),
+        None,
+        Some("Loose"),
+        &["Loose words"],
+    );
+
+    assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+        .current_dir(root)
+        .args(["--mirror-output", "mirror"])
+        .assert()
+        .success();
+
+    let album_lyrics = fs::read_to_string(root.join("mirror/Band/Album/lyrics.txt")).unwrap();
+    assert!(album_lyrics.contains("First words") && album_lyrics.contains("Second words"));
+    assert!(!album_lyrics.contains("Loose words"));
+    let top = fs::read_to_string(root.join("mirror/lyrics.txt")).unwrap();
+    assert!(top.contains("Loose words"));
+    assert!(!root.join("mirror/Band/lyrics.txt").exists());
+    assert!(!root.join("lyrics.txt").exists());
+}
+
+#[test]
+fn split_by_artist_writes_one_file_per_artist() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+    write_track(
+        &root.join("a.mp3"),
+        Some("Udio Band"),
+        None,
+        Some("One"),
+        &["Band words"],
+    );
+    write_track(
+        &root.join("b.mp3"),
+        Some("Udio/Solo"),
+        None,
+        Some("Two"),
+        &["Solo words"],
+    );
+
+    assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+        .current_dir(root)
+        .args(["--split-by-artist", "by-artist"])
+        .assert()
+        .success();
+
+    let band = fs::read_to_string(root.join("by-artist/Udio Band.txt")).expect("band file");
+    assert!(band.contains("Band words") && !band.contains("Solo words"));
+    let solo = fs::read_to_string(root.join("by-artist/Udio_Solo.txt")).expect("solo file");
+    assert!(solo.contains("Solo words"));
+    assert!(!root.join("lyrics.txt").exists());
+}
+
+#[test]
+fn skip_existing_does_not_rewrite_tracks_from_earlier_runs() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+    write_track(
+        &root.join("a.mp3"),
+        Some("Udio"),
+        None,
+        Some("First"),
+        &["Old words"],
+    );
+
+    let run = || {
+        assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+            .current_dir(root)
+            .args(["--skip-existing", "--summary-json", "summary.json"])
+            .assert()
+            .success();
+    };
+    run();
+    write_track(
+        &root.join("b.mp3"),
+        Some("Udio"),
+        None,
+        Some("Second"),
+        &["New words"],
+    );
+    run();
+
+    let contents = fs::read_to_string(root.join("lyrics.txt")).expect("lyrics written");
+    assert_eq!(contents.matches("=== First ===").count(), 1);
+    assert_eq!(contents.matches("=== Second ===").count(), 1);
+
+    let summary: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(root.join("summary.json")).unwrap()).unwrap();
+    assert_eq!(summary["matched"], 1);
+    assert_eq!(summary["skipped_existing"], 1);
+}
+
+#[test]
+fn exclude_from_skips_tracks_in_the_reference() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+    write_track(
+        &root.join("a.mp3"),
+        Some("Udio"),
+        None,
+        Some("Known"),
+        &["Old words"],
+    );
+    write_track(
+        &root.join("b.mp3"),
+        Some("Udio"),
+        None,
+        Some("Fresh"),
+        &["New words"],
+    );
+    fs::write(
+        root.join("known.jsonl"),
+        r#"{"artist":"Udio","title":"Known","lyrics":"Old words"}"#,
+    )
+    .unwrap();
+
+    assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+        .current_dir(root)
+        .args(["--exclude-from", "known.jsonl", "--output", "-"])
+        .assert()
+        .success()
+        .stdout(contains("New words").and(contains("Old words").not()));
+
+    fs::write(root.join("broken.json"), "not json").unwrap();
+    assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+        .current_dir(root)
+        .args(["--exclude-from", "broken.json"])
+        .assert()
+        .failure()
+        .stderr(contains("invalid --exclude-from reference"));
+    assert!(!root.join("lyrics.txt").exists());
+}
+
+#[test]
+fn hash_dedupes_differently_tagged_copies_of_the_same_audio() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+    write_track(
+        &root.join("a.mp3"),
+        Some("Udio"),
+        None,
+        Some("Same"),
+        &["Words"],
+    );
+    write_track(
+        &root.join("b.mp3"),
+        Some("Udio Remaster"),
+        None,
+        Some("Same (Remastered)"),
+        &["Words", "More words"],
+    );
+    write_track(
+        &root.join("c.mp3"),
+        Some("Udio"),
+        None,
+        Some("Other"),
+        &["Else"],
+    );
+    fs::OpenOptions::new()
+        .append(true)
+        .open(root.join("c.mp3"))
+        .unwrap()
+        .write_all(b"different audio")
+        .unwrap();
+
+    assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+        .current_dir(root)
+        .args(["--hash", "md5", "--format", "json", "--output", "-"])
+        .assert()
+        .success()
+        .stdout(contains(r#""hash":""#).count(3));
+
+    assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+        .current_dir(root)
+        .args([
+            "--hash", "blake3", "--dedupe", "--format", "json", "--output", "-",
+        ])
+        .assert()
+        .success()
+        .stdout(
+            contains(r#""hash":""#)
+                .count(2)
+                .and(contains("Remastered").not()),
+        );
+}
+
+#[test]
+fn count_only_counts_artist_matches_without_writing() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+    write_track(
+        &root.join("a.mp3"),
+        Some("Udio"),
+        None,
+        Some("Sung"),
+        &["Words"],
+    );
+    write_track(&root.join("b.mp3"), Some("Udio"), None, Some("Bare"), &[]);
+    write_track(
+        &root.join("c.mp3"),
+        Some("Other"),
+        None,
+        Some("Else"),
+        &["More"],
+    );
+
+    let summary_path = root.join("summary.json");
+    assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+        .current_dir(root)
+        .arg("--count-only")
+        .arg("--summary-json")
+        .arg(&summary_path)
+        .assert()
+        .success();
+
+    assert!(!root.join("lyrics.txt").exists());
+    let summary: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&summary_path).unwrap()).unwrap();
+    assert_eq!(summary["matched"], 2);
+    assert_eq!(summary["skipped_artist"], 1);
+    assert_eq!(summary["missing_lyrics"], 0);
+    assert_eq!(summary["total_lyric_lines"], 0);
+}
+
+#[test]
+fn artist_frames_match_composer_only_tracks() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+    let path = write_track(
+        &root.join("song.mp3"),
+        None,
+        None,
+        Some("Composed"),
+        &["Words"],
+    );
+    let mut tag = Tag::read_from_path(&path).unwrap();
+    tag.add_frame(id3::Frame::text("TCOM", "Udio Writer"));
+    tag.write_to_path(&path, Version::Id3v24).unwrap();
+
+    assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+        .current_dir(root)
+        .args(["--output", "-", "--format", "json"])
+        .assert()
+        .success()
+        .stdout(contains("Composed").not());
+
+    assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+        .current_dir(root)
+        .args([
+            "--artist-frames",
+            "artist,album_artist,composer",
+            "--output",
+            "-",
+        ])
+        .args(["--format", "json"])
+        .assert()
+        .success()
+        .stdout(contains(r#""artist":"Udio Writer""#).and(contains("Composed")));
+}
+
+#[test]
+fn flatten_writes_single_spaced_lyrics() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+    write_track(
+        &root.join("song.mp3"),
+        Some("Udio"),
+        None,
+        Some("Spaced"),
+        &["Line one\n\nLine two"],
+    );
+
+    assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+        .current_dir(root)
+        .args(["--output", "-"])
+        .assert()
+        .success()
+        .stdout(contains("Line one\n\nLine two\n\n"));
+
+    assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+        .current_dir(root)
+        .args(["--flatten", "--output", "-"])
+        .assert()
+        .success()
+        .stdout("=== Spaced ===\nArtist: Udio\nLine one\nLine two\n\n");
+}
+
+#[test]
+fn artist_exclude_skips_matching_artists() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+    write_track(
+        &root.join("a.mp3"),
+        Some("Udio"),
+        None,
+        Some("Kept"),
+        &["Sung"],
+    );
+    write_track(
+        &root.join("b.mp3"),
+        Some("Udio Karaoke"),
+        None,
+        Some("Backing"),
+        &["La"],
+    );
+    write_track(
+        &root.join("c.mp3"),
+        Some("Udio Skits"),
+        None,
+        Some("Skit"),
+        &["Ha"],
+    );
+
+    let summary_path = root.join("summary.json");
+    assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+        .current_dir(root)
+        .args(["--artist-exclude", "karaoke", "--artist-exclude", "skit"])
+        .args(["--output", "-", "--summary-json"])
+        .arg(&summary_path)
+        .assert()
+        .success()
+        .stdout(
+            contains("Kept")
+                .and(contains("Backing").not())
+                .and(contains("Skit").not()),
+        );
+
+    let summary: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&summary_path).unwrap()).unwrap();
+    assert_eq!(summary["matched"], 1);
+    assert_eq!(summary["skipped_artist"], 2);
+}
+
+#[test]
+fn duration_range_skips_tracks_and_keeps_unknown_lengths() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+    write_track(
+        &root.join("untimed.mp3"),
+        Some("Udio"),
+        None,
+        Some("Unknown"),
+        &["Words"],
+    );
+    let path = write_track(
+        &root.join("skit.mp3"),
+        Some("Udio"),
+        None,
+        Some("Skit"),
+        &["Ha"],
+    );
+    // 100 MPEG-1 layer III frames at 128 kbps / 44.1 kHz: about 2.6 seconds.
+    let tag = Tag::read_from_path(&path).unwrap();
+    let mut audio = Vec::new();
+    for _ in 0..100 {
+        audio.extend_from_slice(&[0xFF, 0xFB, 0x90, 0x40]);
+        audio.resize(audio.len() + 413, 0);
+    }
+    fs::write(&path, audio).unwrap();
+    tag.write_to_path(&path, Version::Id3v24).unwrap();
+
+    let summary_path = root.join("summary.json");
+    assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+        .current_dir(root)
+        .args(["--min-duration", "3", "--output", "-", "--summary-json"])
+        .arg(&summary_path)
+        .assert()
+        .success()
+        .stdout(contains("Unknown").and(contains("Skit").not()));
+
+    let summary: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&summary_path).unwrap()).unwrap();
+    assert_eq!(summary["matched"], 1);
+    assert_eq!(summary["outside_duration"], 1);
+
+    assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+        .current_dir(root)
+        .args([
+            "--min-duration",
+            "2",
+            "--max-duration",
+            "3",
+            "--output",
+            "-",
+        ])
+        .assert()
+        .success()
+        .stdout(contains("Unknown").and(contains("Skit")));
+}
+
+#[test]
+fn dedupe_keeps_first_copy_of_each_track() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+
+    for name in ["a.mp3", "b.mp3"] {
+        write_track(
+            &root.join(name),
+            Some("Studio Heroes"),
+            None,
+            Some("Hit Single"),
+            &["Same words"],
+        );
+    }
+
+    let summary_path = root.join("summary.json");
+    assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+        .current_dir(root)
+        .arg("--dedupe")
+        .arg("--summary-json")
+        .arg(&summary_path)
+        .assert()
+        .success();
+
+    let contents = fs::read_to_string(root.join("lyrics.txt")).expect("lyrics written");
+    assert_eq!(contents.matches("=== Hit Single ===").count(), 1);
+
+    let summary: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&summary_path).unwrap()).unwrap();
+    assert_eq!(summary["matched"], 1);
+    assert_eq!(summary["duplicates"], 1);
+}
+
+#[test]
+fn exclude_skips_matching_directories_without_counting_them() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+
+    write_track(
+        &root.join("keep.mp3"),
+        Some("Studio Heroes"),
+        None,
+        Some("Keeper"),
+        &["Stay"],
+    );
+    let trash = root.join(".trash");
+    fs::create_dir(&trash).unwrap();
+    write_track(
+        &trash.join("junk.mp3"),
+        Some("Studio Heroes"),
+        None,
+        Some("Junk Copy"),
+        &["Gone"],
+    );
+
+    let summary_path = root.join("summary.json");
+    assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+        .current_dir(root)
+        .arg("--exclude")
+        .arg(".trash")
+        .arg("--summary-json")
+        .arg(&summary_path)
+        .assert()
+        .success();
+
+    let contents = fs::read_to_string(root.join("lyrics.txt")).expect("lyrics written");
+    assert!(contents.contains("=== Keeper ==="));
+    assert!(!contents.contains("Junk Copy"));
+
+    let summary: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&summary_path).unwrap()).unwrap();
+    assert_eq!(summary["scanned"], 1);
+}
+
+#[test]
+fn progress_is_silent_when_stderr_is_not_a_terminal() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+
+    write_track(
+        &root.join("song.mp3"),
+        Some("Studio Heroes"),
+        None,
+        Some("Quiet Spinner"),
+        &["Hush"],
+    );
+
+    let output = assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+        .current_dir(root)
+        .arg("--progress")
+        .arg("--quiet")
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty());
+    assert!(output.stderr.is_empty());
+    assert!(fs::read_to_string(root.join("lyrics.txt"))
+        .unwrap()
+        .contains("=== Quiet Spinner ==="));
+}
+
+#[test]
+fn dash_output_streams_json_to_stdout() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+
+    write_track(
+        &root.join("song.mp3"),
+        Some("Studio Heroes"),
+        None,
+        Some("Piped"),
+        &["Through jq"],
+    );
+
+    let output = assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+        .current_dir(root)
+        .args(["--output", "-", "--format", "json"])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let record: serde_json::Value = serde_json::from_str(stdout.trim()).expect("one JSON line");
+    assert_eq!(record["title"], "Piped");
+    assert!(!root.join("-").exists());
+    assert!(!root.join("lyrics.txt").exists());
+
+    assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+        .current_dir(root)
+        .args(["--output", "-", "--dry-run"])
+        .assert()
+        .success()
+        .stdout("");
+}
+
+#[test]
+fn by_language_labels_each_language() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+    write_track(
+        &root.join("song.mp3"),
+        Some("Udio"),
+        None,
+        Some("Song"),
+        &["Words", "Mots"],
+    );
+
+    assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+        .current_dir(root)
+        .args(["--by-language", "--output", "-"])
+        .assert()
+        .success()
+        .stdout(contains("[eng]\nWords\n\nMots\n"));
+}
+
+#[test]
+fn artist_source_album_artist_matches_compilations() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+    write_track(
+        &root.join("song.mp3"),
+        Some("Choir"),
+        Some("Udio Compilation"),
+        Some("Song"),
+        &["Words"],
+    );
+
+    assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+        .current_dir(root)
+        .args(["--output", "-"])
+        .assert()
+        .success()
+        .stdout(contains("Words").not());
+
+    assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+        .current_dir(root)
+        .args(["--artist-source", "album-artist", "--output", "-"])
+        .assert()
+        .success()
+        .stdout(contains("Artist: Udio Compilation").and(contains("Words")));
+}
+
+#[test]
+fn timestamp_output_writes_a_dated_file() {
     let temp = TempDir::new().unwrap();
     let root = temp.path();
-
     write_track(
         &root.join("song.mp3"),
-        Some("Audio Ensemble"),
+        Some("Udio"),
         None,
-        Some("Summary Tune"),
-        &["Lines"],
+        Some("Song"),
+        &["Words"],
     );
 
-    let summary_path = root.join("summary").join("run.json");
     assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
         .current_dir(root)
+        .arg("--timestamp-output")
+        .assert()
+        .success();
+
+    assert!(!root.join("lyrics.txt").exists());
+    let written: Vec<String> = std::fs::read_dir(root)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .filter(|name| name.starts_with("lyrics-") && name.ends_with(".txt"))
+        .collect();
+    assert_eq!(written.len(), 1, "{written:?}");
+    let contents = std::fs::read_to_string(root.join(&written[0])).unwrap();
+    assert!(contents.contains("Words"));
+}
+
+#[test]
+fn max_lyric_chars_truncates_long_lyrics_with_a_warning() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+    write_track(
+        &root.join("essay.mp3"),
+        Some("Udio"),
+        None,
+        Some("Essay"),
+        &["Once upon a time"],
+    );
+    write_track(
+        &root.join("short.mp3"),
+        Some("Udio"),
+        None,
+        Some("Short"),
+        &["Hi"],
+    );
+
+    assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+        .current_dir(root)
+        .args(["--max-lyric-chars", "9", "--output", "-"])
+        .assert()
+        .success()
+        .stdout(contains("Once upon…\n").and(contains("Hi\n")))
+        .stderr(contains("Truncated the lyrics of").and(contains("essay.mp3")));
+}
+
+#[test]
+fn max_files_stops_after_examining_enough_files() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+
+    for index in 0..5 {
+        let artist = if index % 2 == 0 { "Choir" } else { "Udio" };
+        write_track(
+            &root.join(format!("track{index}.mp3")),
+            Some(artist),
+            None,
+            Some(&format!("Track {index}")),
+            &["Words"],
+        );
+    }
+
+    let summary_path = root.join("summary.json");
+    assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+        .current_dir(root)
+        .args([
+            "--max-files",
+            "3",
+            "--limit",
+            "5",
+            "--jobs",
+            "1",
+            "--summary-json",
+        ])
+        .arg(&summary_path)
+        .assert()
+        .success()
+        .stderr(contains("Stopped early after examining 3 files"));
+
+    let summary: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&summary_path).unwrap()).unwrap();
+    assert_eq!(summary["scanned"], 3);
+    assert_eq!(summary["matched"], 1);
+}
+
+#[test]
+fn limit_stops_scanning_after_enough_matches() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+
+    for index in 0..5 {
+        write_track(
+            &root.join(format!("track{index}.mp3")),
+            Some("Studio Heroes"),
+            None,
+            Some(&format!("Track {index}")),
+            &["Words"],
+        );
+    }
+
+    let summary_path = root.join("summary.json");
+    assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+        .current_dir(root)
+        .args(["--limit", "2", "--jobs", "1", "--summary-json"])
+        .arg(&summary_path)
+        .assert()
+        .success();
+
+    let contents = fs::read_to_string(root.join("lyrics.txt")).expect("lyrics written");
+    assert!(contents.contains("=== Track 0 ==="));
+    assert!(contents.contains("=== Track 1 ==="));
+    assert!(!contents.contains("=== Track 2 ==="));
+
+    let summary: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&summary_path).unwrap()).unwrap();
+    assert_eq!(summary["matched"], 2);
+    assert_eq!(summary["scanned"], 2);
+}
+
+#[test]
+fn sidecar_lyrics_fill_in_missing_embedded_lyrics() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+
+    write_track(
+        &root.join("bare.mp3"),
+        Some("Studio Heroes"),
+        None,
+        Some("Sidecar Song"),
+        &[],
+    );
+    fs::write(
+        root.join("bare.lrc"),
+        "[ti:Sidecar Song]\n[00:05.00]From the sidecar\n",
+    )
+    .unwrap();
+
+    assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+        .current_dir(root)
+        .env_remove("RUST_LOG")
+        .arg("--dry-run")
+        .assert()
+        .success()
+        .stderr(contains("'Sidecar Song'").and(contains("no lyrics frames found")));
+
+    assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+        .current_dir(root)
+        .arg("--sidecar-lyrics")
+        .assert()
+        .success();
+
+    let contents = fs::read_to_string(root.join("lyrics.txt")).expect("lyrics written");
+    assert!(contents.contains("=== Sidecar Song ==="));
+    assert!(contents.contains("From the sidecar"));
+    assert!(!contents.contains("[00:05.00]"));
+}
+
+#[test]
+fn state_file_skips_unchanged_tracks_on_rerun() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+    let library = root.join("library");
+    fs::create_dir(&library).unwrap();
+
+    write_track(
+        &library.join("song.mp3"),
+        Some("Studio Heroes"),
+        None,
+        Some("Once Only"),
+        &["Words"],
+    );
+
+    let run = |summary: &Path| {
+        assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+            .current_dir(root)
+            .arg("--root")
+            .arg(&library)
+            .arg("--output")
+            .arg(root.join("lyrics.txt"))
+            .arg("--state")
+            .arg(root.join("state.json"))
+            .arg("--summary-json")
+            .arg(summary)
+            .assert()
+            .success();
+        serde_json::from_str::<serde_json::Value>(&fs::read_to_string(summary).unwrap()).unwrap()
+    };
+
+    let first = run(&root.join("first.json"));
+    assert_eq!(first["matched"], 1);
+    assert!(root.join("state.json").exists());
+
+    let second = run(&root.join("second.json"));
+    assert_eq!(second["matched"], 0);
+    assert_eq!(second["unchanged"], 1);
+
+    let contents = fs::read_to_string(root.join("lyrics.txt")).unwrap();
+    assert_eq!(contents.matches("=== Once Only ===").count(), 1);
+}
+
+#[test]
+fn state_file_keeps_files_a_partial_run_did_not_visit() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+    for (file, title) in [("a.mp3", "First"), ("b.mp3", "Second")] {
+        write_track(
+            &root.join(file),
+            Some("Studio Heroes"),
+            None,
+            Some(title),
+            &["Words"],
+        );
+    }
+
+    let run = |extra: &[&str]| {
+        let summary = root.join("summary.json");
+        assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+            .current_dir(root)
+            .args(["--state", "state.json", "--summary-json"])
+            .arg(&summary)
+            .args(extra)
+            .assert()
+            .success();
+        serde_json::from_str::<serde_json::Value>(&fs::read_to_string(&summary).unwrap()).unwrap()
+    };
+
+    assert_eq!(run(&[])["matched"], 2);
+    let partial = run(&["--max-files", "1"]);
+    assert_eq!(partial["unchanged"], 1);
+    let full = run(&[]);
+    assert_eq!(full["matched"], 0);
+    assert_eq!(full["unchanged"], 2);
+
+    let contents = fs::read_to_string(root.join("lyrics.txt")).unwrap();
+    assert_eq!(contents.matches("=== Second ===").count(), 1);
+}
+
+#[test]
+fn scans_every_repeated_root_into_one_output() {
+    let temp = TempDir::new().unwrap();
+    let music = temp.path().join("music");
+    let downloads = temp.path().join("downloads");
+    fs::create_dir(&music).unwrap();
+    fs::create_dir(&downloads).unwrap();
+
+    write_track(
+        &music.join("one.mp3"),
+        Some("Studio Heroes"),
+        None,
+        Some("From Music"),
+        &["First"],
+    );
+    write_track(
+        &downloads.join("two.mp3"),
+        Some("Studio Heroes"),
+        None,
+        Some("From Downloads"),
+        &["Second"],
+    );
+
+    let summary_path = temp.path().join("summary.json");
+    assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+        .current_dir(temp.path())
+        .arg("--root")
+        .arg(&music)
+        .arg("--root")
+        .arg(&downloads)
+        .arg("--summary-json")
+        .arg(&summary_path)
+        .assert()
+        .success();
+
+    let contents = fs::read_to_string(music.join("lyrics.txt")).expect("lyrics written");
+    assert!(contents.contains("=== From Music ==="));
+    assert!(contents.contains("=== From Downloads ==="));
+
+    let summary: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&summary_path).unwrap()).unwrap();
+    assert_eq!(summary["scanned"], 2);
+    assert_eq!(summary["matched"], 2);
+}
+
+#[test]
+fn parallel_jobs_capture_every_track() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+
+    for index in 0..12 {
+        write_track(
+            &root
+                .join(format!("disc{}", index % 3))
+                .join(format!("{index}.mp3")),
+            Some("Audio Ensemble"),
+            None,
+            Some(&format!("Track {index}")),
+            &[&format!("Verse {index}")],
+        );
+    }
+    write_track(
+        &root.join("other.mp3"),
+        Some("Composer"),
+        None,
+        Some("Other"),
+        &["Nope"],
+    );
+
+    let summary_path = root.join("summary.json");
+    assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+        .current_dir(root)
+        .arg("--jobs")
+        .arg("4")
         .arg("--summary-json")
-        .arg("summary/run.json")
-        .arg("--output")
-        .arg("out.txt")
+        .arg(&summary_path)
         .assert()
         .success();
 
-    let summary = std::fs::read_to_string(&summary_path).expect("summary written");
-    let json: serde_json::Value = serde_json::from_str(&summary).expect("valid json");
-    assert_eq!(json["matched"], 1);
-    assert_eq!(json["scanned"], 1);
-    assert_eq!(json["depth_skipped_dirs"], 0);
-    assert!(json["depth_skip_paths"].as_array().unwrap().is_empty());
+    let contents = fs::read_to_string(root.join("lyrics.txt")).expect("lyrics written");
+    for index in 0..12 {
+        assert!(contents.contains(&format!("=== Track {index} ===")));
+    }
+
+    let summary: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&summary_path).unwrap()).unwrap();
+    assert_eq!(summary["scanned"], 13);
+    assert_eq!(summary["matched"], 12);
+    assert_eq!(summary["skipped_artist"], 1);
+}
+
+#[test]
+fn single_slot_queue_still_captures_every_track() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+    for index in 0..6 {
+        write_track(
+            &root.join(format!("{index}.mp3")),
+            Some("Audio Ensemble"),
+            None,
+            Some(&format!("Track {index}")),
+            &[&format!("Verse {index}")],
+        );
+    }
+
+    let assert = assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+        .current_dir(root)
+        .args(["--jobs", "4", "--queue-size", "1", "--summary-json", "-"])
+        .assert()
+        .success();
+
+    let summary: serde_json::Value =
+        serde_json::from_slice(&assert.get_output().stdout).expect("stdout holds the summary");
+    assert_eq!(summary["matched"], 6);
+}
+
+#[test]
+fn parallel_scan_writes_in_walk_order() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+    for index in 0..24 {
+        write_track(
+            &root.join(format!("{index:02}.mp3")),
+            Some("Audio Ensemble"),
+            None,
+            Some(&format!("Track {index:02}")),
+            &["Words"],
+        );
+    }
+
+    assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+        .current_dir(root)
+        .args(["--jobs", "4", "--queue-size", "2", "--output", "-"])
+        .assert()
+        .success()
+        .stdout(predicates::function::function(|out: &str| {
+            let titles: Vec<&str> = out
+                .lines()
+                .filter_map(|line| line.strip_prefix("=== Track "))
+                .collect();
+            let expected: Vec<String> = (0..24).map(|index| format!("{index:02} ===")).collect();
+            titles == expected
+        }));
+}
+
+#[test]
+fn strict_exits_nonzero_when_files_fail_to_read() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+    write_track(
+        &root.join("song.mp3"),
+        Some("Udio"),
+        None,
+        Some("Song"),
+        &["Words"],
+    );
+
+    assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+        .current_dir(root)
+        .args(["--strict", "--dry-run"])
+        .assert()
+        .success();
+
+    fs::write(root.join("broken.mp3"), b"not an id3 tag").unwrap();
+    assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+        .current_dir(root)
+        .args(["--dry-run"])
+        .assert()
+        .success();
+    assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+        .current_dir(root)
+        .args(["--strict", "--dry-run"])
+        .assert()
+        .code(3)
+        .stderr(contains("1 tag and 0 traversal error(s)"));
+}
+
+#[test]
+fn fail_on_empty_sets_exit_code_when_nothing_matches() {
+    let temp = TempDir::new().unwrap();
+    write_track(
+        &temp.path().join("other.mp3"),
+        Some("Someone Else"),
+        None,
+        Some("Skipped"),
+        &["Nope"],
+    );
+
+    assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+        .arg("--root")
+        .arg(temp.path())
+        .arg("--dry-run")
+        .assert()
+        .success();
+
+    assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+        .arg("--root")
+        .arg(temp.path())
+        .arg("--dry-run")
+        .arg("--fail-on-empty")
+        .assert()
+        .code(2)
+        .stderr(contains("No tracks matched"));
+
+    write_track(
+        &temp.path().join("match.mp3"),
+        Some("Studio Band"),
+        None,
+        Some("Found"),
+        &["Words"],
+    );
+    assert_cmd::cargo::cargo_bin_cmd!("mdlyricgetter")
+        .arg("--root")
+        .arg(temp.path())
+        .arg("--dry-run")
+        .arg("--fail-on-empty")
+        .assert()
+        .success();
+}
+
+#[test]
+fn library_run_returns_report() {
+    let temp = TempDir::new().unwrap();
+    write_track(
+        &temp.path().join("match.mp3"),
+        Some("Studio Band"),
+        None,
+        Some("Embedded"),
+        &["Called from code"],
+    );
+    write_track(
+        &temp.path().join("other.mp3"),
+        Some("Someone Else"),
+        None,
+        Some("Skipped"),
+        &["Nope"],
+    );
+    let output = temp.path().join("lyrics.txt");
+
+    let report = mdlyricgetter::run(mdlyricgetter::Config {
+        roots: vec![temp.path().to_path_buf()],
+        output: output.clone(),
+        ..mdlyricgetter::Config::default()
+    })
+    .expect("run succeeds");
+
+    assert_eq!(report.scanned, 2);
+    assert_eq!(report.matched, 1);
+    assert_eq!(report.skipped_artist, 1);
+    assert!(fs::read_to_string(output)
+        .unwrap()
+        .contains("Called from code"));
 }
 
 fn write_track(
@@ -342,3 +2524,51 @@ fn write_track(
     tag.write_to_path(path, Version::Id3v24).expect("write tag");
     path.to_path_buf()
 }
+
+fn write_m4a(path: &Path, artist: &str, title: &str, lyrics: &str) {
+    fn atom(kind: &[u8], body: &[u8]) -> Vec<u8> {
+        let mut bytes = ((body.len() + 8) as u32).to_be_bytes().to_vec();
+        bytes.extend_from_slice(kind);
+        bytes.extend_from_slice(body);
+        bytes
+    }
+    fn text_item(kind: &[u8], text: &str) -> Vec<u8> {
+        let mut data = vec![0, 0, 0, 1, 0, 0, 0, 0];
+        data.extend_from_slice(text.as_bytes());
+        atom(kind, &atom(b"data", &data))
+    }
+
+    let items = [
+        text_item(b"\xa9ART", artist),
+        text_item(b"\xa9nam", title),
+        text_item(b"\xa9lyr", lyrics),
+    ]
+    .concat();
+    let mut meta = vec![0, 0, 0, 0];
+    meta.extend(atom(b"hdlr", &[0; 25]));
+    meta.extend(atom(b"ilst", &items));
+
+    let mut bytes = atom(b"ftyp", b"M4A \0\0\0\0");
+    bytes.extend(atom(b"moov", &atom(b"udta", &atom(b"meta", &meta))));
+    fs::write(path, bytes).unwrap();
+}
+
+/// Writes a single Ogg page holding a stub identification packet and a comment header.
+fn write_ogg(path: &Path, magic: &[u8], comments: &[&str]) {
+    let mut packet = magic.to_vec();
+    packet.extend_from_slice(&0_u32.to_le_bytes());
+    packet.extend_from_slice(&(comments.len() as u32).to_le_bytes());
+    for comment in comments {
+        packet.extend_from_slice(&(comment.len() as u32).to_le_bytes());
+        packet.extend_from_slice(comment.as_bytes());
+    }
+    assert!(packet.len() < 255, "test packet must fit one lacing value");
+
+    let ident = b"ident";
+    let mut bytes = b"OggS".to_vec();
+    bytes.extend_from_slice(&[0; 22]);
+    bytes.extend_from_slice(&[2, ident.len() as u8, packet.len() as u8]);
+    bytes.extend_from_slice(ident);
+    bytes.extend_from_slice(&packet);
+    fs::write(path, bytes).unwrap();
+}